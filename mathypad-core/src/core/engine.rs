@@ -0,0 +1,367 @@
+//! A headless evaluation engine for using mathypad as a library
+//!
+//! The engine bundles the settings and named values a pad is evaluated with, and
+//! evaluates lines one at a time like the editor does, so later lines can use the
+//! variables and results of earlier ones:
+//!
+//! ```
+//! use mathypad_core::MathypadEngine;
+//!
+//! let mut engine = MathypadEngine::builder()
+//!     .decimal_places(2)
+//!     .rate("eur_rate", "$1.08")
+//!     .build()
+//!     .unwrap();
+//! engine.eval("budget = 1000 * eur_rate");
+//! assert_eq!(engine.eval("budget / 3"), Some("360 $".to_string()));
+//! ```
+
+use super::project::Project;
+use super::state::{MathypadCore, is_variable_name};
+use crate::expression::evaluate_expression_value_with_context;
+use crate::settings::{
+    AdditionUnitPolicy, DEFAULT_DECIMAL_PLACES, DEFAULT_INTEGER_TOLERANCE_ULPS, Notation,
+    UnitFamily, addition_unit_policy, assume_bytes, decimal_comma, decimal_places,
+    integer_tolerance_ulps, notation, set_addition_unit_policy, set_assume_bytes,
+    set_decimal_comma, set_decimal_places, set_integer_tolerance_ulps, set_notation,
+    set_strict_expressions, set_unit_family_enabled, strict_expressions, unit_family_enabled,
+};
+
+/// The settings an engine evaluates with
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EngineSettings {
+    notation: Notation,
+    addition_unit_policy: AdditionUnitPolicy,
+    strict_expressions: bool,
+    decimal_comma: bool,
+    assume_bytes: bool,
+    decimal_places: usize,
+    integer_tolerance_ulps: f64,
+    unit_families: [bool; UnitFamily::ALL.len()],
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            notation: Notation::default(),
+            addition_unit_policy: AdditionUnitPolicy::default(),
+            strict_expressions: false,
+            decimal_comma: false,
+            assume_bytes: false,
+            decimal_places: DEFAULT_DECIMAL_PLACES,
+            integer_tolerance_ulps: DEFAULT_INTEGER_TOLERANCE_ULPS,
+            unit_families: [true; UnitFamily::ALL.len()],
+        }
+    }
+}
+
+impl EngineSettings {
+    /// The settings the current thread evaluates with
+    fn current() -> Self {
+        Self {
+            notation: notation(),
+            addition_unit_policy: addition_unit_policy(),
+            strict_expressions: strict_expressions(),
+            decimal_comma: decimal_comma(),
+            assume_bytes: assume_bytes(),
+            decimal_places: decimal_places(),
+            integer_tolerance_ulps: integer_tolerance_ulps(),
+            unit_families: UnitFamily::ALL.map(unit_family_enabled),
+        }
+    }
+
+    /// Make the current thread evaluate with these settings
+    fn apply(&self) {
+        set_notation(self.notation);
+        set_addition_unit_policy(self.addition_unit_policy);
+        set_strict_expressions(self.strict_expressions);
+        set_decimal_comma(self.decimal_comma);
+        set_assume_bytes(self.assume_bytes);
+        set_decimal_places(self.decimal_places);
+        set_integer_tolerance_ulps(self.integer_tolerance_ulps);
+        for (family, enabled) in UnitFamily::ALL.into_iter().zip(self.unit_families) {
+            set_unit_family_enabled(family, enabled);
+        }
+    }
+
+    /// Run `f` with these settings, then put the thread's own settings back
+    ///
+    /// They are put back even if `f` panics.
+    fn while_applied<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _restore = RestoreSettings(Self::current());
+        self.apply();
+        f()
+    }
+}
+
+/// Applies the settings it holds when dropped
+struct RestoreSettings(EngineSettings);
+
+impl Drop for RestoreSettings {
+    fn drop(&mut self) {
+        self.0.apply();
+    }
+}
+
+/// Builds a [`MathypadEngine`]
+///
+/// Settings start at their defaults, whatever the editor on this thread uses.
+#[derive(Debug, Clone, Default)]
+pub struct EngineBuilder {
+    settings: EngineSettings,
+    /// Rates and variables as (name, expression), evaluated when the engine is built
+    values: Vec<(String, String)>,
+    project: Option<Project>,
+}
+
+impl EngineBuilder {
+    /// Write results as grouped digits or with exponents
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.settings.notation = notation;
+        self
+    }
+
+    /// Which unit to keep when adding values with compatible units
+    pub fn addition_unit_policy(mut self, policy: AdditionUnitPolicy) -> Self {
+        self.settings.addition_unit_policy = policy;
+        self
+    }
+
+    /// Only evaluate lines that are entirely an expression
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.settings.strict_expressions = enabled;
+        self
+    }
+
+    /// Read and write numbers in European style, like "3,14"
+    pub fn decimal_comma(mut self, enabled: bool) -> Self {
+        self.settings.decimal_comma = enabled;
+        self
+    }
+
+    /// Read large bare numbers as byte counts
+    pub fn assume_bytes(mut self, enabled: bool) -> Self {
+        self.settings.assume_bytes = enabled;
+        self
+    }
+
+    /// Round results to this many decimal places (3 by default)
+    pub fn decimal_places(mut self, places: usize) -> Self {
+        self.settings.decimal_places = places;
+        self
+    }
+
+    /// How many `FLOAT_EPSILON`s a result may be away from an integer and still be
+    /// treated as one (0 turns this off)
+    pub fn integer_tolerance_ulps(mut self, ulps: f64) -> Self {
+        self.settings.integer_tolerance_ulps = ulps;
+        self
+    }
+
+    /// Turn a unit family on or off, so e.g. "min" isn't read as minutes
+    pub fn unit_family(mut self, family: UnitFamily, enabled: bool) -> Self {
+        self.settings.unit_families[family as usize] = enabled;
+        self
+    }
+
+    /// Define a rate like `("eur_rate", "$1.08")` that every line can use
+    pub fn rate(self, name: &str, value: &str) -> Self {
+        self.variable(name, value)
+    }
+
+    /// Define a variable like `("ram", "64 GiB")` that every line can use
+    pub fn variable(mut self, name: &str, value: &str) -> Self {
+        self.values.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Use a project file's settings and values
+    ///
+    /// The project is applied on top of the other settings, and values defined on
+    /// the builder win over the project's.
+    pub fn project(mut self, project: Project) -> Self {
+        self.project = Some(project);
+        self
+    }
+
+    /// Create the engine, evaluating its rates and variables
+    ///
+    /// Fails with a message naming the value if a name isn't a valid variable name
+    /// or a value doesn't evaluate on its own.
+    pub fn build(self) -> Result<MathypadEngine, String> {
+        let mut settings = self.settings;
+        let mut core = MathypadCore::new();
+
+        if let Some(project) = &self.project {
+            settings = settings.while_applied(|| {
+                project.apply(&mut core);
+                EngineSettings::current()
+            });
+        }
+
+        settings.while_applied(|| {
+            for (name, value) in &self.values {
+                if !is_variable_name(name) {
+                    return Err(format!("invalid name '{}'", name));
+                }
+                let value = evaluate_expression_value_with_context(value, &[], 0)
+                    .ok_or_else(|| format!("can't evaluate '{}' for '{}'", value, name))?;
+                core.pinned.insert(name.clone(), value);
+            }
+            core.reset_variables();
+            Ok(())
+        })?;
+
+        Ok(MathypadEngine {
+            core,
+            settings,
+            line_count: 0,
+        })
+    }
+}
+
+/// Evaluates lines one after another with its own settings and named values
+///
+/// Each line sees the variables assigned and results computed by the lines before
+/// it, and can refer to them as "line1", "prev" and so on. The engine's settings
+/// only apply while it evaluates, so it doesn't change other evaluations on the
+/// same thread.
+#[derive(Debug, Clone)]
+pub struct MathypadEngine {
+    core: MathypadCore,
+    settings: EngineSettings,
+    /// Number of lines evaluated so far
+    line_count: usize,
+}
+
+impl MathypadEngine {
+    /// Start configuring an engine
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    /// Evaluate the next line, returning its result
+    pub fn eval(&mut self, line: &str) -> Option<String> {
+        let line_index = self.line_count;
+        if line_index == 0 {
+            // A new core starts with one empty line
+            self.core.text_lines[0] = line.to_string();
+        } else {
+            self.core.text_lines.push(line.to_string());
+            self.core.results.push(None);
+        }
+        self.line_count += 1;
+
+        let core = &mut self.core;
+        self.settings
+            .while_applied(|| core.update_result(line_index));
+        self.core.results[line_index].clone()
+    }
+
+    /// The lines evaluated so far and their results
+    pub fn core(&self) -> &MathypadCore {
+        &self.core
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_evaluates_dependent_lines() {
+        let mut engine = MathypadEngine::builder()
+            .decimal_places(1)
+            .rate("eur_rate", "$1.08")
+            .variable("servers", "12")
+            .build()
+            .unwrap();
+
+        assert_eq!(engine.eval("Capacity plan"), None);
+        assert_eq!(
+            engine.eval("ram = servers * 64 GiB"),
+            Some("768 GiB".to_string())
+        );
+        assert_eq!(engine.eval("ram to TiB"), Some("0.8 TiB".to_string()));
+        assert_eq!(
+            engine.eval("cost = 999 * eur_rate"),
+            Some("1,078.9 $".to_string())
+        );
+        assert_eq!(engine.eval("cost / servers"), Some("89.9 $".to_string()));
+        assert_eq!(engine.eval("line2 * 2"), Some("1,536 GiB".to_string()));
+        assert_eq!(engine.core().text_lines.len(), 6);
+
+        // The engine's precision doesn't leak into other evaluations
+        assert_eq!(decimal_places(), DEFAULT_DECIMAL_PLACES);
+    }
+
+    #[test]
+    fn test_engine_build_errors() {
+        let error = |builder: EngineBuilder| builder.build().unwrap_err();
+
+        assert_eq!(
+            error(MathypadEngine::builder().rate("2fast", "$1")),
+            "invalid name '2fast'"
+        );
+        assert_eq!(
+            error(MathypadEngine::builder().variable("ram", "lots")),
+            "can't evaluate 'lots' for 'ram'"
+        );
+    }
+
+    #[test]
+    fn test_engine_integer_tolerance() {
+        let mut engine = MathypadEngine::builder()
+            .decimal_places(16)
+            .integer_tolerance_ulps(0.0)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            engine.eval("(0.1 + 0.2) * 10"),
+            Some("3.0000000000000004".to_string())
+        );
+        assert_eq!(integer_tolerance_ulps(), DEFAULT_INTEGER_TOLERANCE_ULPS);
+    }
+
+    #[test]
+    fn test_settings_are_put_back_after_a_panic() {
+        let settings = EngineSettings {
+            decimal_places: 1,
+            ..EngineSettings::default()
+        };
+        let panicked = std::panic::catch_unwind(|| settings.while_applied(|| panic!("oops")));
+
+        assert!(panicked.is_err());
+        assert_eq!(decimal_places(), DEFAULT_DECIMAL_PLACES);
+    }
+
+    #[test]
+    fn test_engine_unit_families() {
+        let mut engine = MathypadEngine::builder()
+            .unit_family(UnitFamily::Pressure, false)
+            .build()
+            .unwrap();
+
+        // "bar" is free to be a variable
+        assert_eq!(engine.eval("bar = 2"), Some("2".to_string()));
+        assert_eq!(engine.eval("bar * 3"), Some("6".to_string()));
+        assert_eq!(engine.eval("2 GiB * 2"), Some("4 GiB".to_string()));
+        assert!(unit_family_enabled(UnitFamily::Pressure));
+    }
+
+    #[test]
+    fn test_engine_uses_project_settings() {
+        let project =
+            Project::from_config("[settings]\nnotation = eng\n[rates]\nrate = 2").unwrap();
+        let mut engine = MathypadEngine::builder().project(project).build().unwrap();
+
+        assert_eq!(engine.eval("rate * 1500"), Some("3e3".to_string()));
+        assert_eq!(notation(), Notation::Plain);
+
+        let project = Project::from_config("[settings]\nprecision = 1").unwrap();
+        let mut engine = MathypadEngine::builder().project(project).build().unwrap();
+        assert_eq!(engine.eval("2 / 3"), Some("0.7".to_string()));
+        assert_eq!(decimal_places(), DEFAULT_DECIMAL_PLACES);
+    }
+}
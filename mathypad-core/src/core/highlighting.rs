@@ -1,5 +1,6 @@
 //! UI-agnostic syntax highlighting for mathematical expressions
 
+use super::theme::Theme;
 use crate::expression::parser::parse_line_reference;
 use crate::units::parse_unit;
 use std::collections::HashMap;
@@ -20,9 +21,9 @@ pub enum HighlightType {
     Unit,
     /// Line references (e.g., "line1", "line2")
     LineReference,
-    /// Keywords (e.g., "to", "in", "of")
+    /// Keywords (e.g., "to", "in", "of", "as")
     Keyword,
-    /// Mathematical operators (e.g., "+", "-", "*", "/", "^", "=")
+    /// Mathematical operators (e.g., "+", "-", "*", "/", "^", "=", ">")
     Operator,
     /// Variable names that are defined
     Variable,
@@ -33,19 +34,10 @@ pub enum HighlightType {
 }
 
 impl HighlightType {
-    /// Get the standard RGB color values for this highlight type
+    /// Get the standard RGB color values for this highlight type (from the default theme)
     /// Returns (red, green, blue) as u8 values
     pub fn rgb_color(&self) -> (u8, u8, u8) {
-        match self {
-            HighlightType::Number => (65, 105, 225), // Royal blue - good contrast on both backgrounds
-            HighlightType::Unit => (34, 139, 34), // Forest green - good contrast on both backgrounds
-            HighlightType::LineReference => (255, 140, 0), // Dark orange - good contrast on both backgrounds
-            HighlightType::Keyword => (178, 34, 34), // Fire brick red - good contrast on both backgrounds
-            HighlightType::Operator => (128, 0, 128), // Purple - good contrast on both backgrounds
-            HighlightType::Variable => (72, 61, 139), // Dark slate blue - good contrast on both backgrounds
-            HighlightType::Function => (138, 43, 226), // Blue violet - good contrast on both backgrounds
-            HighlightType::Normal => (105, 105, 105), // Dim gray - good contrast on both backgrounds
-        }
+        Theme::default().color(self)
     }
 }
 
@@ -59,9 +51,11 @@ pub fn highlight_expression(
     let chars: Vec<char> = text.chars().collect();
 
     while current_pos < chars.len() {
-        if chars[current_pos].is_ascii_alphabetic() {
+        if chars[current_pos].is_ascii_alphabetic() || matches!(chars[current_pos], 'µ' | 'μ') {
             // Handle potential units, keywords, and line references first
+            // (a leading micro sign is part of unit names like "µs")
             let start_pos = current_pos;
+            current_pos += 1;
 
             while current_pos < chars.len()
                 && (chars[current_pos].is_ascii_alphabetic()
@@ -71,20 +65,47 @@ pub fn highlight_expression(
                 current_pos += 1;
             }
 
-            let word_text: String = chars[start_pos..current_pos].iter().collect();
+            let mut word_text: String = chars[start_pos..current_pos].iter().collect();
 
             // Check if it's a valid unit, keyword, line reference, function, or variable
             let highlight_type = if parse_line_reference(&word_text).is_some() {
+                // "lineN.value" is highlighted as one line reference
+                let accessor: String = chars[current_pos..].iter().take(7).collect();
+                if accessor.starts_with(".value")
+                    && !accessor[6..]
+                        .chars()
+                        .any(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    word_text.push_str(".value");
+                    current_pos += 6;
+                }
+                HighlightType::LineReference
+            } else if word_text.eq_ignore_ascii_case("prev")
+                || word_text.eq_ignore_ascii_case("ans")
+            {
                 HighlightType::LineReference
             } else if word_text.to_lowercase() == "to"
                 || word_text.to_lowercase() == "in"
                 || word_text.to_lowercase() == "of"
+                || word_text.to_lowercase() == "as"
+                || word_text.to_lowercase() == "mod"
+                || (word_text.eq_ignore_ascii_case("out") && precedes_of(&chars[current_pos..]))
             {
                 HighlightType::Keyword
-            } else if word_text.to_lowercase() == "sqrt" || word_text.to_lowercase() == "sum_above"
+            } else if word_text.to_lowercase() == "sqrt"
+                || word_text.to_lowercase() == "sum_above"
+                || word_text.to_lowercase() == "typeof"
+                || word_text.to_lowercase() == "nextpow2"
+                || word_text.to_lowercase() == "prevpow2"
+                || word_text.to_lowercase() == "humanize"
+                || word_text.to_lowercase() == "humanize10"
+                // "count" is a common word, so only as a call
+                || (word_text.eq_ignore_ascii_case("count") && chars.get(current_pos) == Some(&'('))
             {
                 HighlightType::Function
-            } else if parse_unit(&word_text).is_some() {
+            } else if parse_unit(&word_text).is_some()
+                || (word_text == "d" && follows_number(&spans))
+            {
                 HighlightType::Unit
             } else if variables.contains_key(&word_text) {
                 HighlightType::Variable
@@ -112,6 +133,28 @@ pub fn highlight_expression(
                     current_pos += 1;
                 } else if ch == ',' {
                     current_pos += 1;
+                } else if ch == '_'
+                    && has_digit
+                    && chars
+                        .get(current_pos + 1)
+                        .is_some_and(|c| c.is_ascii_digit())
+                {
+                    // Underscore digit grouping like 1_000_000
+                    current_pos += 1;
+                } else if matches!(ch, 'e' | 'E') && has_digit {
+                    // Scientific notation exponent like 1e3 or 2.5E-6
+                    let digits_at = match chars.get(current_pos + 1) {
+                        Some('+' | '-') => current_pos + 2,
+                        _ => current_pos + 1,
+                    };
+                    if !chars.get(digits_at).is_some_and(|c| c.is_ascii_digit()) {
+                        break;
+                    }
+                    current_pos = digits_at;
+                    while chars.get(current_pos).is_some_and(|c| c.is_ascii_digit()) {
+                        current_pos += 1;
+                    }
+                    break;
                 } else {
                     break;
                 }
@@ -145,8 +188,8 @@ pub fn highlight_expression(
                 highlight_type: HighlightType::Unit,
             });
             current_pos += 1;
-        } else if "+-*/()=^".contains(chars[current_pos]) {
-            // Handle operators (including assignment and exponentiation)
+        } else if "+-*/()=^<>×÷−".contains(chars[current_pos]) {
+            // Handle operators (including assignment, exponentiation and comparisons)
             spans.push(HighlightedSpan {
                 text: chars[current_pos].to_string(),
                 highlight_type: HighlightType::Operator,
@@ -178,6 +221,29 @@ pub fn highlight_expression_with_cursor(
     (spans, cursor_col)
 }
 
+/// Check whether the last non-blank span is a number, so "d" after it means days
+fn follows_number(spans: &[HighlightedSpan]) -> bool {
+    spans
+        .iter()
+        .rev()
+        .find(|span| !span.text.trim().is_empty())
+        .is_some_and(|span| span.highlight_type == HighlightType::Number)
+}
+
+/// Check if the text after a word starts with " of", as in "out of"
+fn precedes_of(rest: &[char]) -> bool {
+    let rest: String = rest.iter().collect();
+    let Some(after_space) = rest.strip_prefix(' ') else {
+        return false;
+    };
+    let word: String = after_space
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    word.eq_ignore_ascii_case("of")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -192,6 +258,20 @@ mod tests {
         assert_eq!(spans[0].highlight_type, HighlightType::Number);
     }
 
+    #[test]
+    fn test_exponent_highlighting() {
+        let variables = HashMap::new();
+        let spans = highlight_expression("2.5e-3", &variables);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].highlight_type, HighlightType::Number);
+
+        // Without digits after it, the "e" starts a unit
+        let spans = highlight_expression("1EB", &variables);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].text, "1");
+        assert_eq!(spans[1].highlight_type, HighlightType::Unit);
+    }
+
     #[test]
     fn test_operator_highlighting() {
         let variables = HashMap::new();
@@ -203,6 +283,12 @@ mod tests {
         assert_eq!(spans[2].highlight_type, HighlightType::Operator);
         assert_eq!(spans[3].highlight_type, HighlightType::Normal); // space
         assert_eq!(spans[4].highlight_type, HighlightType::Number);
+
+        // Unicode operators are highlighted like their ASCII equivalents
+        for expression in ["2 × 3", "10 ÷ 2", "5 − 2"] {
+            let spans = highlight_expression(expression, &variables);
+            assert_eq!(spans[2].highlight_type, HighlightType::Operator);
+        }
     }
 
     #[test]
@@ -227,6 +313,26 @@ mod tests {
                 .any(|s| s.highlight_type == HighlightType::LineReference)
         );
         assert!(spans.iter().any(|s| s.text == "line1"));
+
+        let spans = highlight_expression("line2.value * 2", &variables);
+        assert_eq!(spans[0].text, "line2.value");
+        assert_eq!(spans[0].highlight_type, HighlightType::LineReference);
+    }
+
+    #[test]
+    fn test_day_suffix_highlighting() {
+        let variables = HashMap::new();
+        let spans = highlight_expression("5d + 2 d", &variables);
+        let d_spans: Vec<_> = spans.iter().filter(|s| s.text == "d").collect();
+        assert_eq!(d_spans.len(), 2);
+        assert!(
+            d_spans
+                .iter()
+                .all(|s| s.highlight_type == HighlightType::Unit)
+        );
+
+        let spans = highlight_expression("d + 1", &variables);
+        assert_eq!(spans[0].highlight_type, HighlightType::Normal);
     }
 
     #[test]
@@ -255,6 +361,13 @@ mod tests {
                 .any(|s| s.highlight_type == HighlightType::Keyword)
         );
         assert!(spans.iter().any(|s| s.text == "to"));
+
+        let spans = highlight_expression("750 GiB out of 1 TiB", &variables);
+        let out = spans.iter().find(|s| s.text == "out").unwrap();
+        assert_eq!(out.highlight_type, HighlightType::Keyword);
+        let spans = highlight_expression("out often", &variables);
+        let out = spans.iter().find(|s| s.text == "out").unwrap();
+        assert_eq!(out.highlight_type, HighlightType::Normal);
     }
 
     #[test]
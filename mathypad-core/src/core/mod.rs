@@ -1,9 +1,17 @@
 //! Core abstractions for shared application state and logic
 
+pub mod engine;
 pub mod file_ops;
 pub mod highlighting;
+pub mod project;
+pub mod results;
 pub mod state;
+pub mod theme;
 
+pub use engine::{EngineBuilder, MathypadEngine};
 pub use file_ops::{FileOperations, deserialize_lines, serialize_lines};
 pub use highlighting::{HighlightType, HighlightedSpan, highlight_expression};
-pub use state::MathypadCore;
+pub use project::Project;
+pub use results::{RenderedResult, ResultStyle, error_message};
+pub use state::{LineReferenceStatus, MathypadCore};
+pub use theme::{BUILTIN_THEMES, Theme};
@@ -0,0 +1,287 @@
+//! Project files that set up the evaluation environment for a pad
+//!
+//! A project file holds named values and settings in a small subset of TOML, so a
+//! pad evaluates the same way wherever it is opened:
+//!
+//! ```toml
+//! [settings]
+//! notation = "eng"        # plain, scientific or engineering
+//! precision = 2           # decimal places in results
+//! addunit = "left"        # smaller, left or larger
+//! strict = true
+//! decimal_comma = false
+//! assume_bytes = false
+//!
+//! [rates]
+//! eur_rate = "$1.08"
+//!
+//! [variables]
+//! servers = 12
+//! ram = "64 GiB"
+//! ```
+//!
+//! Rates and variables are both pinned values: they can be used like variables on
+//! every line and don't change while editing.
+
+use super::state::{MathypadCore, is_variable_name};
+use crate::UnitValue;
+use crate::expression::{evaluate_expression_value_with_context, strip_comment};
+use crate::settings::{
+    AdditionUnitPolicy, Notation, addition_unit_policy, assume_bytes, decimal_comma,
+    decimal_places, notation, set_addition_unit_policy, set_assume_bytes, set_decimal_comma,
+    set_decimal_places, set_notation, set_strict_expressions, strict_expressions,
+};
+
+/// Named values and settings read from a project file
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// Values defined under [rates] and [variables], in file order
+    pub values: Vec<(String, UnitValue)>,
+    pub notation: Option<Notation>,
+    pub decimal_places: Option<usize>,
+    pub addition_unit_policy: Option<AdditionUnitPolicy>,
+    pub strict_expressions: Option<bool>,
+    pub decimal_comma: Option<bool>,
+    pub assume_bytes: Option<bool>,
+}
+
+impl Project {
+    /// Parse a project file
+    ///
+    /// Every key must be in a [settings], [rates] or [variables] section. Values may
+    /// be quoted, and rates and variables can be any expression that evaluates on its
+    /// own, like "64 GiB" or "$1.08". Blank lines and '#' comments are skipped.
+    pub fn from_config(config: &str) -> Result<Self, String> {
+        let mut project = Project::default();
+        let mut section = None;
+
+        for (line_number, line) in config.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", line_number + 1, message);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                match name.trim() {
+                    name @ ("settings" | "rates" | "variables") => section = Some(name),
+                    name => return Err(error(format!("unknown section '{}'", name))),
+                }
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error("expected 'key = value'".to_string()));
+            };
+            let key = key.trim();
+            let value = unquote(value.trim());
+
+            match section {
+                Some("settings") => project.set(key, value).map_err(error)?,
+                Some(_) => {
+                    if !is_variable_name(key) {
+                        return Err(error(format!("invalid name '{}'", key)));
+                    }
+                    let value = evaluate_expression_value_with_context(value, &[], 0)
+                        .ok_or_else(|| error(format!("can't evaluate '{}'", value)))?;
+                    project.values.push((key.to_string(), value));
+                }
+                None => {
+                    return Err(error(
+                        "expected a [section] before the first key".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(project)
+    }
+
+    /// Apply one [settings] entry
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value '{}' for '{}'", value, key);
+        let flag = || match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(invalid()),
+        };
+
+        match key {
+            "notation" => self.notation = Some(Notation::from_name(value).ok_or_else(invalid)?),
+            "precision" => self.decimal_places = Some(value.parse().map_err(|_| invalid())?),
+            "addunit" => {
+                self.addition_unit_policy =
+                    Some(AdditionUnitPolicy::from_name(value).ok_or_else(invalid)?)
+            }
+            "strict" => self.strict_expressions = Some(flag()?),
+            "decimal_comma" => self.decimal_comma = Some(flag()?),
+            "assume_bytes" => self.assume_bytes = Some(flag()?),
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+    /// Use the project's settings and values, then recalculate every line
+    ///
+    /// Settings the project doesn't mention are left as they are.
+    pub fn apply(&self, core: &mut MathypadCore) {
+        self.apply_settings();
+        self.pin_values(core);
+        core.recalculate_all();
+    }
+
+    /// Use the project's settings, returning a project with the settings they replaced
+    ///
+    /// Settings are shared by everything evaluated on the thread, so an editor with
+    /// several pads applies the returned project to put them back when switching to
+    /// a pad from elsewhere. Settings the project doesn't mention are left as they are.
+    pub fn apply_settings(&self) -> Project {
+        let replaced = Project {
+            values: Vec::new(),
+            notation: self.notation.map(|_| notation()),
+            decimal_places: self.decimal_places.map(|_| decimal_places()),
+            addition_unit_policy: self.addition_unit_policy.map(|_| addition_unit_policy()),
+            strict_expressions: self.strict_expressions.map(|_| strict_expressions()),
+            decimal_comma: self.decimal_comma.map(|_| decimal_comma()),
+            assume_bytes: self.assume_bytes.map(|_| assume_bytes()),
+        };
+
+        if let Some(notation) = self.notation {
+            set_notation(notation);
+        }
+        if let Some(places) = self.decimal_places {
+            set_decimal_places(places);
+        }
+        if let Some(policy) = self.addition_unit_policy {
+            set_addition_unit_policy(policy);
+        }
+        if let Some(enabled) = self.strict_expressions {
+            set_strict_expressions(enabled);
+        }
+        if let Some(enabled) = self.decimal_comma {
+            set_decimal_comma(enabled);
+        }
+        if let Some(enabled) = self.assume_bytes {
+            set_assume_bytes(enabled);
+        }
+        replaced
+    }
+
+    /// Make the project's rates and variables usable on every line of a pad
+    pub fn pin_values(&self, core: &mut MathypadCore) {
+        for (name, value) in &self.values {
+            core.pinned.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Remove the quotes around a string value
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::notation;
+
+    #[test]
+    fn test_project_settings_and_values_take_effect() {
+        let config = r#"
+# Shared setup for the capacity plan
+[settings]
+notation = "eng"
+addunit = left
+
+[rates]
+eur_rate = "$1.08"   # dollars per euro
+
+[variables]
+servers = 12
+ram = "64 GiB"
+"#;
+        let project = Project::from_config(config).unwrap();
+        assert_eq!(project.notation, Some(Notation::Engineering));
+        assert_eq!(project.decimal_places, None);
+        assert_eq!(project.addition_unit_policy, Some(AdditionUnitPolicy::Left));
+        assert_eq!(project.values.len(), 3);
+
+        let mut core = MathypadCore::from_lines(vec![
+            "servers * ram".to_string(),
+            "1000 * eur_rate".to_string(),
+            "1 TB + 500 GB".to_string(),
+        ]);
+        assert_eq!(core.results[0], None);
+
+        project.apply(&mut core);
+        assert_eq!(core.results[0], Some("768e0 GiB".to_string()));
+        assert_eq!(core.results[1], Some("1.08e3 $".to_string()));
+        assert_eq!(core.results[2], Some("1.5e0 TB".to_string()));
+        assert_eq!(notation(), Notation::Engineering);
+
+        set_notation(Notation::Plain);
+        set_addition_unit_policy(AdditionUnitPolicy::Smaller);
+    }
+
+    #[test]
+    fn test_project_settings_can_be_put_back() {
+        set_strict_expressions(true);
+        let project = Project::from_config(
+            "[settings]
+notation = sci
+precision = 1
+strict = false",
+        )
+        .unwrap();
+
+        let replaced = project.apply_settings();
+        assert_eq!(notation(), Notation::Scientific);
+        assert_eq!(decimal_places(), 1);
+        assert!(!strict_expressions());
+        // Only the settings the project changes are put back
+        assert_eq!(replaced.notation, Some(Notation::Plain));
+        assert_eq!(replaced.decimal_places, Some(3));
+        assert_eq!(replaced.strict_expressions, Some(true));
+        assert_eq!(replaced.decimal_comma, None);
+
+        replaced.apply_settings();
+        assert_eq!(notation(), Notation::Plain);
+        assert_eq!(decimal_places(), 3);
+        assert!(strict_expressions());
+        set_strict_expressions(false);
+    }
+
+    #[test]
+    fn test_project_errors_name_the_line() {
+        let error = |config: &str| Project::from_config(config).unwrap_err();
+
+        assert_eq!(
+            error("notation = eng"),
+            "line 1: expected a [section] before the first key"
+        );
+        assert_eq!(error("[units]"), "line 1: unknown section 'units'");
+        assert_eq!(
+            error("[settings]\nprecison = 2"),
+            "line 2: unknown setting 'precison'"
+        );
+        assert_eq!(
+            error("[settings]\nprecision = -1"),
+            "line 2: invalid value '-1' for 'precision'"
+        );
+        assert_eq!(
+            error("[settings]\nstrict = yes"),
+            "line 2: invalid value 'yes' for 'strict'"
+        );
+        assert_eq!(
+            error("[variables]\n2fast = 1"),
+            "line 2: invalid name '2fast'"
+        );
+        assert_eq!(
+            error("[rates]\neur = hello"),
+            "line 2: can't evaluate 'hello'"
+        );
+        assert_eq!(error("[rates]\neur"), "line 2: expected 'key = value'");
+    }
+}
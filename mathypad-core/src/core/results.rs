@@ -0,0 +1,115 @@
+//! UI-agnostic layout of the results panel
+//!
+//! Editor integrations can use this to show results the way the TUI does without
+//! re-implementing its rules for values, errors and hints.
+
+use super::state::MathypadCore;
+use crate::expression::{EvaluationError, ParenMismatch};
+use crate::units::find_unit_suggestion;
+
+/// Width of the line number gutter in front of every result ("{:4} ")
+pub const RESULT_COLUMN: usize = 5;
+
+/// What a piece of text in the results panel is, so a UI can pick its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultStyle {
+    /// A computed value (green)
+    Value,
+    /// Why the line has no value, like a failed unit assertion (red)
+    Error,
+    /// A suggestion for a likely typo, like "did you mean seconds?" (gray)
+    Hint,
+}
+
+/// The text shown in the results panel for one line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedResult {
+    /// Index of the line in the pad, starting at 0
+    pub line_index: usize,
+    pub text: String,
+    pub style: ResultStyle,
+    /// Column the text starts at, after the line number gutter
+    pub column: usize,
+}
+
+impl MathypadCore {
+    /// The results panel text for a line, or None if the panel shows nothing for it
+    pub fn rendered_result(&self, line_index: usize) -> Option<RenderedResult> {
+        let (text, style) = if let Some(value) = self.results.get(line_index)?.clone() {
+            (value, ResultStyle::Value)
+        } else if let Some(message) = self
+            .line_error(line_index)
+            .and_then(|error| error_message(&error))
+        {
+            (message, ResultStyle::Error)
+        } else {
+            let (_, suggestion) = find_unit_suggestion(self.text_lines.get(line_index)?)?;
+            (format!("did you mean {}?", suggestion), ResultStyle::Hint)
+        };
+
+        Some(RenderedResult {
+            line_index,
+            text,
+            style,
+            column: RESULT_COLUMN,
+        })
+    }
+
+    /// The results panel text for every line that shows something, in line order
+    pub fn rendered_results(&self) -> Vec<RenderedResult> {
+        (0..self.results.len())
+            .filter_map(|line_index| self.rendered_result(line_index))
+            .collect()
+    }
+}
+
+/// Short text shown in the results panel for a line error, if it gets one
+pub fn error_message(error: &EvaluationError) -> Option<String> {
+    match error {
+        // Flag a line whose "... :: unit" assertion doesn't hold
+        EvaluationError::UnitAssertionFailed(unit) => {
+            Some(format!("expected {}", unit.display_name()))
+        }
+        EvaluationError::UnbalancedParenthesis(ParenMismatch::UnclosedOpen(_)) => {
+            Some("unclosed '('".to_string())
+        }
+        EvaluationError::UnbalancedParenthesis(ParenMismatch::UnmatchedClose(_)) => {
+            Some("unmatched ')'".to_string())
+        }
+        EvaluationError::CircularReference => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rendered_results_for_a_small_pad() {
+        let core = MathypadCore::from_lines(vec![
+            "Budget".to_string(),
+            "5 GiB + 3 GiB".to_string(),
+            "(2 + 3".to_string(),
+            "1 hour :: GiB".to_string(),
+            "2 secnod".to_string(),
+        ]);
+
+        let rendered = core.rendered_results();
+        let summary: Vec<_> = rendered
+            .iter()
+            .map(|r| (r.line_index, r.text.as_str(), r.style))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "8 GiB", ResultStyle::Value),
+                (2, "unclosed '('", ResultStyle::Error),
+                (3, "expected GiB", ResultStyle::Error),
+                (4, "did you mean second?", ResultStyle::Hint),
+            ]
+        );
+        assert!(rendered.iter().all(|r| r.column == RESULT_COLUMN));
+        assert_eq!(core.rendered_result(0), None);
+        assert_eq!(core.rendered_result(5), None);
+    }
+}
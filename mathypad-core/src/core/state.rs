@@ -1,7 +1,19 @@
 //! Core application state shared between TUI and web UI
 
-use crate::expression::{evaluate_with_variables, update_line_references_in_text};
-use std::collections::HashMap;
+use crate::expression::{
+    EvaluationError, Token, evaluate_with_variables, evaluate_with_variables_using,
+    find_circular_assignment_lines_using, paren_mismatch_in, parse_result_string,
+    remap_line_references_in_text, split_trailing_conversion, split_unit_assertion, strip_comment,
+    tokenize_with_units, update_line_references_in_text,
+};
+use crate::settings::{
+    UnitFamily, assume_bytes, decimal_comma, swap_decimal_separators, unit_family_enabled,
+};
+use crate::units::{format_number_with_commas, parse_unit};
+use crate::{Unit, UnitValue};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 /// Core application state containing text, results, and variables
 /// This is UI-agnostic and can be used by both TUI and web implementations
@@ -17,6 +29,129 @@ pub struct MathypadCore {
     pub results: Vec<Option<String>>,
     /// Variable storage (variable_name -> value_string)
     pub variables: HashMap<String, String>,
+    /// Pinned results (label -> value), frozen when pinned and usable as variables
+    pub pinned: HashMap<String, UnitValue>,
+    /// Units pinned to lines (line index -> unit); their results are shown in that unit
+    pub display_units: HashMap<usize, Unit>,
+    /// Tokens of the expressions on each line, reused while their text is unchanged
+    token_cache: TokenCache,
+    /// Assignment lines that are part of a circular variable reference
+    circular_lines: HashSet<usize>,
+}
+
+/// What a "lineN" reference on some line points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineReferenceStatus {
+    /// A line above with a result, so the reference has a value
+    Resolved,
+    /// A line above without a result
+    NoResult,
+    /// The referencing line itself or a line below it, which can't be used
+    Forward,
+    /// A line past the end of the pad
+    Missing,
+}
+
+/// Tokens read from each line, keyed by the expression without its trailing conversion
+///
+/// Changing only the "to <unit>" at the end of a line keeps its tokens, and any other
+/// edit to the line drops them.
+#[derive(Debug, Clone, Default)]
+struct TokenCache {
+    /// The decimal comma, assume bytes and unit family settings the tokens were read with
+    settings: (bool, bool, [bool; UnitFamily::ALL.len()]),
+    lines: HashMap<usize, HashMap<String, Option<Vec<Token>>>>,
+}
+
+impl TokenCache {
+    /// Take the cached tokens for a line, dropping everything if the settings changed
+    fn take_line(&mut self, line_index: usize) -> HashMap<String, Option<Vec<Token>>> {
+        let settings = (
+            decimal_comma(),
+            assume_bytes(),
+            UnitFamily::ALL.map(unit_family_enabled),
+        );
+        if self.settings != settings {
+            self.settings = settings;
+            self.lines.clear();
+        }
+        self.lines.remove(&line_index).unwrap_or_default()
+    }
+
+    /// Tokens for an expression on a line, kept with the line's other tokens so
+    /// evaluating the line doesn't read them again
+    fn tokens(&mut self, line_index: usize, expression: &str) -> Option<Vec<Token>> {
+        let mut line = self.take_line(line_index);
+        let mut used = HashMap::new();
+        let tokens = cached_tokens(expression, &line, &mut used);
+        line.extend(used);
+        self.lines.insert(line_index, line);
+        tokens
+    }
+}
+
+/// Tokenize an expression, reusing the tokens from `cached` if it was read before
+///
+/// The tokens used are recorded in `used`, and a trailing conversion is added as
+/// tokens without parsing it.
+fn cached_tokens(
+    expression: &str,
+    cached: &HashMap<String, Option<Vec<Token>>>,
+    used: &mut HashMap<String, Option<Vec<Token>>>,
+) -> Option<Vec<Token>> {
+    let (base, conversion) = match split_trailing_conversion(expression) {
+        Some((base, keyword, unit)) => (base, Some((keyword, unit))),
+        None => (expression, None),
+    };
+    let tokens = match cached.get(base) {
+        Some(tokens) => tokens.clone(),
+        None => tokenize_with_units(base),
+    };
+    used.insert(base.to_string(), tokens.clone());
+
+    let mut tokens = tokens?;
+    if let Some((keyword, unit)) = conversion {
+        tokens.push(keyword);
+        tokens.push(Token::NumberWithUnit(1.0, unit));
+    }
+    Some(tokens)
+}
+
+/// Add `delta` to a number as written in a line, like "1,024", "1_000" or "1.5"
+///
+/// The result is written the same way: with as many decimal places, and grouped
+/// with the same separator if the number was grouped. Whole numbers are added
+/// exactly, even past the integers an f64 can hold. Returns None if the text isn't
+/// a number.
+fn increment_number(number: &str, delta: i64) -> Option<String> {
+    let number = if decimal_comma() {
+        swap_decimal_separators(number)
+    } else {
+        number.to_string()
+    };
+    let separator = ['_', ','].into_iter().find(|&c| number.contains(c));
+    let plain = number.replace([',', '_'], "");
+    let group = |whole: i128| match separator {
+        Some(separator) => format_number_with_commas(whole).replace(',', &separator.to_string()),
+        None => whole.to_string(),
+    };
+
+    let formatted = match plain.split_once('.') {
+        None => group(plain.parse::<i128>().ok()?.checked_add(delta.into())?),
+        Some((_, fraction)) => {
+            let value = plain.parse::<f64>().ok()? + delta as f64;
+            let formatted = format!("{:.*}", fraction.len(), value.abs());
+            let (whole, fraction) = formatted.split_once('.')?;
+            let sign = if value < 0.0 { "-" } else { "" };
+            format!("{}{}.{}", sign, group(whole.parse().ok()?), fraction)
+        }
+    };
+
+    Some(if decimal_comma() {
+        swap_decimal_separators(&formatted)
+    } else {
+        formatted
+    })
 }
 
 impl Default for MathypadCore {
@@ -27,6 +162,10 @@ impl Default for MathypadCore {
             cursor_col: 0,
             results: vec![None],
             variables: HashMap::new(),
+            pinned: HashMap::new(),
+            display_units: HashMap::new(),
+            token_cache: TokenCache::default(),
+            circular_lines: HashSet::new(),
         }
     }
 }
@@ -50,6 +189,10 @@ impl MathypadCore {
             cursor_col: 0,
             results: vec![None; line_count],
             variables: HashMap::new(),
+            pinned: HashMap::new(),
+            display_units: HashMap::new(),
+            token_cache: TokenCache::default(),
+            circular_lines: HashSet::new(),
         };
         core.recalculate_all();
         core
@@ -57,6 +200,11 @@ impl MathypadCore {
 
     /// Insert a character at the current cursor position
     pub fn insert_char(&mut self, c: char) {
+        self.insert_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Insert text at the current cursor position, leaving the cursor after it
+    pub fn insert_str(&mut self, text: &str) {
         if self.cursor_line < self.text_lines.len() {
             // Convert cursor position from character index to byte index for insertion
             let line = &self.text_lines[self.cursor_line];
@@ -77,8 +225,8 @@ impl MathypadCore {
                     .unwrap_or(line.len())
             };
 
-            self.text_lines[self.cursor_line].insert(byte_index, c);
-            self.cursor_col += 1;
+            self.text_lines[self.cursor_line].insert_str(byte_index, text);
+            self.cursor_col = safe_cursor_col + text.chars().count();
             self.update_result(self.cursor_line);
             self.update_sum_above_dependent_lines(self.cursor_line);
         }
@@ -116,6 +264,7 @@ impl MathypadCore {
 
                 // Remove the corresponding result
                 self.results.remove(self.cursor_line + 1);
+                self.shift_display_units(self.cursor_line + 1, -1);
 
                 // Update all affected line references
                 self.update_line_references_for_deletion(self.cursor_line + 1);
@@ -153,6 +302,16 @@ impl MathypadCore {
 
             // Insert corresponding result placeholder
             self.results.insert(self.cursor_line, None);
+            self.shift_display_units(self.cursor_line, 1);
+
+            // Splitting at the start moves the whole expression down, and its unit with it
+            if self.text_lines[self.cursor_line - 1].trim().is_empty()
+                && !self.text_lines[self.cursor_line].trim().is_empty()
+            {
+                if let Some(unit) = self.display_units.remove(&(self.cursor_line - 1)) {
+                    self.display_units.insert(self.cursor_line, unit);
+                }
+            }
 
             // Update line references for insertion
             self.update_line_references_for_insertion(self.cursor_line);
@@ -160,19 +319,149 @@ impl MathypadCore {
         }
     }
 
+    /// Copy the current line to a new line below it and move the cursor to the copy
+    ///
+    /// References below the copy are renumbered like for any inserted line, and the
+    /// copy refers to the same lines as the original.
+    pub fn duplicate_line(&mut self) {
+        if self.cursor_line >= self.text_lines.len() {
+            return;
+        }
+
+        let inserted_at = self.cursor_line + 1;
+        for line in self.text_lines.iter_mut() {
+            *line = update_line_references_in_text(line, inserted_at, 1);
+        }
+
+        let copy = self.text_lines[self.cursor_line].clone();
+        self.text_lines.insert(inserted_at, copy);
+        self.results
+            .insert(inserted_at.min(self.results.len()), None);
+        self.shift_display_units(inserted_at, 1);
+        if let Some(unit) = self.display_units.get(&(inserted_at - 1)).cloned() {
+            self.display_units.insert(inserted_at, unit);
+        }
+        self.cursor_line = inserted_at;
+        self.recalculate_all();
+    }
+
+    /// Add `delta` to the number under the cursor, or to the next one after it
+    ///
+    /// Like Vim's Ctrl+A and Ctrl+X, only the number changes, so "1.5 GiB" becomes
+    /// "2.5 GiB", and it keeps its sign, decimal places and thousands separators. The
+    /// cursor ends on the number's last character. Returns false if there is no
+    /// number at or after the cursor on the current line.
+    pub fn increment_number_at_cursor(&mut self, delta: i64) -> bool {
+        let Some(line) = self.text_lines.get(self.cursor_line) else {
+            return false;
+        };
+        let chars: Vec<char> = line.chars().collect();
+
+        // Digits, and separators between digits
+        let is_number_char = |i: usize| {
+            chars[i].is_ascii_digit()
+                || (matches!(chars[i], '.' | ',' | '_')
+                    && i > 0
+                    && chars[i - 1].is_ascii_digit()
+                    && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+        };
+        let Some(mut start) = (self.cursor_col..chars.len()).find(|&i| is_number_char(i)) else {
+            return false;
+        };
+        while start > 0 && is_number_char(start - 1) {
+            start -= 1;
+        }
+        let end = (start..chars.len())
+            .find(|&i| !is_number_char(i))
+            .unwrap_or(chars.len());
+        // A "-" right before the number is its sign, unless it follows a value,
+        // like in "5 - 3" or "5 -3"
+        if start > 0 && chars[start - 1] == '-' {
+            let follows_value = chars[..start - 1]
+                .iter()
+                .rev()
+                .find(|c| !c.is_whitespace())
+                .is_some_and(|&c| c.is_alphanumeric() || c == ')');
+            if !follows_value {
+                start -= 1;
+            }
+        }
+
+        let number: String = chars[start..end].iter().collect();
+        let Some(incremented) = increment_number(&number, delta) else {
+            return false;
+        };
+
+        let mut new_chars = chars[..start].to_vec();
+        new_chars.extend(incremented.chars());
+        new_chars.extend(&chars[end..]);
+        self.text_lines[self.cursor_line] = new_chars.into_iter().collect();
+        self.cursor_col = start + incremented.chars().count() - 1;
+        self.recalculate_all();
+        true
+    }
+
     /// Update the result for a specific line
     pub fn update_result(&mut self, line_index: usize) {
+        // Only an assignment that references other variables can start a cycle,
+        // and only a pad that has one can lose it
+        let may_be_circular = self.text_lines.get(line_index).is_some_and(|line| {
+            split_assignment(line)
+                .is_some_and(|(_, rhs)| rhs.contains(|c: char| c.is_ascii_alphabetic()))
+        });
+        if may_be_circular || !self.circular_lines.is_empty() {
+            self.find_circular_lines();
+        }
+        self.evaluate_line(line_index);
+    }
+
+    /// Update the result for a line, with the circular assignment lines already found
+    fn evaluate_line(&mut self, line_index: usize) {
         if line_index < self.text_lines.len() {
-            let line_text = &self.text_lines[line_index];
+            // Circular variable references would otherwise pick up stale values
+            // from earlier edits, so every line in the cycle evaluates to nothing
+            if self.circular_lines.contains(&line_index) {
+                self.results.resize(self.text_lines.len(), None);
+                for i in self.circular_lines.clone() {
+                    if let Some((name, _)) = split_assignment(&self.text_lines[i]) {
+                        self.variables.remove(name);
+                    }
+                    self.results[i] = None;
+                }
+                return;
+            }
 
-            // Evaluate the expression with current variables and other line results
-            let (result, variable_assignment) =
-                evaluate_with_variables(line_text, &self.variables, &self.results, line_index);
+            // A '#' comment is kept in the text but not evaluated, so "5 * 3 # fives"
+            // shows 15
+            let line_text = strip_comment(&self.text_lines[line_index]).to_string();
+            let cached = self.token_cache.take_line(line_index);
+            let mut used = HashMap::new();
+
+            // A line can hold several independent expressions separated by ";",
+            // e.g. "5+5; 2*3" shows "10; 6". Expressions without a result are skipped.
+            let mut results = Vec::new();
+            for expression in line_text.split(';').filter(|e| !e.trim().is_empty()) {
+                // Evaluate the expression with current variables and other line results
+                let (result, variable_assignment) = evaluate_with_variables_using(
+                    expression,
+                    &mut |text| cached_tokens(text, &cached, &mut used),
+                    &self.variables,
+                    &self.results,
+                    line_index,
+                );
+
+                // Handle variable assignment if present
+                if let Some((var_name, var_value)) = variable_assignment {
+                    self.variables.insert(var_name, var_value);
+                }
 
-            // Handle variable assignment if present
-            if let Some((var_name, var_value)) = variable_assignment {
-                self.variables.insert(var_name, var_value);
+                results.extend(result.map(|r| self.in_display_unit(line_index, r)));
             }
+            let result = if results.is_empty() {
+                None
+            } else {
+                Some(results.join("; "))
+            };
 
             // Ensure results vector is large enough
             while self.results.len() <= line_index {
@@ -181,23 +470,296 @@ impl MathypadCore {
 
             // Store the result
             self.results[line_index] = result;
+            self.token_cache.lines.insert(line_index, used);
+        }
+    }
+
+    /// Show a line's result in another unit by ending the line with "to <unit>"
+    ///
+    /// An existing trailing conversion is replaced, and the tokens read for the rest
+    /// of the line are reused. The line is only rewritten if it has a result and the
+    /// converted line evaluates to the unit; otherwise it is left unchanged and false
+    /// is returned.
+    pub fn convert_line_to(&mut self, line_index: usize, unit: &str) -> bool {
+        let unit = unit.trim();
+        let Some(target_unit) = parse_unit(unit) else {
+            return false;
+        };
+        if !matches!(self.results.get(line_index), Some(Some(_))) {
+            return false;
+        }
+
+        let original = self.text_lines[line_index].clone();
+        let line = original.trim_end().trim_end_matches('=').trim_end();
+        let expression =
+            split_trailing_conversion(line).map_or(line, |(expression, _, _)| expression);
+        self.text_lines[line_index] = format!("{} to {}", expression, unit);
+        self.update_result(line_index);
+
+        // A partial evaluation can still produce a result, so check the unit too
+        let converted = self.results[line_index]
+            .as_deref()
+            .and_then(parse_result_string)
+            .is_some_and(|value| value.unit == Some(target_unit));
+        if !converted {
+            // Incompatible unit - restore the original line and its result
+            self.text_lines[line_index] = original;
+            self.update_result(line_index);
+        }
+        converted
+    }
+
+    /// Rewrite a result in the unit pinned to its line, if any and if it converts
+    fn in_display_unit(&self, line_index: usize, result: String) -> String {
+        let Some(unit) = self.display_units.get(&line_index) else {
+            return result;
+        };
+        parse_result_string(&result)
+            .and_then(|value| value.to_unit(unit))
+            .map(|value| value.format())
+            .unwrap_or(result)
+    }
+
+    /// Always show a line's result in the given unit, whatever the expression becomes
+    ///
+    /// Results that can't be converted to the unit are shown as they are. Returns
+    /// false if the unit isn't recognized.
+    pub fn set_display_unit(&mut self, line_index: usize, unit: &str) -> bool {
+        let Some(unit) = parse_unit(unit.trim()) else {
+            return false;
+        };
+        self.display_units.insert(line_index, unit);
+        self.recalculate_all();
+        true
+    }
+
+    /// Go back to showing a line's result in its natural unit
+    pub fn clear_display_unit(&mut self, line_index: usize) -> bool {
+        let removed = self.display_units.remove(&line_index).is_some();
+        if removed {
+            self.recalculate_all();
+        }
+        removed
+    }
+
+    /// Move pinned display units along with lines inserted (`offset` 1) or deleted
+    /// (`offset` -1) at `at`; a deleted line's unit is dropped
+    pub fn shift_display_units(&mut self, at: usize, offset: isize) {
+        self.display_units = std::mem::take(&mut self.display_units)
+            .into_iter()
+            .filter(|&(line, _)| !(offset < 0 && line == at))
+            .map(|(line, unit)| {
+                if line >= at {
+                    (line.saturating_add_signed(offset), unit)
+                } else {
+                    (line, unit)
+                }
+            })
+            .collect();
+    }
+
+    /// Classify why a line has no result, if the reason is known
+    ///
+    /// A line with several ";"-separated expressions fails its unit assertion if any
+    /// of them does. Unbalanced parentheses are only reported for expressions that
+    /// contain a number and don't evaluate, so prose with a stray "(" isn't flagged.
+    pub fn line_error(&self, line_index: usize) -> Option<EvaluationError> {
+        let line_text = strip_comment(self.text_lines.get(line_index)?);
+        if self.circular_lines.contains(&line_index) {
+            return Some(EvaluationError::CircularReference);
+        }
+
+        let mut segment_start = 0;
+        for expression in line_text.split(';') {
+            let checked = split_unit_assertion(expression).map_or(expression, |(e, _)| e);
+            if checked.contains(|c: char| c.is_ascii_digit()) {
+                if let Some(mismatch) = paren_mismatch_in(checked) {
+                    let (result, _) = evaluate_with_variables(
+                        expression,
+                        &self.variables,
+                        &self.results,
+                        line_index,
+                    );
+                    if result.is_none() {
+                        return Some(EvaluationError::UnbalancedParenthesis(
+                            mismatch.shifted(segment_start),
+                        ));
+                    }
+                }
+            }
+            segment_start += expression.len() + 1;
+        }
+
+        line_text.split(';').find_map(|expression| {
+            let (_, unit) = split_unit_assertion(expression)?;
+            let (result, _) =
+                evaluate_with_variables(expression, &self.variables, &self.results, line_index);
+            match result {
+                Some(_) => None,
+                None => Some(EvaluationError::UnitAssertionFailed(unit)),
+            }
+        })
+    }
+
+    /// Classify what a reference to line `target` (0-indexed) from `from_line` resolves to
+    pub fn line_reference_status(&self, from_line: usize, target: usize) -> LineReferenceStatus {
+        if target >= self.text_lines.len() {
+            LineReferenceStatus::Missing
+        } else if target >= from_line {
+            LineReferenceStatus::Forward
+        } else if matches!(self.results.get(target), Some(Some(_))) {
+            LineReferenceStatus::Resolved
+        } else {
+            LineReferenceStatus::NoResult
+        }
+    }
+
+    /// Find the assignment lines that are part of a circular variable reference,
+    /// reading their tokens through the token cache
+    fn find_circular_lines(&mut self) {
+        let token_cache = &mut self.token_cache;
+        self.circular_lines =
+            find_circular_assignment_lines_using(&self.text_lines, |line_index, expression| {
+                token_cache.tokens(line_index, expression)
+            });
+    }
+
+    /// Pin a line's current result under a label
+    ///
+    /// The value is a snapshot: it stays the same when the line or its inputs change
+    /// later, and every line can use the label like a variable. Returns false if the
+    /// line has no result or the label isn't a valid variable name.
+    pub fn pin_result(&mut self, line_index: usize, label: &str) -> bool {
+        let value = self
+            .results
+            .get(line_index)
+            .and_then(|result| result.as_deref())
+            .and_then(parse_result_string);
+
+        match value {
+            Some(value) if is_variable_name(label) => {
+                self.pinned.insert(label.to_string(), value);
+                self.recalculate_all();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sort a range of lines by their result, smallest first (or largest first with
+    /// `descending`)
+    ///
+    /// Results are compared in the unit of the range's first result, so "1 GiB" sorts
+    /// after "500 MiB". Lines without a result, or with one that can't be converted to
+    /// that unit, keep their order at the end. Line references are renumbered to keep
+    /// pointing at the same lines.
+    ///
+    /// A sort that would change any line's result, like moving a line above the line
+    /// or variable it uses, isn't done: the lines are left as they were and false is
+    /// returned.
+    pub fn sort_lines_by_result(&mut self, lines: Range<usize>, descending: bool) -> bool {
+        self.results.resize(self.text_lines.len(), None);
+        let lines = lines.start.min(self.text_lines.len())..lines.end.min(self.text_lines.len());
+        let values: Vec<Option<UnitValue>> = self
+            .results
+            .iter()
+            .map(|result| result.as_deref().and_then(parse_result_string))
+            .collect();
+
+        // Only values comparable with the first result are sorted; the rest go last
+        let first = values[lines.clone()].iter().flatten().next().cloned();
+        let sort_key = |index: usize| -> Option<&UnitValue> {
+            let value = values[index].as_ref()?;
+            first.as_ref()?.cmp_compatible(value).map(|_| value)
+        };
+
+        let mut order: Vec<usize> = (0..self.text_lines.len()).collect();
+        order[lines].sort_by(|&a, &b| match (sort_key(a), sort_key(b)) {
+            (Some(x), Some(y)) if descending => y.cmp_compatible(x).unwrap_or(Ordering::Equal),
+            (Some(x), Some(y)) => x.cmp_compatible(y).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+
+        let mut new_positions = vec![0; order.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            new_positions[old_index] = new_index;
+        }
+        let original_lines = self.text_lines.clone();
+        let original_results = self.results.clone();
+        let original_display_units = self.display_units.clone();
+
+        self.text_lines = order
+            .iter()
+            .map(|&old_index| {
+                remap_line_references_in_text(&self.text_lines[old_index], &new_positions)
+            })
+            .collect();
+        self.results = order.iter().map(|&i| self.results[i].clone()).collect();
+        self.display_units = std::mem::take(&mut self.display_units)
+            .into_iter()
+            .filter_map(|(line, unit)| Some((*new_positions.get(line)?, unit)))
+            .collect();
+        self.recalculate_all();
+
+        let unchanged = order
+            .iter()
+            .enumerate()
+            .all(|(new_index, &old_index)| self.results[new_index] == original_results[old_index]);
+        if !unchanged {
+            self.text_lines = original_lines;
+            self.display_units = original_display_units;
+            self.recalculate_all();
+        }
+        unchanged
+    }
+
+    /// Remove a pinned result, returning whether it existed
+    pub fn unpin_result(&mut self, label: &str) -> bool {
+        let removed = self.pinned.remove(label).is_some();
+        if removed {
+            self.recalculate_all();
         }
+        removed
+    }
+
+    /// Clear variables before recalculating, keeping pinned results available
+    ///
+    /// Pinned values keep every digit, so rounding results doesn't change them.
+    pub fn reset_variables(&mut self) {
+        self.variables = self
+            .pinned
+            .iter()
+            .map(|(label, value)| (label.clone(), value.format_full_precision()))
+            .collect();
     }
 
     /// Recalculate all results and variables
     pub fn recalculate_all(&mut self) {
         // Clear variables and recalculate from scratch
-        self.variables.clear();
+        self.reset_variables();
 
         // Ensure results vector matches text lines
         self.results.resize(self.text_lines.len(), None);
 
-        // Evaluate each line in order
+        // Evaluate each line in order, finding circular assignments once for all of them
+        self.find_circular_lines();
         for i in 0..self.text_lines.len() {
-            self.update_result(i);
+            self.evaluate_line(i);
         }
     }
 
+    /// Re-evaluate every line in order from a clean slate
+    ///
+    /// Unlike `recalculate_all`, results and cached tokens are dropped first, so
+    /// nothing left over from earlier evaluations can end up in the new results.
+    pub fn recompute_all(&mut self) {
+        self.results = vec![None; self.text_lines.len()];
+        self.token_cache = TokenCache::default();
+        self.recalculate_all();
+    }
+
     /// Update line references after a line insertion
     fn update_line_references_for_insertion(&mut self, inserted_at: usize) {
         for (i, line) in self.text_lines.iter_mut().enumerate() {
@@ -278,6 +840,7 @@ impl MathypadCore {
         self.cursor_col = 0;
         self.results = vec![None; self.text_lines.len()];
         self.variables.clear();
+        self.display_units.clear();
         self.recalculate_all();
     }
 
@@ -351,3 +914,614 @@ impl MathypadCore {
         self.recalculate_all();
     }
 }
+
+/// Split an assignment line like "x = 5" into its variable name and right-hand side
+fn split_assignment(line: &str) -> Option<(&str, &str)> {
+    // Only the first of several ";"-separated expressions is the line's assignment
+    let line = strip_comment(line);
+    let line = line.split(';').next().unwrap_or(line);
+    let (name, rhs) = line.split_once('=')?;
+    let name = name.trim();
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    // "x == 5" is a comparison, not an assignment
+    if is_identifier && !rhs.starts_with('=') {
+        Some((name, rhs))
+    } else {
+        None
+    }
+}
+
+/// Check that a name can be used as a variable, like "baseline" or "_total2"
+pub(crate) fn is_variable_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_lines_by_result() {
+        let mut core = MathypadCore::from_lines(vec![
+            "1 GiB".to_string(),
+            "notes".to_string(),
+            "500 MiB".to_string(),
+            "line1 * 2".to_string(),
+            "3 hours".to_string(),
+            "100 MB".to_string(),
+        ]);
+
+        assert!(core.sort_lines_by_result(0..6, false));
+        assert_eq!(
+            core.text_lines,
+            vec![
+                "100 MB",
+                "500 MiB",
+                "1 GiB",
+                "line3 * 2",
+                "notes",
+                "3 hours"
+            ]
+        );
+        assert_eq!(core.results[0], Some("100 MB".to_string()));
+        assert_eq!(core.results[2], Some("1 GiB".to_string()));
+        assert_eq!(core.results[3], Some("2 GiB".to_string()));
+        assert_eq!(core.results[4], None);
+
+        // Largest first would put "line3 * 2" above the line it uses, so nothing moves
+        assert!(!core.sort_lines_by_result(0..6, true));
+        assert_eq!(core.text_lines[0], "100 MB");
+        assert_eq!(core.text_lines[3], "line3 * 2");
+        assert_eq!(core.results[0], Some("100 MB".to_string()));
+        assert_eq!(core.results[3], Some("2 GiB".to_string()));
+    }
+
+    #[test]
+    fn test_sort_lines_in_a_range() {
+        let mut core = MathypadCore::from_lines(vec![
+            "x = 2 GiB".to_string(),
+            "500 MiB".to_string(),
+            "x / 4".to_string(),
+            "1 GiB".to_string(),
+            "10 MiB".to_string(),
+        ]);
+
+        // Lines outside the range stay where they are
+        assert!(core.sort_lines_by_result(1..4, true));
+        assert_eq!(
+            core.text_lines,
+            vec!["x = 2 GiB", "1 GiB", "x / 4", "500 MiB", "10 MiB"]
+        );
+        assert_eq!(core.results[0], Some("2 GiB".to_string()));
+        assert_eq!(core.results[2], Some("0.5 GiB".to_string()));
+
+        // Sorting everything would move the assignment below the line using it
+        assert!(!core.sort_lines_by_result(0..5, false));
+        assert_eq!(core.text_lines[0], "x = 2 GiB");
+        assert_eq!(core.results[2], Some("0.5 GiB".to_string()));
+    }
+
+    #[test]
+    fn test_duplicate_line() {
+        let mut core = MathypadCore::from_lines(vec![
+            "10 GiB".to_string(),
+            "line1 * 2".to_string(),
+            "line2 + line1".to_string(),
+        ]);
+        core.cursor_line = 1;
+
+        core.duplicate_line();
+        assert_eq!(
+            core.text_lines,
+            vec!["10 GiB", "line1 * 2", "line1 * 2", "line2 + line1"]
+        );
+        assert_eq!(core.cursor_line, 2);
+        // Both copies evaluate, and the line below still refers to the original
+        assert_eq!(core.results[1], Some("20 GiB".to_string()));
+        assert_eq!(core.results[2], Some("20 GiB".to_string()));
+        assert_eq!(core.results[3], Some("30 GiB".to_string()));
+
+        // Duplicating the last line appends the copy
+        core.cursor_line = 3;
+        core.duplicate_line();
+        assert_eq!(core.text_lines[4], "line2 + line1");
+        assert_eq!(core.results[4], Some("30 GiB".to_string()));
+        assert_eq!(core.cursor_line, 4);
+    }
+
+    #[test]
+    fn test_ans_recalls_last_result_in_session() {
+        // Type a session line by line, like entering expressions into a calculator
+        let mut core = MathypadCore::new();
+        let enter = |core: &mut MathypadCore, line: &str| {
+            for c in line.chars() {
+                core.insert_char(c);
+            }
+            let result = core.current_result().map(str::to_string);
+            core.new_line();
+            result
+        };
+
+        assert_eq!(enter(&mut core, "ans + 1"), None);
+        assert_eq!(enter(&mut core, "5 GiB"), Some("5 GiB".to_string()));
+        assert_eq!(enter(&mut core, "ans * 2"), Some("10 GiB".to_string()));
+        // Blank lines and prose don't replace the last answer, unlike `prev`
+        assert_eq!(enter(&mut core, ""), None);
+        assert_eq!(enter(&mut core, "now in MiB"), None);
+        assert_eq!(
+            enter(&mut core, "ans to MiB"),
+            Some("10,240 MiB".to_string())
+        );
+        assert_eq!(enter(&mut core, "ANS / 4"), Some("2,560 MiB".to_string()));
+        assert_eq!(enter(&mut core, ""), None);
+        assert_eq!(
+            enter(&mut core, "ans + 512 MiB"),
+            Some("3,072 MiB".to_string())
+        );
+        assert_eq!(enter(&mut core, ""), None);
+        assert_eq!(enter(&mut core, "prev + ans"), None);
+    }
+
+    #[test]
+    fn test_circular_variable_references_fail_safely() {
+        let mut core = MathypadCore::from_lines(vec![
+            "alpha = beta + 1".to_string(),
+            "beta = alpha + 1".to_string(),
+            "gamma = 5".to_string(),
+        ]);
+
+        assert_eq!(core.results[0], None);
+        assert_eq!(core.results[1], None);
+        assert_eq!(core.results[2], Some("5".to_string()));
+        assert_eq!(core.line_error(0), Some(EvaluationError::CircularReference));
+        assert_eq!(core.line_error(1), Some(EvaluationError::CircularReference));
+        assert_eq!(core.line_error(2), None);
+
+        // Re-evaluating one line of the cycle must not pick up stale values
+        core.variables.insert("beta".to_string(), "10".to_string());
+        core.update_result(0);
+        assert_eq!(core.results[0], None);
+        assert!(!core.variables.contains_key("alpha"));
+        assert!(!core.variables.contains_key("beta"));
+    }
+
+    #[test]
+    fn test_cycle_created_by_editing() {
+        let mut core = MathypadCore::from_lines(vec![
+            "alpha = 5".to_string(),
+            "beta = alpha + 1".to_string(),
+        ]);
+        assert_eq!(core.results[1], Some("6".to_string()));
+
+        // Editing line 1 to depend on beta closes the loop; both lines stop evaluating
+        core.text_lines[0] = "alpha = beta + 1".to_string();
+        core.update_result(0);
+        assert_eq!(core.results[0], None);
+        assert_eq!(core.results[1], None);
+
+        // Breaking it again clears the error without a full recalculation
+        core.text_lines[0] = "alpha = 7".to_string();
+        core.update_result(0);
+        assert_eq!(core.results[0], Some("7".to_string()));
+        assert_eq!(core.line_error(0), None);
+        core.update_result(1);
+        assert_eq!(core.results[1], Some("8".to_string()));
+        assert_eq!(core.line_error(1), None);
+    }
+
+    #[test]
+    fn test_reassignment_from_previous_value_is_not_circular() {
+        let core = MathypadCore::from_lines(vec![
+            "alpha = 5".to_string(),
+            "alpha = alpha + 1".to_string(),
+        ]);
+        assert_eq!(core.results[1], Some("6".to_string()));
+        assert_eq!(core.line_error(1), None);
+    }
+
+    #[test]
+    fn test_multiple_expressions_on_one_line() {
+        let core = MathypadCore::from_lines(vec![
+            "5+5; 2*3".to_string(),
+            "line1 * 2".to_string(),
+            "1 GiB to MiB; oops; 2 GiB + 1 GiB;".to_string(),
+            "rate = 10 MB/s; rate * 60 s".to_string(),
+            "sum_above()".to_string(),
+        ]);
+
+        assert_eq!(core.results[0], Some("10; 6".to_string()));
+        // Line references use the first expression's result
+        assert_eq!(core.results[1], Some("20".to_string()));
+        // Expressions without a result and empty segments are skipped
+        assert_eq!(core.results[2], Some("1,024 MiB; 3 GiB".to_string()));
+        // Assignments in earlier expressions are visible to later ones
+        assert_eq!(core.results[3], Some("10 MB/s; 600 MB".to_string()));
+        assert_eq!(core.variables.get("rate"), Some(&"10 MB/s".to_string()));
+        // sum_above adds up first results too: 10 + 20 + 1,024 MiB (10 MB/s doesn't fit)
+        assert_eq!(core.results[4], Some("1,054 MiB".to_string()));
+    }
+
+    #[test]
+    fn test_pinned_result_is_a_frozen_snapshot() {
+        let mut core = MathypadCore::from_lines(vec![
+            "servers = 10".to_string(),
+            "servers * 2 GiB".to_string(),
+        ]);
+        assert_eq!(core.results[1], Some("20 GiB".to_string()));
+
+        assert!(core.pin_result(1, "baseline"));
+        assert_eq!(core.variables.get("baseline"), Some(&"20 GiB".to_string()));
+
+        // Change the input the pinned line depended on
+        core.text_lines[0] = "servers = 25".to_string();
+        core.text_lines.push("baseline to MiB".to_string());
+        core.text_lines
+            .push("servers * 2 GiB - baseline".to_string());
+        core.recalculate_all();
+
+        assert_eq!(core.results[1], Some("50 GiB".to_string()));
+        assert_eq!(core.results[2], Some("20,480 MiB".to_string()));
+        assert_eq!(core.results[3], Some("30 GiB".to_string()));
+        assert_eq!(core.pinned["baseline"].value, 20.0);
+
+        // Pinned results survive replacing the whole content
+        core.set_content("baseline * 2");
+        assert_eq!(core.results[0], Some("40 GiB".to_string()));
+
+        // Unpinning makes the label undefined again
+        assert!(core.unpin_result("baseline"));
+        assert_eq!(core.results[0], None);
+        assert!(!core.unpin_result("baseline"));
+
+        // Lines without a result and invalid labels can't be pinned
+        core.set_content("hello\n5 GiB");
+        assert!(!core.pin_result(0, "greeting"));
+        assert!(!core.pin_result(1, "not a label"));
+        assert!(!core.pin_result(1, "2fast"));
+        assert!(core.pinned.is_empty());
+    }
+
+    #[test]
+    fn test_display_unit_survives_edits() {
+        let mut core = MathypadCore::from_lines(vec!["1024 MiB".to_string()]);
+        assert!(core.set_display_unit(0, "GiB"));
+        assert_eq!(core.results[0], Some("1 GiB".to_string()));
+        assert!(!core.set_display_unit(0, "furlongs"));
+
+        // Editing the expression keeps the unit
+        core.move_cursor_to(0, 8);
+        core.insert_str(" + 2048 MiB");
+        assert_eq!(core.results[0], Some("3 GiB".to_string()));
+
+        // Results that don't convert are shown as they are
+        core.text_lines[0] = "42".to_string();
+        core.recalculate_all();
+        assert_eq!(core.results[0], Some("42".to_string()));
+
+        // The unit moves with its line when lines are inserted above it
+        core.text_lines[0] = "4 TiB".to_string();
+        core.move_cursor_to(0, 0);
+        core.new_line();
+        assert_eq!(core.display_units.keys().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(core.results[1], Some("4,096 GiB".to_string()));
+
+        // Duplicates keep the unit too
+        core.duplicate_line();
+        assert_eq!(core.results[2], Some("4,096 GiB".to_string()));
+
+        assert!(core.clear_display_unit(1));
+        assert_eq!(core.results[1], Some("4 TiB".to_string()));
+        assert!(!core.clear_display_unit(1));
+    }
+
+    #[test]
+    fn test_count_lines_with_results() {
+        let core = MathypadCore::from_lines(vec![
+            "5 GiB".to_string(),
+            "".to_string(),
+            "just a note".to_string(),
+            "x = 3".to_string(),
+            "1 / 0".to_string(),
+            "count(line1..line5)".to_string(),
+            "count(line5..line1) * 10 GiB".to_string(),
+            "count(line1..line9)".to_string(),
+            "count(line2..line3)".to_string(),
+        ]);
+
+        // Only lines 1 and 4 produced a result
+        assert_eq!(core.results[5], Some("2".to_string()));
+        assert_eq!(core.results[6], Some("20 GiB".to_string()));
+        // Counts are results too, and lines from the current one on aren't counted
+        assert_eq!(core.results[7], Some("4".to_string()));
+        assert_eq!(core.results[8], Some("0".to_string()));
+    }
+
+    #[test]
+    fn test_unit_assertion() {
+        let mut core = MathypadCore::from_lines(vec![
+            "1 GiB".to_string(),
+            "512 MiB".to_string(),
+            "line1 + line2 :: GiB".to_string(),
+            "line1 * 2 :: hours".to_string(),
+            "42 :: GiB".to_string(),
+            "line1 :: furlongs".to_string(),
+        ]);
+
+        // A satisfied assertion keeps the result as it is
+        assert_eq!(core.results[2], Some("1,536 MiB".to_string()));
+        assert_eq!(core.line_error(2), None);
+
+        // Violated ones have no result, and the line says why
+        assert_eq!(core.results[3], None);
+        assert_eq!(
+            core.line_error(3),
+            Some(EvaluationError::UnitAssertionFailed(crate::Unit::Hour))
+        );
+        assert_eq!(core.results[4], None);
+        assert_eq!(
+            core.line_error(4),
+            Some(EvaluationError::UnitAssertionFailed(crate::Unit::GiB))
+        );
+
+        // An unknown unit isn't an assertion
+        assert_eq!(core.results[5], Some("1 GiB".to_string()));
+
+        // The assertion is checked again when the inputs change
+        core.text_lines[1] = "10 minutes".to_string();
+        core.recalculate_all();
+        assert_eq!(core.results[2], None);
+    }
+
+    #[test]
+    fn test_unbalanced_parenthesis_error() {
+        use crate::expression::ParenMismatch;
+
+        let core = MathypadCore::from_lines(vec![
+            "((1+2)".to_string(),
+            "1+2)".to_string(),
+            "5; 2 * (3 + 4".to_string(),
+            "(((1 + 2) * 3) - 4) / 5".to_string(),
+            "notes (to self".to_string(),
+        ]);
+        assert_eq!(
+            core.line_error(0),
+            Some(EvaluationError::UnbalancedParenthesis(
+                ParenMismatch::UnclosedOpen(0)
+            ))
+        );
+        assert_eq!(
+            core.line_error(1),
+            Some(EvaluationError::UnbalancedParenthesis(
+                ParenMismatch::UnmatchedClose(3)
+            ))
+        );
+        // The offset is into the whole line, not the ";"-separated part
+        assert_eq!(
+            core.line_error(2),
+            Some(EvaluationError::UnbalancedParenthesis(
+                ParenMismatch::UnclosedOpen(7)
+            ))
+        );
+        assert_eq!(core.results[3], Some("1".to_string()));
+        assert_eq!(core.line_error(3), None);
+        assert_eq!(core.line_error(4), None);
+    }
+
+    #[test]
+    fn test_line_reference_status() {
+        let core = MathypadCore::from_lines(vec![
+            "5 GiB".to_string(),
+            "Notes".to_string(),
+            "line1 + line2 + line4 + line9".to_string(),
+            "line3 * 2".to_string(),
+        ]);
+
+        assert_eq!(
+            core.line_reference_status(2, 0),
+            LineReferenceStatus::Resolved
+        );
+        assert_eq!(
+            core.line_reference_status(2, 1),
+            LineReferenceStatus::NoResult
+        );
+        assert_eq!(
+            core.line_reference_status(2, 3),
+            LineReferenceStatus::Forward
+        );
+        assert_eq!(
+            core.line_reference_status(2, 2),
+            LineReferenceStatus::Forward
+        );
+        assert_eq!(
+            core.line_reference_status(2, 8),
+            LineReferenceStatus::Missing
+        );
+        // A line that fails makes references to it dead too
+        assert_eq!(
+            core.line_reference_status(3, 2),
+            LineReferenceStatus::NoResult
+        );
+    }
+
+    #[test]
+    fn test_recompute_all_matches_incremental_evaluation() {
+        let lines = [
+            "# Cluster",
+            "nodes = 12",
+            "ram = nodes * 64 GiB",
+            "line3 to TiB",
+            "disk = 2 TB; nodes * disk",
+            "sum_above()",
+            "prev / nodes",
+        ];
+
+        // Type the pad line by line, evaluating as the editor does
+        let mut core = MathypadCore::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                core.new_line();
+            }
+            core.insert_str(line);
+        }
+        let typed_results = core.results.clone();
+        let typed_variables = core.variables.clone();
+        assert_eq!(typed_results[3], Some("0.75 TiB".to_string()));
+
+        core.recompute_all();
+        assert_eq!(core.results, typed_results);
+        assert_eq!(core.variables, typed_variables);
+
+        // Stale state is replaced rather than reused
+        core.results[2] = Some("1 GiB".to_string());
+        core.variables.insert("nodes".to_string(), "3".to_string());
+        core.variables.insert("gone".to_string(), "5".to_string());
+        core.token_cache
+            .lines
+            .get_mut(&3)
+            .unwrap()
+            .insert("line3".to_string(), tokenize_with_units("1 GiB"));
+        core.recompute_all();
+        assert_eq!(core.results, typed_results);
+        assert_eq!(core.variables, typed_variables);
+    }
+
+    #[test]
+    fn test_increment_number_at_cursor() {
+        let increment = |line: &str, cursor_col: usize, delta: i64| {
+            let mut core = MathypadCore::from_lines(vec![line.to_string()]);
+            core.cursor_col = cursor_col;
+            let changed = core.increment_number_at_cursor(delta);
+            (
+                changed.then(|| core.text_lines[0].clone()),
+                core.cursor_col,
+                core.results[0].clone(),
+            )
+        };
+
+        assert_eq!(
+            increment("5", 0, 1),
+            (Some("6".to_string()), 0, Some("6".to_string()))
+        );
+        // The whole number changes wherever the cursor is on it
+        for cursor_col in [0, 1, 2] {
+            assert_eq!(
+                increment("1.5 GiB", cursor_col, 1),
+                (Some("2.5 GiB".to_string()), 2, Some("2.5 GiB".to_string()))
+            );
+        }
+        // Before a number, the next one on the line changes
+        assert_eq!(
+            increment("ram: 1.5 GiB", 0, 1).0,
+            Some("ram: 2.5 GiB".to_string())
+        );
+        assert_eq!(
+            increment("2 * 10", 1, 1),
+            (Some("2 * 11".to_string()), 5, Some("22".to_string()))
+        );
+        // Decrementing keeps the decimal places and grouping
+        assert_eq!(increment("0.25", 0, -1).0, Some("-0.75".to_string()));
+        assert_eq!(increment("1,000 MB", 3, -1).0, Some("999 MB".to_string()));
+        assert_eq!(increment("9,999 MB", 0, 1).0, Some("10,000 MB".to_string()));
+        assert_eq!(increment("0", 0, -1).0, Some("-1".to_string()));
+        // Underscore groups are kept too
+        for cursor_col in [0, 2, 8] {
+            assert_eq!(
+                increment("1_000_000", cursor_col, 1),
+                (
+                    Some("1_000_001".to_string()),
+                    8,
+                    Some("1,000,001".to_string())
+                )
+            );
+        }
+        assert_eq!(increment("1_000", 0, -1).0, Some("999".to_string()));
+        assert_eq!(
+            increment("2_999.5 MB", 0, 1).0,
+            Some("3_000.5 MB".to_string())
+        );
+        // Whole numbers past 2^53 change by exactly one
+        assert_eq!(
+            increment("9007199254740993", 0, 1).0,
+            Some("9007199254740994".to_string())
+        );
+        assert_eq!(
+            increment("-9,007,199,254,740,993", 0, -1).0,
+            Some("-9,007,199,254,740,994".to_string())
+        );
+
+        // A leading "-" is the number's sign
+        assert_eq!(
+            increment("-1", 0, 1),
+            (Some("0".to_string()), 0, Some("0".to_string()))
+        );
+        assert_eq!(increment("0", 0, 1).0, Some("1".to_string()));
+        assert_eq!(increment("-0.75", 3, -1).0, Some("-1.75".to_string()));
+        assert_eq!(increment("-5", 1, -1).0, Some("-6".to_string()));
+        assert_eq!(
+            increment("x = -2 GiB", 0, 3).0,
+            Some("x = 1 GiB".to_string())
+        );
+        assert_eq!(increment("(-2) * 3", 0, 1).0, Some("(-1) * 3".to_string()));
+        // After a value it's a subtraction
+        assert_eq!(
+            increment("5 - 3", 2, 1),
+            (Some("5 - 4".to_string()), 4, Some("1".to_string()))
+        );
+        assert_eq!(increment("5 -3", 2, 1).0, Some("5 -4".to_string()));
+        assert_eq!(increment("(8)-3", 3, 1).0, Some("(8)-4".to_string()));
+
+        // Nothing to change after the cursor
+        assert_eq!(increment("5 GiB", 2, 1).0, None);
+        assert_eq!(increment("Notes", 0, 1).0, None);
+    }
+
+    #[test]
+    fn test_conversion_target_change_reuses_tokens() {
+        let mut core = MathypadCore::from_lines(vec!["2 GiB + 512 MiB".to_string()]);
+        assert!(core.convert_line_to(0, "MiB"));
+        assert_eq!(core.text_lines[0], "2 GiB + 512 MiB to MiB");
+        assert_eq!(core.results[0], Some("2,560 MiB".to_string()));
+        assert!(core.token_cache.lines[&0].contains_key("2 GiB + 512 MiB"));
+
+        // Swap in other tokens for the expression: a new target must use them
+        // rather than parsing the line again
+        core.token_cache
+            .lines
+            .get_mut(&0)
+            .unwrap()
+            .insert("2 GiB + 512 MiB".to_string(), tokenize_with_units("1 GiB"));
+        assert!(core.convert_line_to(0, "KiB"));
+        assert_eq!(core.text_lines[0], "2 GiB + 512 MiB to KiB");
+        assert_eq!(core.results[0], Some("1,048,576 KiB".to_string()));
+
+        // Editing the expression itself reads it again
+        core.text_lines[0] = "3 GiB + 512 MiB to KiB".to_string();
+        core.update_result(0);
+        assert_eq!(core.results[0], Some("3,670,016 KiB".to_string()));
+        assert!(!core.token_cache.lines[&0].contains_key("2 GiB + 512 MiB"));
+
+        // Incompatible units leave the line as it was
+        assert!(!core.convert_line_to(0, "hours"));
+        assert_eq!(core.text_lines[0], "3 GiB + 512 MiB to KiB");
+    }
+
+    #[test]
+    fn test_inline_comments_stay_in_the_text() {
+        let content = "# Servers\nram = 64 GiB # per node\nram * 3 # three nodes\n";
+        let mut core = MathypadCore::new();
+        core.set_content(content);
+
+        assert_eq!(core.text_lines[1], "ram = 64 GiB # per node");
+        assert_eq!(core.results[0], None);
+        assert_eq!(core.results[1], Some("64 GiB".to_string()));
+        assert_eq!(core.results[2], Some("192 GiB".to_string()));
+        assert_eq!(core.get_content(), content);
+    }
+}
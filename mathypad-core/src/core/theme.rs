@@ -0,0 +1,213 @@
+//! Color themes for syntax highlighting
+
+use super::highlighting::HighlightType;
+
+/// An RGB color as (red, green, blue)
+pub type Rgb = (u8, u8, u8);
+
+/// Names of the built-in themes, in the order they're listed
+pub const BUILTIN_THEMES: [&str; 3] = ["default", "high-contrast", "monochrome"];
+
+/// The color used for each kind of highlighted text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub number: Rgb,
+    pub unit: Rgb,
+    pub line_reference: Rgb,
+    pub keyword: Rgb,
+    pub operator: Rgb,
+    pub variable: Rgb,
+    pub function: Rgb,
+    pub normal: Rgb,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "default".to_string(),
+            number: (65, 105, 225),        // Royal blue
+            unit: (34, 139, 34),           // Forest green
+            line_reference: (255, 140, 0), // Dark orange
+            keyword: (178, 34, 34),        // Fire brick red
+            operator: (128, 0, 128),       // Purple
+            variable: (72, 61, 139),       // Dark slate blue
+            function: (138, 43, 226),      // Blue violet
+            normal: (105, 105, 105),       // Dim gray
+        }
+    }
+}
+
+impl Theme {
+    /// Bright, saturated colors for dark terminals and low-vision use
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "high-contrast".to_string(),
+            number: (0, 191, 255),
+            unit: (0, 255, 127),
+            line_reference: (255, 165, 0),
+            keyword: (255, 85, 85),
+            operator: (255, 0, 255),
+            variable: (255, 255, 0),
+            function: (0, 255, 255),
+            normal: (255, 255, 255),
+        }
+    }
+
+    /// Shades of gray only, with values brightest
+    pub fn monochrome() -> Self {
+        Theme {
+            name: "monochrome".to_string(),
+            number: (255, 255, 255),
+            unit: (200, 200, 200),
+            line_reference: (255, 255, 255),
+            keyword: (170, 170, 170),
+            operator: (170, 170, 170),
+            variable: (200, 200, 200),
+            function: (200, 200, 200),
+            normal: (128, 128, 128),
+        }
+    }
+
+    /// Look up a built-in theme by name (see `BUILTIN_THEMES`)
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(Theme::default()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Get the color for a kind of highlighted text
+    pub fn color(&self, highlight_type: &HighlightType) -> Rgb {
+        match highlight_type {
+            HighlightType::Number => self.number,
+            HighlightType::Unit => self.unit,
+            HighlightType::LineReference => self.line_reference,
+            HighlightType::Keyword => self.keyword,
+            HighlightType::Operator => self.operator,
+            HighlightType::Variable => self.variable,
+            HighlightType::Function => self.function,
+            HighlightType::Normal => self.normal,
+        }
+    }
+
+    /// Parse a theme config, one "key = value" setting per line
+    ///
+    /// "theme = <name>" picks the built-in theme to start from, and the other keys
+    /// ("number", "unit", "line_reference", "keyword", "operator", "variable",
+    /// "function" and "normal") override single colors as "#rrggbb". Blank lines
+    /// and lines starting with '#' are skipped.
+    pub fn from_config(config: &str) -> Result<Self, String> {
+        let mut theme = Theme::default();
+        let mut overrides = Vec::new();
+
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", line_number + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            if key == "theme" {
+                theme = Theme::builtin(value).ok_or_else(|| {
+                    format!("line {}: unknown theme '{}'", line_number + 1, value)
+                })?;
+            } else {
+                let color = parse_hex_color(value).ok_or_else(|| {
+                    format!("line {}: invalid color '{}'", line_number + 1, value)
+                })?;
+                overrides.push((line_number, key, color));
+            }
+        }
+
+        // Colors override the base theme wherever "theme =" appears in the file
+        for (line_number, key, color) in overrides {
+            let slot = match key {
+                "number" => &mut theme.number,
+                "unit" => &mut theme.unit,
+                "line_reference" => &mut theme.line_reference,
+                "keyword" => &mut theme.keyword,
+                "operator" => &mut theme.operator,
+                "variable" => &mut theme.variable,
+                "function" => &mut theme.function,
+                "normal" => &mut theme.normal,
+                _ => return Err(format!("line {}: unknown key '{}'", line_number + 1, key)),
+            };
+            *slot = color;
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse a "#rrggbb" color
+fn parse_hex_color(value: &str) -> Option<Rgb> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes() {
+        for name in BUILTIN_THEMES {
+            assert_eq!(Theme::builtin(name).unwrap().name, name);
+        }
+        assert_eq!(
+            Theme::builtin("High-Contrast"),
+            Some(Theme::high_contrast())
+        );
+        assert_eq!(Theme::builtin("solarized"), None);
+
+        // The default theme keeps the standard colors
+        assert_eq!(
+            Theme::default().color(&HighlightType::Number),
+            HighlightType::Number.rgb_color()
+        );
+        assert_eq!(
+            Theme::monochrome().color(&HighlightType::Unit),
+            (200, 200, 200)
+        );
+    }
+
+    #[test]
+    fn test_theme_from_config() {
+        let theme = Theme::from_config(
+            "# My colors\nnumber = #ff0000\n\ntheme = monochrome\nunit = #00FF80\n",
+        )
+        .unwrap();
+        assert_eq!(theme.name, "monochrome");
+        assert_eq!(theme.color(&HighlightType::Number), (255, 0, 0));
+        assert_eq!(theme.color(&HighlightType::Unit), (0, 255, 128));
+        assert_eq!(theme.color(&HighlightType::Keyword), (170, 170, 170));
+
+        assert_eq!(Theme::from_config(""), Ok(Theme::default()));
+        assert_eq!(
+            Theme::from_config("theme = neon"),
+            Err("line 1: unknown theme 'neon'".to_string())
+        );
+        assert_eq!(
+            Theme::from_config("number = blue"),
+            Err("line 1: invalid color 'blue'".to_string())
+        );
+        assert_eq!(
+            Theme::from_config("\nnumbers = #000000"),
+            Err("line 2: unknown key 'numbers'".to_string())
+        );
+        assert_eq!(
+            Theme::from_config("number"),
+            Err("line 1: expected 'key = value'".to_string())
+        );
+    }
+}
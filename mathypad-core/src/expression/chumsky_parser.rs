@@ -1,47 +1,97 @@
 //! New chumsky-based parser implementation for mathematical expressions
 
 use super::tokens::Token;
-use crate::units::parse_unit;
+use crate::settings::{ASSUME_BYTES_THRESHOLD, assume_bytes};
+use crate::units::{Unit, parse_unit};
 use chumsky::prelude::*;
+use std::ops::Range;
+
+/// An unbalanced parenthesis, with the byte offset of the offending character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParenMismatch {
+    /// A "(" that is never closed; the outermost one if there are several
+    UnclosedOpen(usize),
+    /// A ")" with no "(" left to close
+    UnmatchedClose(usize),
+}
+
+impl ParenMismatch {
+    /// Byte offset of the offending parenthesis in the input
+    pub fn offset(&self) -> usize {
+        match self {
+            ParenMismatch::UnclosedOpen(offset) | ParenMismatch::UnmatchedClose(offset) => *offset,
+        }
+    }
+
+    /// The same mismatch with its offset moved by `by` bytes, for a checked substring
+    pub fn shifted(self, by: usize) -> Self {
+        match self {
+            ParenMismatch::UnclosedOpen(offset) => ParenMismatch::UnclosedOpen(offset + by),
+            ParenMismatch::UnmatchedClose(offset) => ParenMismatch::UnmatchedClose(offset + by),
+        }
+    }
+}
+
+impl std::fmt::Display for ParenMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParenMismatch::UnclosedOpen(_) => write!(f, "Unmatched opening parenthesis"),
+            ParenMismatch::UnmatchedClose(_) => write!(f, "Unmatched closing parenthesis"),
+        }
+    }
+}
+
+/// Find the first parenthesis that breaks the balance of a token stream
+///
+/// A ")" with nothing open is reported as soon as it is seen; otherwise the
+/// outermost "(" still open at the end is.
+pub fn find_paren_mismatch(tokens: &[(Token, Range<usize>)]) -> Option<ParenMismatch> {
+    let mut open = Vec::new();
+    for (token, span) in tokens {
+        match token {
+            Token::LeftParen => open.push(span.start),
+            Token::RightParen if open.pop().is_none() => {
+                return Some(ParenMismatch::UnmatchedClose(span.start));
+            }
+            _ => {}
+        }
+    }
+    open.first()
+        .map(|&offset| ParenMismatch::UnclosedOpen(offset))
+}
+
+/// Find an unbalanced parenthesis in an expression, if it tokenizes at all
+pub fn paren_mismatch_in(input: &str) -> Option<ParenMismatch> {
+    let tokens = create_token_parser().parse(input).into_result().ok()?;
+    find_paren_mismatch(&fold_unary_signs(tokens))
+}
 
 /// Parse a mathematical expression using chumsky
 pub fn parse_expression_chumsky(input: &str) -> Result<Vec<Token>, String> {
+    let tokens = parse_expression_with_spans(input)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Parse a mathematical expression into tokens along with the byte range each one
+/// was read from
+pub fn parse_expression_with_spans(input: &str) -> Result<Vec<(Token, Range<usize>)>, String> {
     // Create a simple parser that directly parses from string to tokens
     let parser = create_token_parser();
 
     match parser.parse(input).into_result() {
         Ok(tokens) => {
-            // Validate parentheses are balanced
-            let mut paren_count = 0;
-            for token in &tokens {
-                match token {
-                    Token::LeftParen => paren_count += 1,
-                    Token::RightParen => {
-                        paren_count -= 1;
-                        if paren_count < 0 {
-                            return Err("Unmatched closing parenthesis".to_string());
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            if paren_count != 0 {
-                return Err("Unmatched opening parenthesis".to_string());
+            let tokens = demote_prose_modulo(fold_unary_signs(tokens));
+
+            if let Some(mismatch) = find_paren_mismatch(&tokens) {
+                return Err(mismatch.to_string());
             }
 
             // Validate no consecutive operators (except minus for negation)
-            for i in 0..tokens.len().saturating_sub(1) {
-                let current = &tokens[i];
-                let next = &tokens[i + 1];
-
-                let is_current_op = matches!(
-                    current,
-                    Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power
-                );
-                let is_next_op = matches!(
-                    next,
-                    Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power
-                );
+            for pair in tokens.windows(2) {
+                let (current, next) = (&pair[0].0, &pair[1].0);
+
+                let is_current_op = current.is_binary_operator();
+                let is_next_op = next.is_binary_operator();
 
                 if is_current_op && is_next_op {
                     // Allow minus after operators for negation, but not other combinations
@@ -64,26 +114,133 @@ pub fn parse_expression_chumsky(input: &str) -> Result<Vec<Token>, String> {
     }
 }
 
+/// Fold a sign written directly in front of a number into the number
+///
+/// The sign is only unary where no value precedes it, so "+5 + 3", "2 * -3" and
+/// "(-2)^2" read as signed numbers, while "10 -5" is still a subtraction. A sign
+/// separated from the number ("- 5 GiB", like a list bullet) is left alone, as is
+/// one in front of a power, since "-2^2" means -(2^2).
+fn fold_unary_signs(tokens: Vec<(Token, SimpleSpan)>) -> Vec<(Token, Range<usize>)> {
+    let mut folded: Vec<(Token, Range<usize>)> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let (token, span) = &tokens[i];
+        let sign = match token {
+            Token::Plus => Some(1.0),
+            Token::Minus => Some(-1.0),
+            _ => None,
+        };
+        let is_unary = folded.last().is_none_or(|(prev, _)| !prev.ends_value());
+        let before_power = matches!(tokens.get(i + 2), Some((Token::Power, _)));
+
+        if let (Some(sign), true, false, Some((number, number_span))) =
+            (sign, is_unary, before_power, tokens.get(i + 1))
+        {
+            if number_span.start == span.end {
+                let signed = match number {
+                    Token::Number(n) => Some(Token::Number(sign * n)),
+                    Token::NumberWithUnit(n, unit) => {
+                        Some(Token::NumberWithUnit(sign * n, unit.clone()))
+                    }
+                    _ => None,
+                };
+                if let Some(signed) = signed {
+                    folded.push((signed, span.start..number_span.end));
+                    i += 2;
+                    continue;
+                }
+            }
+        }
+
+        folded.push((token.clone(), span.into_range()));
+        i += 1;
+    }
+
+    folded
+}
+
+/// Read "mod" as a plain word unless it sits between two operands
+///
+/// "17 mod 5" and "x mod 3" are remainders, but when an operand is a word right next
+/// to another value, as in "ask the mod 5 times" or "the mod said 5", the line is
+/// prose and "mod" stays a variable.
+fn demote_prose_modulo(mut tokens: Vec<(Token, Range<usize>)>) -> Vec<(Token, Range<usize>)> {
+    let is_value = |token: Option<&(Token, Range<usize>)>| {
+        matches!(
+            token,
+            Some((
+                Token::Number(_)
+                    | Token::NumberWithUnit(_, _)
+                    | Token::LineReference(_)
+                    | Token::LineValue(_)
+                    | Token::Variable(_),
+                _
+            ))
+        )
+    };
+    let is_word =
+        |token: Option<&(Token, Range<usize>)>| matches!(token, Some((Token::Variable(_), _)));
+
+    for i in 0..tokens.len() {
+        if !matches!(tokens[i].0, Token::Modulo) {
+            continue;
+        }
+        let before = i.checked_sub(1).and_then(|j| tokens.get(j));
+        let before_that = i.checked_sub(2).and_then(|j| tokens.get(j));
+        let after = tokens.get(i + 1);
+        let after_that = tokens.get(i + 2);
+
+        let between_operands = before.is_some_and(|(t, _)| t.ends_value())
+            && after.is_some_and(|(t, _)| {
+                t.ends_value() && !matches!(t, Token::RightParen)
+                    || matches!(t, Token::LeftParen | Token::Function(_))
+            });
+        let in_prose =
+            is_word(before) && is_value(before_that) || is_word(after) && is_value(after_that);
+        if !between_operands || in_prose {
+            tokens[i].0 = Token::Variable("mod".to_string());
+        }
+    }
+
+    tokens
+}
+
 /// Create the main token parser
-fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<Rich<'a, char>>> {
-    // Parser for numerical suffixes like "k" for thousands
-    let number_suffix = choice((just('k').to(1_000.0), just('K').to(1_000.0)));
+fn create_token_parser<'a>()
+-> impl Parser<'a, &'a str, Vec<(Token, SimpleSpan)>, extra::Err<Rich<'a, char>>> {
+    // Parser for numerical suffixes like "k" for thousands or "M" for millions.
+    // A letter right after the suffix makes it part of a unit, like "2KB" or "5Mb".
+    let number_suffix = choice((
+        just('k').to(1_000.0),
+        just('K').to(1_000.0),
+        just('M').to(1_000_000.0),
+    ))
+    .then_ignore(any().filter(|c: &char| c.is_ascii_alphanumeric()).not());
 
-    // Parser for numbers (integers and decimals with optional commas and suffixes)
+    // Scientific notation exponent (like the "e3" in "1e3" or "e-6" in "2.5E-6").
+    // Digits are required, so "1 EB" and "1eb" stay exabytes.
+    let exponent = one_of("eE")
+        .then(one_of("+-").or_not())
+        .then(text::digits(10));
+
+    // Parser for numbers (integers and decimals with optional commas, exponents and suffixes)
     let number = choice((
-        // Numbers with commas (like 1,000 or 1,234.56)
+        // Numbers with comma or underscore grouping (like 1,000, 1,234.56 or 1_000_000)
         text::digits(10)
-            .then(just(',').then(text::digits(10)).repeated())
+            .then(one_of(",_").then(text::digits(10)).repeated())
             .then(just('.').then(text::digits(10)).or_not())
+            .then(exponent.or_not())
             .to_slice(),
         // Regular numbers without commas
         text::int(10)
             .then(just('.').then(text::digits(10)).or_not())
+            .then(exponent.or_not())
             .to_slice(),
     ))
     .then(number_suffix.or_not())
     .map(|(s, suffix_opt): (&str, Option<f64>)| {
-        let cleaned = s.replace(",", "");
+        let cleaned = s.replace([',', '_'], "");
         let base_value = cleaned.parse::<f64>().unwrap_or(0.0);
         if let Some(multiplier) = suffix_opt {
             base_value * multiplier
@@ -145,45 +302,103 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
             },
         );
 
-    // Parser for line references (like "line1", "line2", etc.)
+    // Parser for line references (like "line1", "line2", etc.), optionally
+    // followed by ".value" to take just the number from that line's result
     let line_ref = just("line")
         .then(text::int(10))
-        .map(|(_, num_str): (_, &str)| {
-            if let Ok(line_num) = num_str.parse::<usize>() {
-                if line_num > 0 {
-                    Token::LineReference(line_num - 1)
-                } else {
-                    Token::LineReference(0)
-                }
+        .then(just(".value").or_not())
+        .map(|((_, num_str), value): ((_, &str), Option<_>)| {
+            let index = num_str
+                .parse::<usize>()
+                .map_or(0, |line_num| line_num.saturating_sub(1));
+            if value.is_some() {
+                Token::LineValue(index)
             } else {
-                Token::LineReference(0)
+                Token::LineReference(index)
             }
         });
 
-    // Parser for keywords
+    // Parser for ranges of lines (like "line1..line10"), the argument of count()
+    let line_number = just("line")
+        .ignore_then(text::int(10))
+        .map(|num_str: &str| num_str.parse::<usize>().map_or(0, |n| n.saturating_sub(1)));
+    let line_range = line_number
+        .then_ignore(just(".."))
+        .then(line_number)
+        .map(|(start, end)| Token::LineRange(start, end));
+
+    // Parser for keywords, which are read in any case ("TO", "In")
+    let keyword_word = |word: &'static str| {
+        text::ascii::ident().try_map(move |ident: &str, span| {
+            if ident.eq_ignore_ascii_case(word) {
+                Ok(())
+            } else {
+                Err(Rich::custom(span, "Not a keyword"))
+            }
+        })
+    };
     let keyword = choice((
-        text::keyword("to").to(Token::To),
-        text::keyword("in").to(Token::In),
-        text::keyword("of").to(Token::Of),
+        keyword_word("to").to(Token::To),
+        keyword_word("in").to(Token::In),
+        keyword_word("out")
+            .then(just(' ').repeated().at_least(1))
+            .then(keyword_word("of"))
+            .to(Token::OutOf),
+        keyword_word("of").to(Token::Of),
+        keyword_word("as").to(Token::As),
+        // "%" is percent, so remainders are spelled out
+        keyword_word("mod").to(Token::Modulo),
     ));
 
-    // Parser for operators (including assignment)
+    // Parser for fractions written as words, like "half of 1 GiB". They are read as
+    // percentages so they work like "50% of 1 GiB", and only right before "of", so
+    // "the other half" or "1 quarter" keep their usual meaning.
+    let fraction_word = choice((
+        keyword_word("half").to(50.0),
+        keyword_word("third").to(100.0 / 3.0),
+        keyword_word("quarter").to(25.0),
+    ))
+    .then_ignore(
+        just(' ')
+            .repeated()
+            .at_least(1)
+            .then(keyword_word("of"))
+            .rewind(),
+    )
+    .map(|percent| Token::NumberWithUnit(percent, Unit::Percent));
+
+    // Parser for operators (including assignment and comparisons)
+    // Two-character operators must come before their single-character prefixes
     let operator = choice((
+        just(">=").to(Token::GreaterEqual),
+        just("<=").to(Token::LessEqual),
+        just("==").to(Token::Equal),
+        just('>').to(Token::Greater),
+        just('<').to(Token::Less),
         just('+').to(Token::Plus),
         just('-').to(Token::Minus),
+        just("**").to(Token::Power),
         just('*').to(Token::Multiply),
         just('/').to(Token::Divide),
         just('^').to(Token::Power),
         just('(').to(Token::LeftParen),
         just(')').to(Token::RightParen),
         just('=').to(Token::Assign),
+        just('?').to(Token::Question),
+        just(':').to(Token::Colon),
     ));
 
+    // Parser for micro-prefixed units written with the micro sign (like "µs")
+    let micro_identifier = one_of("µμ")
+        .then(text::ascii::ident())
+        .map(|(micro, rest): (char, &str)| format!("{}{}", micro, rest));
+
     // Combined unit parser (tries currency rates first, then compound units, then simple identifiers, then percent, then currency)
     let unit_identifier = choice((
         currency_rate, // Must come first to match $/year before $ is parsed separately
         compound_identifier,
         identifier,
+        micro_identifier,
         percent_symbol,
         currency_symbol,
     ));
@@ -196,10 +411,17 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
                 .then(unit_identifier)
                 .try_map(|(_, unit_str): ((), String), span| {
                     // Don't treat keywords as units in this context
-                    if unit_str == "to" || unit_str == "in" || unit_str == "of" {
+                    if matches!(
+                        unit_str.to_ascii_lowercase().as_str(),
+                        "to" | "in" | "of" | "as" | "mod"
+                    ) {
                         Err(Rich::custom(span, "Keywords are not units"))
                     } else if let Some(unit) = parse_unit(&unit_str) {
                         Ok(unit)
+                    } else if unit_str == "d" && Unit::Day.is_enabled() {
+                        // A lone "d" is only days right after a number ("5d"); anywhere
+                        // else it's too ambiguous, so parse_unit doesn't know it
+                        Ok(Unit::Day)
                     } else {
                         Err(Rich::custom(span, format!("Unknown unit: {}", unit_str)))
                     }
@@ -209,6 +431,9 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
         .map(|(num, unit_opt)| {
             if let Some(unit) = unit_opt {
                 Token::NumberWithUnit(num, unit)
+            } else if assume_bytes() && num.fract() == 0.0 && num >= ASSUME_BYTES_THRESHOLD {
+                // Opt-in: large bare numbers are byte counts
+                Token::NumberWithUnit(num, Unit::Byte)
             } else {
                 Token::Number(num)
             }
@@ -261,6 +486,10 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
         .try_map(|name: String, span| match name.to_lowercase().as_str() {
             "sqrt" => Ok(Token::Function(name)),
             "sum_above" => Ok(Token::Function(name)),
+            "count" => Ok(Token::Function(name)),
+            "typeof" => Ok(Token::Function(name)),
+            "nextpow2" | "prevpow2" => Ok(Token::Function(name)),
+            "humanize" | "humanize10" => Ok(Token::Function(name)),
             _ => Err(Rich::custom(span, "Unknown function")),
         });
 
@@ -269,8 +498,10 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
 
     // Main token parser - try each option in order (most specific first)
     let token = choice((
+        line_range,           // "line1..line10" before "line1" is read on its own
         line_ref,             // Must come first to catch "line1" before "line" is treated as unit
-        keyword,              // "to" and "in" keywords
+        keyword,              // "to", "in", "of", "out of", "as" and "mod" keywords
+        fraction_word,        // "half", "third" and "quarter" before "of"
         currency_rate_amount, // Currency rate amounts like "$5/hr" (must come before currency_amount)
         currency_amount, // Currency symbols followed by numbers (must come before number_with_unit)
         number_with_unit, // Numbers with optional units
@@ -282,11 +513,9 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
 
     // Parser for punctuation/separators to skip
     let punctuation = choice((
-        just(':'),
         just(';'),
         just(','),
         just('!'),
-        just('?'),
         just('.'), // Keep it simple - decimal points in numbers are handled in number parser
         just('"'),
         just('\''),
@@ -300,12 +529,14 @@ fn create_token_parser<'a>() -> impl Parser<'a, &'a str, Vec<Token>, extra::Err<
         just(']'),
         just('{'),
         just('}'),
-        just('<'),
-        just('>'),
     ));
 
     // Combined parser that tries tokens first, then skips punctuation
-    let element = choice((token.map(Some), punctuation.to(None)));
+    // Tokens keep their spans so signs can be told apart from operators
+    let element = choice((
+        token.map_with(|token, e| Some((token, e.span()))),
+        punctuation.to(None),
+    ));
 
     // Parse elements separated by whitespace, filter out None (punctuation)
     element
@@ -458,6 +689,104 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_underscore_separated_numbers() {
+        let result = parse_expression_chumsky("1_000_000 GiB");
+        assert!(result.is_ok(), "Parsing failed: {:?}", result);
+        let tokens = result.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(
+            tokens[0],
+            Token::NumberWithUnit(1000000.0, Unit::GiB)
+        ));
+
+        let result = parse_expression_chumsky("1_000.5");
+        assert!(result.is_ok(), "Parsing failed: {:?}", result);
+        let tokens = result.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(tokens[0], Token::Number(1000.5)));
+
+        // An underscore next to an operator is not part of the number
+        let result = parse_expression_chumsky("1_000 + 1");
+        assert!(result.is_ok(), "Parsing failed: {:?}", result);
+        let tokens = result.unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(tokens[0], Token::Number(1000.0)));
+    }
+
+    #[test]
+    fn test_exponent_vs_exabyte_tokens() {
+        let tokens = |input: &str| {
+            let result = parse_expression_chumsky(input);
+            assert!(result.is_ok(), "Parsing failed for {}: {:?}", input, result);
+            result.unwrap()
+        };
+
+        // Scientific notation needs digits after the "e"
+        assert!(matches!(tokens("1e3")[..], [Token::Number(1000.0)]));
+        assert!(matches!(tokens("1E3")[..], [Token::Number(1000.0)]));
+        assert!(matches!(tokens("2.5e-3")[..], [Token::Number(0.0025)]));
+        assert!(matches!(tokens("1e+2")[..], [Token::Number(100.0)]));
+        assert!(matches!(
+            tokens("2e3 GiB")[..],
+            [Token::NumberWithUnit(2000.0, Unit::GiB)]
+        ));
+
+        // ...so exabyte units right after a number are still units
+        assert!(matches!(
+            tokens("1 EB")[..],
+            [Token::NumberWithUnit(1.0, Unit::EB)]
+        ));
+        assert!(matches!(
+            tokens("1EB")[..],
+            [Token::NumberWithUnit(1.0, Unit::EB)]
+        ));
+        assert!(matches!(
+            tokens("1 EiB")[..],
+            [Token::NumberWithUnit(1.0, Unit::EiB)]
+        ));
+        assert!(matches!(
+            tokens("1eb")[..],
+            [Token::NumberWithUnit(1.0, Unit::EB)]
+        ));
+        assert!(matches!(
+            tokens("1 eb to pb")[..],
+            [
+                Token::NumberWithUnit(1.0, Unit::EB),
+                Token::To,
+                Token::NumberWithUnit(1.0, Unit::PB)
+            ]
+        ));
+
+        // "to"/"in" are keywords, while words that merely start with them are not
+        assert!(matches!(
+            tokens("1 TB to tb")[..],
+            [
+                Token::NumberWithUnit(1.0, Unit::TB),
+                Token::To,
+                Token::NumberWithUnit(1.0, Unit::TB)
+            ]
+        ));
+        assert!(matches!(
+            tokens("1 Tb in TB")[..],
+            [
+                Token::NumberWithUnit(1.0, Unit::Tb),
+                Token::In,
+                Token::NumberWithUnit(1.0, Unit::TB)
+            ]
+        ));
+        assert!(
+            !tokens("5 inch")
+                .iter()
+                .any(|token| matches!(token, Token::In))
+        );
+        assert!(
+            !tokens("1 GiB tomorrow")
+                .iter()
+                .any(|token| matches!(token, Token::To))
+        );
+    }
+
     #[test]
     fn test_numbers_without_spaces() {
         // Test basic numbers without spaces
@@ -583,6 +912,107 @@ mod tests {
         assert!(matches!(tokens[8], Token::Number(5.0)));
     }
 
+    #[test]
+    fn test_as_keyword() {
+        let result = parse_expression_chumsky("42 as GiB");
+        assert!(result.is_ok(), "Parsing failed: {:?}", result);
+        let tokens = result.unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(tokens[0], Token::Number(42.0)));
+        assert!(matches!(tokens[1], Token::As));
+        assert!(matches!(tokens[2], Token::NumberWithUnit(1.0, Unit::GiB)));
+    }
+
+    #[test]
+    fn test_line_range() {
+        let tokens = parse_expression_chumsky("count(line1..line10)").unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert!(matches!(&tokens[0], Token::Function(name) if name == "count"));
+        assert!(matches!(tokens[2], Token::LineRange(0, 9)));
+
+        // A single reference followed by a period is still a reference
+        let tokens = parse_expression_chumsky("line1.").unwrap();
+        assert!(matches!(tokens[..], [Token::LineReference(0)]));
+    }
+
+    #[test]
+    fn test_paren_mismatch() {
+        assert_eq!(
+            paren_mismatch_in("((1+2)"),
+            Some(ParenMismatch::UnclosedOpen(0))
+        );
+        assert_eq!(
+            paren_mismatch_in("1+2)"),
+            Some(ParenMismatch::UnmatchedClose(3))
+        );
+        assert_eq!(
+            paren_mismatch_in("(1 + 2)) * (3"),
+            Some(ParenMismatch::UnmatchedClose(7))
+        );
+        assert_eq!(
+            paren_mismatch_in("2 * (3 + (4"),
+            Some(ParenMismatch::UnclosedOpen(4))
+        );
+
+        let nested = format!("{}1{}", "(".repeat(50), ")".repeat(50));
+        assert_eq!(paren_mismatch_in(&nested), None);
+        assert!(parse_expression_chumsky(&nested).is_ok());
+        assert_eq!(paren_mismatch_in("((1 + (2 * 3)) - (4 / (5)))"), None);
+
+        assert_eq!(
+            parse_expression_chumsky("((1+2)").unwrap_err(),
+            "Unmatched opening parenthesis"
+        );
+        assert_eq!(
+            parse_expression_chumsky("1+2)").unwrap_err(),
+            "Unmatched closing parenthesis"
+        );
+    }
+
+    #[test]
+    fn test_mod_keyword() {
+        let tokens = parse_expression_chumsky("17 mod 5").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(tokens[1], Token::Modulo));
+
+        // "mo" is still months, and "mod" inside a word isn't the operator
+        let tokens = parse_expression_chumsky("5 mo").unwrap();
+        assert!(matches!(
+            tokens[..],
+            [Token::NumberWithUnit(5.0, Unit::Month)]
+        ));
+        let tokens = parse_expression_chumsky("5 modules").unwrap();
+        assert!(matches!(tokens[1], Token::Variable(_)));
+
+        // In prose "mod" is just a word
+        let tokens = parse_expression_chumsky("ask the mod 5 times").unwrap();
+        assert!(matches!(&tokens[2], Token::Variable(name) if name == "mod"));
+        let tokens = parse_expression_chumsky("mod 5").unwrap();
+        assert!(matches!(tokens[0], Token::Variable(_)));
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        // Two-character comparisons must not be split into single characters
+        let result = parse_expression_chumsky("1 > 2 < 3 >= 4 <= 5 == 6");
+        assert!(result.is_ok(), "Parsing comparisons failed: {:?}", result);
+        let tokens = result.unwrap();
+        assert_eq!(tokens.len(), 11);
+        assert!(matches!(tokens[1], Token::Greater));
+        assert!(matches!(tokens[3], Token::Less));
+        assert!(matches!(tokens[5], Token::GreaterEqual));
+        assert!(matches!(tokens[7], Token::LessEqual));
+        assert!(matches!(tokens[9], Token::Equal));
+
+        // A single '=' is still assignment
+        let tokens = parse_expression_chumsky("x = 5").unwrap();
+        assert!(matches!(tokens[1], Token::Assign));
+
+        // Comparisons followed by another operator are rejected (except negation)
+        assert!(parse_expression_chumsky("5 > * 3").is_err());
+        assert!(parse_expression_chumsky("5 > -3").is_ok());
+    }
+
     #[test]
     fn test_nested_parentheses() {
         let result = parse_expression_chumsky("((1 + 2) * (3 - 4)) / 5");
@@ -720,6 +1150,12 @@ mod tests {
         assert!(result.is_ok(), "Parsing '2^3^2' failed: {:?}", result);
         let tokens = result.unwrap();
         assert_eq!(tokens.len(), 5);
+
+        // "**" is one power operator, not two multiplications
+        let tokens = parse_expression_chumsky("2 ** 10").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(tokens[1], Token::Power));
+        assert!(parse_expression_chumsky("2 * * 3").is_err());
     }
 
     #[test]
@@ -1,11 +1,79 @@
 //! Expression evaluation functions with unit-aware arithmetic
 
-use super::parser::tokenize_with_units;
+use super::chumsky_parser::ParenMismatch;
+use super::parser::{split_unit_assertion, strip_comment, tokenize_with_units};
 use super::tokens::Token;
 use crate::FLOAT_EPSILON;
 use crate::rate_unit;
+use crate::settings::{
+    AdditionUnitPolicy, addition_unit_policy, decimal_comma, strict_expressions,
+};
 use crate::units::{Unit, UnitType, UnitValue, parse_unit};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+thread_local! {
+    /// Reductions recorded while `evaluate_with_steps` is running
+    static STEPS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Record an evaluation step if `evaluate_with_steps` is tracing
+fn record_step(step: impl FnOnce() -> String) {
+    STEPS.with(|steps| {
+        if let Some(steps) = steps.borrow_mut().as_mut() {
+            steps.push(step());
+        }
+    });
+}
+
+/// Evaluate an expression and record how it reduces, as a learning aid
+///
+/// The steps start with the tokens, followed by each operator application and
+/// conversion in the order they happen, e.g. for "(1 GiB + 1 GiB) / 2 to MiB":
+/// "1 GiB + 1 GiB = 2 GiB", "2 GiB / 2 = 1 GiB" and "1 GiB to MiB = 1,024 MiB".
+/// Returns the steps along with the result, if the expression evaluates.
+pub fn evaluate_with_steps(
+    text: &str,
+    variables: &HashMap<String, String>,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> (Vec<String>, Option<String>) {
+    let Some(tokens) = tokenize_with_units(text) else {
+        return (Vec::new(), None);
+    };
+    let tokens = resolve_previous_line_references(tokens, previous_results, current_line);
+    let tokens = resolve_line_values(tokens, previous_results, current_line);
+    let tokens = resolve_line_counts(tokens, previous_results, current_line);
+    let tokens = strip_evaluate_marker(&tokens);
+
+    // Target units are tokenized as "1 <unit>", but are shown the way they were typed
+    let token_list: Vec<String> = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| match token {
+            Token::NumberWithUnit(_, unit) if i > 0 && tokens[i - 1].is_conversion() => {
+                unit.display_name().into_owned()
+            }
+            _ => token.to_string(),
+        })
+        .collect();
+    STEPS.with(|steps| {
+        *steps.borrow_mut() = Some(vec![format!("Tokens: {}", token_list.join(" "))])
+    });
+
+    let result = evaluate_tokens_with_units_and_context_and_variables(
+        tokens,
+        variables,
+        previous_results,
+        current_line,
+    );
+
+    let steps = STEPS
+        .with(|steps| steps.borrow_mut().take())
+        .unwrap_or_default();
+    (steps, result.map(|value| value.format()))
+}
 
 /// Main evaluation function that handles context for line references
 pub fn evaluate_expression_with_context(
@@ -13,11 +81,31 @@ pub fn evaluate_expression_with_context(
     previous_results: &[Option<String>],
     current_line: usize,
 ) -> Option<String> {
+    let text = strip_comment(text);
+    if let Some((expression, unit)) = split_unit_assertion(text) {
+        let result = evaluate_expression_with_context(expression, previous_results, current_line);
+        return result.filter(|result| result_fits_unit(result, &unit));
+    }
+
     // New approach: tokenize everything then find mathematical patterns
     if let Some(tokens) = super::parser::tokenize_with_units(text) {
+        let tokens = resolve_previous_line_references(tokens, previous_results, current_line);
+        let tokens = resolve_line_values(tokens, previous_results, current_line);
+        let tokens = resolve_line_counts(tokens, previous_results, current_line);
+        let tokens = strip_evaluate_marker(&tokens);
+        if has_only_bare_units(text, tokens, &HashMap::new()) {
+            return None;
+        }
+
+        if let Some(type_name) = evaluate_typeof(tokens, |inner| {
+            evaluate_tokens_stream_with_context(inner, previous_results, current_line)
+        }) {
+            return Some(type_name);
+        }
+
         // Try to find and evaluate mathematical patterns in the token stream
         if let Some(result) =
-            evaluate_tokens_stream_with_context(&tokens, previous_results, current_line)
+            evaluate_tokens_stream_with_context(tokens, previous_results, current_line)
         {
             return Some(result.format());
         }
@@ -26,6 +114,48 @@ pub fn evaluate_expression_with_context(
     None
 }
 
+/// Evaluate an expression to its unrounded value, for callers that format it themselves
+pub fn evaluate_expression_value_with_context(
+    text: &str,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> Option<UnitValue> {
+    let text = strip_comment(text);
+    let tokens = tokenize_with_units(text)?;
+    let tokens = resolve_previous_line_references(tokens, previous_results, current_line);
+    let tokens = resolve_line_values(tokens, previous_results, current_line);
+    let tokens = resolve_line_counts(tokens, previous_results, current_line);
+    let tokens = strip_evaluate_marker(&tokens);
+    if has_only_bare_units(text, tokens, &HashMap::new()) {
+        return None;
+    }
+    evaluate_tokens_stream_with_context(tokens, previous_results, current_line)
+}
+
+/// Check whether a line holds a math expression that produces a result
+///
+/// A lone value like "42" or "5 GiB" is trivial and doesn't count; use
+/// [`line_has_expression_with`] to count those too. The line is evaluated on its
+/// own, so references to other lines and variables don't resolve.
+pub fn line_has_expression(text: &str) -> bool {
+    line_has_expression_with(text, false)
+}
+
+/// Like [`line_has_expression`], with `count_lone_values` deciding whether a line
+/// that is only a value (no operator, conversion or function) counts as math
+pub fn line_has_expression_with(text: &str, count_lone_values: bool) -> bool {
+    let Some(tokens) = tokenize_with_units(text) else {
+        return false;
+    };
+    let computes = tokens.iter().any(|t| {
+        t.is_binary_operator()
+            || t.is_conversion()
+            || matches!(t, Token::Of | Token::Function(_) | Token::Question)
+    });
+
+    (count_lone_values || computes) && evaluate_expression_with_context(text, &[], 0).is_some()
+}
+
 /// Find and evaluate mathematical patterns in a token stream
 pub fn evaluate_tokens_stream_with_context(
     tokens: &[Token],
@@ -36,6 +166,15 @@ pub fn evaluate_tokens_stream_with_context(
         return None;
     }
 
+    // In strict mode the whole line must be an expression, prose isn't searched.
+    // There are no variables here, so any word means the line isn't just an expression.
+    if strict_expressions() {
+        if tokens.iter().any(|t| matches!(t, Token::Variable(_))) {
+            return None;
+        }
+        return evaluate_tokens_with_units_and_context(tokens, previous_results, current_line);
+    }
+
     // Look for the longest valid mathematical subsequence
     // Try different starting positions and lengths
     for start in 0..tokens.len() {
@@ -56,21 +195,24 @@ pub fn evaluate_tokens_stream_with_context(
                 // 3. Mixed expressions with conversion at the end (prevents "5 GiB + 10 in seconds" fallback)
                 if start == 0 && end == tokens.len() {
                     let has_math = has_mathematical_operators(subseq);
-                    let has_conversion = subseq.iter().any(|t| matches!(t, Token::To | Token::In));
+                    let has_conversion = subseq.iter().any(|t| t.is_conversion());
 
                     // Check if this is an expression with conversion at the end (like "A + B in C")
                     // These should fail entirely if conversion is impossible, not fall back
-                    let has_conversion_at_end = tokens.len() >= 2
-                        && matches!(tokens[tokens.len() - 2], Token::To | Token::In);
+                    let has_conversion_at_end =
+                        tokens.len() >= 2 && tokens[tokens.len() - 2].is_conversion();
 
                     // Prevent fallback for:
                     // 1. Pure math expressions: has_math && !has_conversion
                     // 2. Pure conversion expressions: has_conversion && !has_math
                     // 3. Mixed expressions with conversion at the end: has_math && has_conversion && has_conversion_at_end
+                    // 4. Conversions in the middle of an expression (prevents "(1 GiB to QPS) + 5"
+                    //    from evaluating as "1 GiB")
                     #[allow(clippy::nonminimal_bool)]
                     if !has_math && has_conversion
                         || has_math && !has_conversion
                         || has_math && has_conversion_at_end
+                        || has_mid_expression_conversion(tokens)
                     {
                         return None; // Fail entirely for these cases
                     }
@@ -85,12 +227,9 @@ pub fn evaluate_tokens_stream_with_context(
 
 /// Check if a token sequence contains mathematical operators
 fn has_mathematical_operators(tokens: &[Token]) -> bool {
-    tokens.iter().any(|t| {
-        matches!(
-            t,
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power
-        )
-    })
+    tokens
+        .iter()
+        .any(|t| t.is_binary_operator() || matches!(t, Token::Question))
 }
 
 /// Check if a token sequence forms a valid mathematical expression
@@ -143,10 +282,7 @@ fn is_valid_mathematical_sequence(tokens: &[Token]) -> bool {
         let is_unit_or_var =
             |t: &Token| matches!(t, Token::NumberWithUnit(_, _) | Token::Variable(_));
 
-        if is_value_or_var(&tokens[0])
-            && matches!(tokens[1], Token::To | Token::In)
-            && is_unit_or_var(&tokens[2])
-        {
+        if is_value_or_var(&tokens[0]) && tokens[1].is_conversion() && is_unit_or_var(&tokens[2]) {
             return true;
         }
 
@@ -159,6 +295,24 @@ fn is_valid_mathematical_sequence(tokens: &[Token]) -> bool {
         }
     }
 
+    // Pattern 2b: Chained conversions (e.g., "5 GiB to MiB to KiB")
+    if tokens.len() > 3 && tokens.len() % 2 == 1 {
+        let is_value = matches!(
+            tokens[0],
+            Token::Number(_)
+                | Token::NumberWithUnit(_, _)
+                | Token::LineReference(_)
+                | Token::Variable(_)
+        );
+        let all_conversions = tokens[1..].chunks(2).all(|pair| {
+            pair[0].is_conversion()
+                && matches!(pair[1], Token::NumberWithUnit(_, _) | Token::Variable(_))
+        });
+        if is_value && all_conversions {
+            return true;
+        }
+    }
+
     // Pattern 3: Function calls (function ( value ))
     if tokens.len() == 4 {
         if let (Token::Function(_), Token::LeftParen, _, Token::RightParen) =
@@ -188,12 +342,7 @@ fn is_valid_mathematical_sequence(tokens: &[Token]) -> bool {
                     | Token::Variable(_)
             )
         };
-        let is_op = |t: &Token| {
-            matches!(
-                t,
-                Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power
-            )
-        };
+        let is_op = |t: &Token| t.is_binary_operator();
 
         if is_value(&tokens[0]) && is_op(&tokens[1]) && is_value(&tokens[2]) {
             return true;
@@ -211,12 +360,9 @@ fn is_valid_mathematical_sequence(tokens: &[Token]) -> bool {
     // Pattern 5: More complex expressions with parentheses, multiple operations
     // For now, if we have values and operators, assume it could be valid
     // The actual evaluation will determine if it's truly valid
-    let has_operator = tokens.iter().any(|t| {
-        matches!(
-            t,
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power
-        )
-    });
+    let has_operator = tokens
+        .iter()
+        .any(|t| t.is_binary_operator() || matches!(t, Token::Question));
 
     has_value && (tokens.len() == 1 || has_operator)
 }
@@ -228,24 +374,72 @@ pub fn evaluate_with_variables(
     previous_results: &[Option<String>],
     current_line: usize,
 ) -> (Option<String>, Option<(String, String)>) {
+    evaluate_with_variables_using(
+        text,
+        &mut |expression| super::parser::tokenize_with_units(expression),
+        variables,
+        previous_results,
+        current_line,
+    )
+}
+
+/// Like [`evaluate_with_variables`], getting the tokens for the expression from
+/// `tokenize`, so callers that keep tokens around don't have to parse text again
+pub fn evaluate_with_variables_using<F>(
+    text: &str,
+    tokenize: &mut F,
+    variables: &HashMap<String, String>,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> (Option<String>, Option<(String, String)>)
+where
+    F: FnMut(&str) -> Option<Vec<Token>>,
+{
     // Return (result, optional_variable_assignment)
+    let text = strip_comment(text);
+
+    // "expr :: GiB" only has a result if it is GiB-compatible
+    if let Some((expression, unit)) = split_unit_assertion(text) {
+        let (result, assignment) = evaluate_with_variables_using(
+            expression,
+            tokenize,
+            variables,
+            previous_results,
+            current_line,
+        );
+        return match result {
+            Some(result) if result_fits_unit(&result, &unit) => (Some(result), assignment),
+            _ => (None, None),
+        };
+    }
 
     // New approach: tokenize everything then find patterns
-    if let Some(tokens) = super::parser::tokenize_with_units(text) {
+    if let Some(tokens) = tokenize(text) {
+        let tokens = resolve_previous_line_references(tokens, previous_results, current_line);
+        let tokens = resolve_line_values(tokens, previous_results, current_line);
+        let tokens = resolve_line_counts(tokens, previous_results, current_line);
+        let tokens = strip_evaluate_marker(&tokens);
+        if has_only_bare_units(text, tokens, variables) {
+            return (None, None);
+        }
+
+        if let Some(type_name) = evaluate_typeof(tokens, |inner| {
+            evaluate_tokens_stream_with_variables(inner, variables, previous_results, current_line)
+        }) {
+            return (Some(type_name), None);
+        }
+
         // First check for variable assignments
         if let Some(assignment) =
-            find_variable_assignment_in_tokens(&tokens, variables, previous_results, current_line)
+            find_variable_assignment_in_tokens(tokens, variables, previous_results, current_line)
         {
             return (Some(assignment.1.clone()), Some(assignment));
         }
 
         // Then look for mathematical expressions
-        if let Some(result) = evaluate_tokens_stream_with_variables(
-            &tokens,
-            variables,
-            previous_results,
-            current_line,
-        ) {
+        if let Some(result) =
+            evaluate_tokens_stream_with_variables(tokens, variables, previous_results, current_line)
+        {
             return (Some(result.format()), None);
         }
     }
@@ -253,6 +447,322 @@ pub fn evaluate_with_variables(
     (None, None)
 }
 
+/// Check if a line without any number only has unit names for values, like "GiB",
+/// "to MiB" or "hours of work", so it has no result
+///
+/// A unit name without a number reads as one of the unit, so "5 * GiB" is 5 GiB,
+/// but on its own it's a label rather than a value. Converting one unit to another
+/// like "GiB to MiB" still shows how many of one fit in the other.
+fn has_only_bare_units(text: &str, tokens: &[Token], variables: &HashMap<String, String>) -> bool {
+    if text.contains(char::is_numeric) {
+        return false;
+    }
+    if let [
+        Token::NumberWithUnit(_, _),
+        keyword,
+        Token::NumberWithUnit(_, _),
+    ] = tokens
+    {
+        if keyword.is_conversion() {
+            return false;
+        }
+    }
+    tokens.iter().all(|token| match token {
+        Token::Number(_)
+        | Token::LineReference(_)
+        | Token::LineValue(_)
+        | Token::LineRange(_, _)
+        | Token::Function(_) => false,
+        Token::Variable(name) => !variables.contains_key(name),
+        _ => true,
+    })
+}
+
+/// Check that a result has a unit that converts to `unit`
+fn result_fits_unit(result: &str, unit: &Unit) -> bool {
+    parse_result_string(result)
+        .is_some_and(|value| value.unit.is_some() && value.to_unit(unit).is_some())
+}
+
+/// Result of evaluating an expression with `evaluate_with_bindings`
+#[derive(Debug, Clone)]
+pub struct EvalResult {
+    /// The value of the expression (or of the right-hand side of an assignment)
+    pub value: UnitValue,
+    /// The variable assigned, for expressions like "y = x * 2"
+    pub assigned_variable: Option<String>,
+}
+
+/// Evaluate an expression against caller-provided variable bindings
+///
+/// Unlike `evaluate_with_variables` there are no other lines, so line references
+/// don't resolve. This is meant for library users embedding mathypad in their own
+/// tools, e.g. evaluating "x * 2 to MiB" with `x` bound to 5 GiB.
+pub fn evaluate_with_bindings(
+    expr: &str,
+    bindings: &HashMap<String, UnitValue>,
+) -> Option<EvalResult> {
+    // Bindings are passed on unrounded, in the same form as results from other lines
+    let variables: HashMap<String, String> = bindings
+        .iter()
+        .map(|(name, binding)| (name.clone(), binding_result_string(binding)))
+        .collect();
+
+    let tokens = tokenize_with_units(expr)?;
+    let tokens = strip_evaluate_marker(&tokens);
+
+    if let [Token::Variable(name), Token::Assign, rhs @ ..] = tokens {
+        let value = evaluate_tokens_with_units_and_context_and_variables(rhs, &variables, &[], 0)?;
+        return Some(EvalResult {
+            value,
+            assigned_variable: Some(name.clone()),
+        });
+    }
+
+    let value = evaluate_tokens_stream_with_variables(tokens, &variables, &[], 0)?;
+    Some(EvalResult {
+        value,
+        assigned_variable: None,
+    })
+}
+
+/// Write a bound value as a result string without rounding it
+fn binding_result_string(binding: &UnitValue) -> String {
+    let number = if decimal_comma() {
+        binding.value.to_string().replace('.', ",")
+    } else {
+        binding.value.to_string()
+    };
+
+    match &binding.unit {
+        Some(unit) => format!("{} {}", number, unit.display_name()),
+        None => number,
+    }
+}
+
+/// Evaluate a `typeof(expr)` line, returning the unit type name of `expr`
+///
+/// The result is a type name like "DataRate" or "Dimensionless" rather than a value,
+/// so it is only supported as a whole line and can't be used inside other expressions.
+fn evaluate_typeof<F>(tokens: &[Token], evaluate: F) -> Option<String>
+where
+    F: FnOnce(&[Token]) -> Option<UnitValue>,
+{
+    let inner = match tokens {
+        [
+            Token::Function(name),
+            Token::LeftParen,
+            inner @ ..,
+            Token::RightParen,
+        ] if name.eq_ignore_ascii_case("typeof") => inner,
+        _ => return None,
+    };
+
+    let value = evaluate(inner)?;
+    let type_name = match &value.unit {
+        Some(unit) => unit.unit_type().name(),
+        None => "Dimensionless",
+    };
+    Some(type_name.to_string())
+}
+
+/// Resolve `prev` and `ans` to references to lines above the current one
+///
+/// `prev` is the line directly above, while `ans` recalls the last answer like a
+/// pocket calculator: the nearest line above that has a result, skipping blank
+/// lines and prose. Both are relative, so unlike "lineN" they never need
+/// renumbering when lines are inserted or deleted. Without such a line (e.g. on
+/// the first line, or in one-shot mode) they are left as (undefined) variables.
+fn resolve_previous_line_references(
+    tokens: Vec<Token>,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> Vec<Token> {
+    if current_line == 0 {
+        return tokens;
+    }
+    let last_answer = previous_results[..current_line.min(previous_results.len())]
+        .iter()
+        .rposition(Option::is_some);
+
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Variable(name) if name.eq_ignore_ascii_case("prev") => {
+                Token::LineReference(current_line - 1)
+            }
+            Token::Variable(name) if name.eq_ignore_ascii_case("ans") => match last_answer {
+                Some(index) => Token::LineReference(index),
+                None => Token::Variable(name),
+            },
+            token => token,
+        })
+        .collect()
+}
+
+/// Resolve "lineN.value" to the number from that line's result, without its unit
+///
+/// A line without a result is left as a plain reference to it, so it fails to
+/// evaluate the same way "lineN" would.
+fn resolve_line_values(
+    tokens: Vec<Token>,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> Vec<Token> {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::LineValue(index) => {
+                match resolve_line_reference(index, previous_results, current_line) {
+                    Some(value) => Token::Number(value.value),
+                    None => Token::LineReference(index),
+                }
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// Resolve "count(lineA..lineB)" to how many lines in that range have a result
+///
+/// Only lines above the current one are counted, like any line reference, and the
+/// range can be written either way round. The count is a plain number.
+fn resolve_line_counts(
+    tokens: Vec<Token>,
+    previous_results: &[Option<String>],
+    current_line: usize,
+) -> Vec<Token> {
+    let mut resolved = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let [
+            Token::Function(name),
+            Token::LeftParen,
+            Token::LineRange(start, end),
+            Token::RightParen,
+            ..,
+        ] = &tokens[i..]
+        {
+            if name.eq_ignore_ascii_case("count") {
+                let (first, last) = (*start.min(end), *start.max(end));
+                let count = (first..=last)
+                    .filter(|&line| line < current_line)
+                    .filter(|&line| matches!(previous_results.get(line), Some(Some(_))))
+                    .count();
+                resolved.push(Token::Number(count as f64));
+                i += 4;
+                continue;
+            }
+        }
+        resolved.push(tokens[i].clone());
+        i += 1;
+    }
+    resolved
+}
+
+/// Strip a trailing "=" evaluate marker (e.g., "5 * 3 =") from a token stream
+///
+/// Only a trailing "=" is a marker: "x = 5" is an assignment, while "x =" shows
+/// the current value of x.
+fn strip_evaluate_marker(tokens: &[Token]) -> &[Token] {
+    match tokens {
+        [rest @ .., Token::Assign] if !rest.is_empty() => rest,
+        _ => tokens,
+    }
+}
+
+/// Classification of why a line produced no result
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluationError {
+    /// The line assigns a variable that (directly or indirectly) depends on itself
+    CircularReference,
+    /// The line asserts a unit ("... :: GiB") that its result doesn't convert to
+    UnitAssertionFailed(Unit),
+    /// The line's parentheses don't balance; the offset is a byte offset into the line
+    UnbalancedParenthesis(ParenMismatch),
+}
+
+/// Find assignment lines that are part of a circular variable reference
+///
+/// A variable used on a line resolves to its most recent assignment above that line.
+/// If there is none, it resolves to the first assignment at or below the line, which
+/// only has a value left over from an earlier edit. Lines that reach themselves through
+/// these dependencies (e.g., "a = b + 1" and "b = a + 1") are returned.
+pub fn find_circular_assignment_lines(lines: &[String]) -> HashSet<usize> {
+    find_circular_assignment_lines_using(lines, |_, expression| tokenize_with_units(expression))
+}
+
+/// Find circular assignment lines like `find_circular_assignment_lines`, reading
+/// tokens with `tokenize`, which gets the line index and the expression to read
+pub fn find_circular_assignment_lines_using<F>(lines: &[String], mut tokenize: F) -> HashSet<usize>
+where
+    F: FnMut(usize, &str) -> Option<Vec<Token>>,
+{
+    // Collect (assigned variable, referenced variables) for every assignment line
+    let assignments: Vec<Option<(String, Vec<String>)>> = lines
+        .iter()
+        .enumerate()
+        .map(|(line_index, line)| {
+            // Only the first of several ";"-separated expressions is the line's assignment
+            let line = strip_comment(line);
+            let expression = line.split(';').next().unwrap_or(line);
+            let tokens = tokenize(line_index, expression)?;
+            match (tokens.first(), tokens.get(1)) {
+                (Some(Token::Variable(name)), Some(Token::Assign)) => {
+                    let references = tokens[2..]
+                        .iter()
+                        .filter_map(|t| match t {
+                            Token::Variable(var) => Some(var.clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    Some((name.clone(), references))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    // Find the assignment line each variable reference resolves to
+    let defining_line = |var: &str, line_index: usize| -> Option<usize> {
+        let is_definition = |i: &usize| matches!(&assignments[*i], Some((name, _)) if name == var);
+        (0..line_index)
+            .rev()
+            .find(is_definition)
+            .or_else(|| (line_index..assignments.len()).find(is_definition))
+    };
+
+    let dependencies: Vec<Vec<usize>> = assignments
+        .iter()
+        .enumerate()
+        .map(|(i, assignment)| match assignment {
+            Some((_, references)) => references
+                .iter()
+                .filter_map(|var| defining_line(var, i))
+                .collect(),
+            None => Vec::new(),
+        })
+        .collect();
+
+    // A line is circular if following its dependencies leads back to it
+    let mut circular = HashSet::new();
+    for start in 0..dependencies.len() {
+        let mut visited = HashSet::new();
+        let mut pending = dependencies[start].clone();
+        while let Some(line) = pending.pop() {
+            if line == start {
+                circular.insert(start);
+                break;
+            }
+            if visited.insert(line) {
+                pending.extend(dependencies[line].iter().copied());
+            }
+        }
+    }
+
+    circular
+}
+
 /// Find variable assignment pattern in token stream
 fn find_variable_assignment_in_tokens(
     tokens: &[Token],
@@ -292,6 +802,19 @@ fn evaluate_tokens_stream_with_variables(
         return None;
     }
 
+    // In strict mode the whole line must be an expression, prose isn't searched
+    if strict_expressions() {
+        if !all_variables_defined(tokens, variables) {
+            return None;
+        }
+        return evaluate_tokens_with_units_and_context_and_variables(
+            tokens,
+            variables,
+            previous_results,
+            current_line,
+        );
+    }
+
     // First check if we have undefined variables in what looks like a mathematical context
     if has_undefined_variables_in_math_context(tokens, variables) {
         return None; // Fail entirely if undefined variables are in mathematical expressions
@@ -320,10 +843,14 @@ fn evaluate_tokens_stream_with_variables(
                 // Note: Mixed expressions (both math and conversion) allow fallback for partial evaluation
                 if start == 0 && end == tokens.len() {
                     let has_math = has_mathematical_operators(subseq);
-                    let has_conversion = subseq.iter().any(|t| matches!(t, Token::To | Token::In));
+                    let has_conversion = subseq.iter().any(|t| t.is_conversion());
 
-                    // Prevent fallback only for pure expressions that fail
-                    if (has_math && !has_conversion) || (has_conversion && !has_math) {
+                    // Prevent fallback only for pure expressions that fail, and for
+                    // conversions in the middle of an expression
+                    if (has_math && !has_conversion)
+                        || (has_conversion && !has_math)
+                        || has_mid_expression_conversion(tokens)
+                    {
                         return None; // Fail entirely for pure expressions
                     }
                     // For mixed expressions (has_math && has_conversion), allow fallback
@@ -335,6 +862,18 @@ fn evaluate_tokens_stream_with_variables(
     None
 }
 
+/// Check if a conversion target is followed by more of the expression, like the
+/// "to MiB" in "1 GiB to MiB + 512 KiB" or "(1 GiB to MiB) * 2"
+fn has_mid_expression_conversion(tokens: &[Token]) -> bool {
+    tokens.windows(3).any(|window| {
+        window[0].is_conversion()
+            && matches!(window[1], Token::NumberWithUnit(_, _))
+            && (window[2].is_binary_operator()
+                || window[2].is_conversion()
+                || matches!(window[2], Token::RightParen))
+    })
+}
+
 /// Check if there are undefined variables in what appears to be a mathematical context
 fn has_undefined_variables_in_math_context(
     tokens: &[Token],
@@ -369,10 +908,18 @@ fn is_math_token(token: &Token) -> bool {
             | Token::Multiply
             | Token::Divide
             | Token::Power
+            | Token::Modulo
+            | Token::OutOf
+            | Token::Greater
+            | Token::Less
+            | Token::GreaterEqual
+            | Token::LessEqual
+            | Token::Equal
             | Token::LeftParen
             | Token::RightParen
             | Token::To
             | Token::In
+            | Token::As
             | Token::Function(_)
     )
 }
@@ -409,6 +956,21 @@ pub fn evaluate_tokens_with_units_and_context(
         return None;
     }
 
+    // Handle conditionals like "a > b ? x : y" (lowest precedence, right-associative)
+    if let Some((question, colon)) = find_conditional_split(tokens) {
+        let condition = evaluate_tokens_with_units_and_context(
+            &tokens[..question],
+            previous_results,
+            current_line,
+        )?;
+        let branch = if condition.is_truthy() {
+            &tokens[question + 1..colon]
+        } else {
+            &tokens[colon + 1..]
+        };
+        return evaluate_tokens_with_units_and_context(branch, previous_results, current_line);
+    }
+
     // Handle simple conversion expressions like "1 GiB to KiB" (only if it's the entire expression)
     if tokens.len() == 3 {
         if let (
@@ -443,18 +1005,36 @@ pub fn evaluate_tokens_with_units_and_context(
         }
     }
 
-    // Check if we have an "in" or "to" conversion request at the end
+    // Conversions inside parentheses only apply to the parenthesized group
+    if let Some(group) = find_parenthesized_conversion(tokens) {
+        return evaluate_parenthesized_conversion(tokens, group, |part| {
+            evaluate_tokens_with_units_and_context(part, previous_results, current_line)
+        });
+    }
+
+    // Check if we have an "in", "to" or "as" conversion request at the end
     let mut target_unit_for_conversion = None;
+    let mut is_unit_cast = false;
     let mut evaluation_tokens = tokens;
 
-    // Look for "in" or "to" followed by a unit at the end
+    // Look for "in", "to" or "as" followed by a unit at the end
     for i in 0..tokens.len().saturating_sub(1) {
-        if let Token::In | Token::To = &tokens[i] {
-            // Look for unit after "in" or "to"
+        if tokens[i].is_conversion() {
+            // Look for unit after "in", "to" or "as"
             for j in (i + 1)..tokens.len() {
                 if let Token::NumberWithUnit(_, unit) = &tokens[j] {
+                    if j + 1 < tokens.len() {
+                        return evaluate_chained_conversion(tokens, j, |part| {
+                            evaluate_tokens_with_units_and_context(
+                                part,
+                                previous_results,
+                                current_line,
+                            )
+                        });
+                    }
                     target_unit_for_conversion = Some(unit.clone());
-                    evaluation_tokens = &tokens[..i]; // Evaluate everything before "in"/"to"
+                    is_unit_cast = matches!(tokens[i], Token::As);
+                    evaluation_tokens = &tokens[..i]; // Evaluate everything before the keyword
                     break;
                 }
             }
@@ -484,18 +1064,9 @@ pub fn evaluate_tokens_with_units_and_context(
                     return None; // Invalid or circular reference
                 }
             }
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power => {
+            _ if token.is_binary_operator() => {
                 while let Some(top_op) = operator_stack.last() {
-                    // Power is right-associative, others are left-associative
-                    let should_pop = if matches!(token, Token::Power) {
-                        // For right-associative operators, pop only if top has higher precedence
-                        precedence_unit(token) < precedence_unit(top_op)
-                    } else {
-                        // For left-associative operators, pop if top has same or higher precedence
-                        precedence_unit(token) <= precedence_unit(top_op)
-                    };
-
-                    if should_pop {
+                    if should_pop_operator(token, top_op) {
                         let op = operator_stack.pop().unwrap();
                         if !apply_operator_with_units(&mut value_stack, &op) {
                             return None;
@@ -551,7 +1122,22 @@ pub fn evaluate_tokens_with_units_and_context(
 
         // If we have a target unit for conversion, convert the result
         if let Some(target_unit) = target_unit_for_conversion {
-            if let Some(converted) = result.to_unit(&target_unit) {
+            if is_unit_cast {
+                // "as" attaches the unit to a plain number without converting it.
+                // Values that already have a unit are rejected rather than reinterpreted.
+                if result.unit.is_some() {
+                    return None;
+                }
+                result = UnitValue::new(result.value, Some(target_unit));
+            } else if let Some(converted) = result.to_unit(&target_unit) {
+                record_step(|| {
+                    format!(
+                        "{} to {} = {}",
+                        result.format(),
+                        target_unit.display_name(),
+                        converted.format()
+                    )
+                });
                 result = converted;
             } else {
                 return None; // Explicit conversion failed, fail the entire expression
@@ -575,6 +1161,27 @@ fn evaluate_tokens_with_units_and_context_and_variables(
         return None;
     }
 
+    // Handle conditionals like "a > b ? x : y" (lowest precedence, right-associative)
+    if let Some((question, colon)) = find_conditional_split(tokens) {
+        let condition = evaluate_tokens_with_units_and_context_and_variables(
+            &tokens[..question],
+            variables,
+            previous_results,
+            current_line,
+        )?;
+        let branch = if condition.is_truthy() {
+            &tokens[question + 1..colon]
+        } else {
+            &tokens[colon + 1..]
+        };
+        return evaluate_tokens_with_units_and_context_and_variables(
+            branch,
+            variables,
+            previous_results,
+            current_line,
+        );
+    }
+
     // Handle simple conversion expressions like "1 GiB to KiB" (only if it's the entire expression)
     if tokens.len() == 3 {
         if let (
@@ -611,18 +1218,42 @@ fn evaluate_tokens_with_units_and_context_and_variables(
         }
     }
 
-    // Check if we have an "in" or "to" conversion request at the end
+    // Conversions inside parentheses only apply to the parenthesized group
+    if let Some(group) = find_parenthesized_conversion(tokens) {
+        return evaluate_parenthesized_conversion(tokens, group, |part| {
+            evaluate_tokens_with_units_and_context_and_variables(
+                part,
+                variables,
+                previous_results,
+                current_line,
+            )
+        });
+    }
+
+    // Check if we have an "in", "to" or "as" conversion request at the end
     let mut target_unit_for_conversion = None;
+    let mut is_unit_cast = false;
     let mut evaluation_tokens = tokens;
 
-    // Look for "in" or "to" followed by a unit at the end
+    // Look for "in", "to" or "as" followed by a unit at the end
     for i in 0..tokens.len().saturating_sub(1) {
-        if let Token::In | Token::To = &tokens[i] {
-            // Look for unit after "in" or "to"
+        if tokens[i].is_conversion() {
+            // Look for unit after "in", "to" or "as"
             for j in (i + 1)..tokens.len() {
                 if let Token::NumberWithUnit(_, unit) = &tokens[j] {
+                    if j + 1 < tokens.len() {
+                        return evaluate_chained_conversion(tokens, j, |part| {
+                            evaluate_tokens_with_units_and_context_and_variables(
+                                part,
+                                variables,
+                                previous_results,
+                                current_line,
+                            )
+                        });
+                    }
                     target_unit_for_conversion = Some(unit.clone());
-                    evaluation_tokens = &tokens[..i]; // Evaluate everything before "in"/"to"
+                    is_unit_cast = matches!(tokens[i], Token::As);
+                    evaluation_tokens = &tokens[..i]; // Evaluate everything before the keyword
                     break;
                 }
             }
@@ -660,18 +1291,9 @@ fn evaluate_tokens_with_units_and_context_and_variables(
                     return None; // Undefined variable
                 }
             }
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power => {
+            _ if token.is_binary_operator() => {
                 while let Some(top_op) = operator_stack.last() {
-                    // Power is right-associative, others are left-associative
-                    let should_pop = if matches!(token, Token::Power) {
-                        // For right-associative operators, pop only if top has higher precedence
-                        precedence_unit(token) < precedence_unit(top_op)
-                    } else {
-                        // For left-associative operators, pop if top has same or higher precedence
-                        precedence_unit(token) <= precedence_unit(top_op)
-                    };
-
-                    if should_pop {
+                    if should_pop_operator(token, top_op) {
                         let op = operator_stack.pop().unwrap();
                         if !apply_operator_with_units(&mut value_stack, &op) {
                             return None;
@@ -727,7 +1349,22 @@ fn evaluate_tokens_with_units_and_context_and_variables(
 
         // If we have a target unit for conversion, convert the result
         if let Some(target_unit) = target_unit_for_conversion {
-            if let Some(converted) = result.to_unit(&target_unit) {
+            if is_unit_cast {
+                // "as" attaches the unit to a plain number without converting it.
+                // Values that already have a unit are rejected rather than reinterpreted.
+                if result.unit.is_some() {
+                    return None;
+                }
+                result = UnitValue::new(result.value, Some(target_unit));
+            } else if let Some(converted) = result.to_unit(&target_unit) {
+                record_step(|| {
+                    format!(
+                        "{} to {} = {}",
+                        result.format(),
+                        target_unit.display_name(),
+                        converted.format()
+                    )
+                });
                 result = converted;
             } else {
                 return None; // Explicit conversion failed, fail the entire expression
@@ -740,6 +1377,130 @@ fn evaluate_tokens_with_units_and_context_and_variables(
     }
 }
 
+/// Find the innermost parenthesized group containing a conversion, like the
+/// "(1 GiB to MiB)" in "(1 GiB to MiB) + 512 KiB"
+/// Returns the token positions of the opening and closing parentheses
+fn find_parenthesized_conversion(tokens: &[Token]) -> Option<(usize, usize)> {
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen => open.push(i),
+            Token::RightParen => {
+                let start = open.pop()?;
+                if tokens[start + 1..i].iter().any(|t| t.is_conversion()) {
+                    return Some((start, i));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Evaluate a parenthesized conversion on its own and continue with its value in
+/// place of the group, so "(1 GiB to MiB) * 2" is "2,048 MiB"
+fn evaluate_parenthesized_conversion<F>(
+    tokens: &[Token],
+    (open, close): (usize, usize),
+    evaluate: F,
+) -> Option<UnitValue>
+where
+    F: Fn(&[Token]) -> Option<UnitValue>,
+{
+    let inner = evaluate(&tokens[open + 1..close])?;
+    let mut folded = tokens[..=open].to_vec();
+    folded.push(unit_value_token(&inner));
+    folded.extend_from_slice(&tokens[close..]);
+    Some(carry_approximate(evaluate(&folded)?, &inner, &folded))
+}
+
+/// Turn an evaluated value back into a token so evaluation can continue from it
+fn unit_value_token(value: &UnitValue) -> Token {
+    match &value.unit {
+        Some(unit) => Token::NumberWithUnit(value.value, unit.clone()),
+        None => Token::Number(value.value),
+    }
+}
+
+/// Keep the "≈" of a value that evaluation continued from as a token, so
+/// "(1 GB to GiB) * 2" is approximate too. Comparisons are plain true or false.
+fn carry_approximate(mut result: UnitValue, folded: &UnitValue, tokens: &[Token]) -> UnitValue {
+    if folded.approximate && !tokens.iter().any(Token::is_comparison) {
+        result.approximate = true;
+    }
+    result
+}
+
+/// Evaluate an expression that continues after a conversion, like "1 GiB to MiB + 512 KiB"
+///
+/// Outside of parentheses, a conversion applies to everything on its left, and the rest of the expression
+/// continues from the converted value: "1 GiB to MiB + 512 KiB" is "1,024.5 MiB" and
+/// "5 GiB to MiB to KiB" chains both conversions. Unless a later conversion picks
+/// another unit, the result stays in the first target unit when it can be expressed
+/// in it. Anything other than an operator or another conversion after the target
+/// unit (e.g., "1 GiB to MiB 5") is rejected. Parenthesized conversions are
+/// evaluated first by `evaluate_parenthesized_conversion`.
+fn evaluate_chained_conversion<F>(
+    tokens: &[Token],
+    unit_index: usize,
+    evaluate: F,
+) -> Option<UnitValue>
+where
+    F: Fn(&[Token]) -> Option<UnitValue>,
+{
+    let rest = &tokens[unit_index + 1..];
+    if !rest
+        .first()
+        .is_some_and(|token| token.is_binary_operator() || token.is_conversion())
+    {
+        return None;
+    }
+
+    let converted = evaluate(&tokens[..=unit_index])?;
+    let mut chained = vec![unit_value_token(&converted)];
+    chained.extend_from_slice(rest);
+
+    let result = carry_approximate(evaluate(&chained)?, &converted, &chained);
+    match converted.unit {
+        Some(unit) if !rest.iter().any(|token| token.is_conversion()) => {
+            Some(result.to_unit(&unit).unwrap_or(result))
+        }
+        _ => Some(result),
+    }
+}
+
+/// Find the top-level "?" and its matching ":" in a conditional expression
+/// Returns the token positions of both, or None if the tokens aren't a conditional
+fn find_conditional_split(tokens: &[Token]) -> Option<(usize, usize)> {
+    let mut paren_depth = 0;
+    let mut question = None;
+    let mut nested = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen => paren_depth += 1,
+            Token::RightParen => paren_depth -= 1,
+            Token::Question if paren_depth == 0 => {
+                if question.is_none() {
+                    question = Some(i);
+                } else {
+                    // A conditional nested inside the "then" branch
+                    nested += 1;
+                }
+            }
+            Token::Colon if paren_depth == 0 && question.is_some() => {
+                if nested == 0 {
+                    return Some((question?, i));
+                }
+                nested -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
 /// Resolve a variable to its UnitValue
 fn resolve_variable(var_name: &str, variables: &HashMap<String, String>) -> Option<UnitValue> {
     if let Some(var_value_str) = variables.get(var_name) {
@@ -751,6 +1512,11 @@ fn resolve_variable(var_name: &str, variables: &HashMap<String, String>) -> Opti
 }
 
 /// Resolve a line reference to its calculated result
+///
+/// Lines can only reference lines above them. A reference to the current line or a
+/// line below it resolves to None, even if that line had a result before the current
+/// edit, so "line2 to GiB" written on line 2 never reuses its own stale value. Use
+/// `prev` to refer to the line directly above instead.
 pub fn resolve_line_reference(
     line_index: usize,
     previous_results: &[Option<String>],
@@ -773,37 +1539,106 @@ pub fn resolve_line_reference(
 }
 
 /// Parse a result string back into a UnitValue
+///
+/// Reads results as they are shown, like "14 GiB", "1,234.5 $/h" or "≈ 0.931 GiB",
+/// and also values written the way they are typed: with a currency symbol in front
+/// ("$5/h", "-€20"), spaces around a rate's "/" ("5 MB / hour") or an exponent
+/// ("1.5e9 B"). The unit has to be a known one, so "5 apples" isn't a value.
 pub fn parse_result_string(result_str: &str) -> Option<UnitValue> {
-    // Parse a result string like "14 GiB" or "42" back into a UnitValue
-    let parts: Vec<&str> = result_str.split_whitespace().collect();
+    // A line with several "; "-separated results stands for its first one
+    let result_str = result_str.split(';').next().unwrap_or(result_str).trim();
+    // Approximate results like "≈ 0.931 GiB" stay approximate where they're used
+    let (approximate, result_str) = match result_str.strip_prefix('≈') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, result_str),
+    };
 
-    if parts.is_empty() {
-        return None;
-    }
+    let (negative, text) = match result_str.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, result_str),
+    };
 
-    // Try to parse the first part as a number
-    let number_str = parts[0].replace(",", ""); // Remove commas
-    if let Ok(value) = number_str.parse::<f64>() {
-        if parts.len() == 1 {
-            // Just a number
-            return Some(UnitValue::new(value, None));
-        } else if parts.len() == 2 {
-            // Number with unit
-            if let Some(unit) = parse_unit(parts[1]) {
-                return Some(UnitValue::new(value, Some(unit)));
-            }
+    // A currency symbol in front of the number belongs to the unit: "$5/h" is "5 $/h"
+    let symbol_len = text
+        .find(|c: char| c.is_alphanumeric() || c.is_whitespace() || c == '.')
+        .unwrap_or(text.len());
+    let (symbol, text) = text.split_at(symbol_len);
+    let text = text.trim_start();
+
+    let (number, rest) = text.split_at(leading_number_len(text));
+    let rest = rest.trim();
+    let unit_text = match (symbol, rest) {
+        (symbol, "") => Cow::Borrowed(symbol),
+        ("", rest) => Cow::Borrowed(rest),
+        (symbol, rest) if rest.starts_with('/') => Cow::Owned(format!("{}{}", symbol, rest)),
+        _ => return None,
+    };
+
+    // Remove thousands separators, which are dots in European style
+    let number = if decimal_comma() {
+        number.replace('.', "").replace(',', ".")
+    } else {
+        number.replace(',', "")
+    };
+    let value = number.parse::<f64>().ok()?;
+    let value = if negative { -value } else { value };
+
+    let unit = if unit_text.is_empty() {
+        None
+    } else {
+        Some(parse_unit(&unit_text)?)
+    };
+    Some(UnitValue {
+        value,
+        unit,
+        approximate,
+    })
+}
+
+/// Length of the number at the start of `text`, with its separators and exponent
+fn leading_number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = bytes
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || matches!(b, b',' | b'.'))
+        .count();
+
+    // An exponent like "e9" or "e-5" is only part of the number when digits follow
+    if len > 0 && matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let digits = bytes[len + 1 + sign..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits > 0 {
+            len += 1 + sign + digits;
         }
     }
+    len
+}
 
-    None
+/// Decide whether the operator on top of the stack is applied before pushing `token`
+///
+/// Power is right-associative ("2 ^ 3 ^ 2" is "2 ^ 9"), so it only yields to
+/// higher precedence. Everything else is left-associative and also yields to equal
+/// precedence, so "100 / 10 / 2" is "(100 / 10) / 2" and "20 - 5 - 3" is
+/// "(20 - 5) - 3". Parentheses have precedence 0 and are never popped here.
+fn should_pop_operator(token: &Token, top_op: &Token) -> bool {
+    if matches!(token, Token::Power) {
+        precedence_unit(token) < precedence_unit(top_op)
+    } else {
+        precedence_unit(token) <= precedence_unit(top_op)
+    }
 }
 
 /// Get operator precedence for unit-aware evaluation
 fn precedence_unit(token: &Token) -> i32 {
     match token {
-        Token::Plus | Token::Minus => 1,
-        Token::Multiply | Token::Divide => 2,
-        Token::Power => 3, // Highest precedence
+        _ if token.is_comparison() => 1, // Lowest precedence: compare whole sides
+        Token::OutOf => 2,               // "a + b out of c" is the share of the whole sum
+        Token::Plus | Token::Minus => 3,
+        Token::Multiply | Token::Divide | Token::Modulo => 4,
+        Token::Power => 5, // Highest precedence
         _ => 0,
     }
 }
@@ -816,23 +1651,27 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
 
     let b = stack.pop().unwrap();
     let a = stack.pop().unwrap();
+    let b = match op {
+        Token::Plus | Token::Minus => align_bits_and_bytes(&a, b),
+        _ => b,
+    };
 
-    let result = match op {
+    let mut result = match op {
         Token::Plus => {
             // Addition: units must be compatible
             match (&a.unit, &b.unit) {
+                // Adding a percent of the value itself: "50 + 20%" is 60
+                (unit_a, Some(Unit::Percent)) if unit_a != &Some(Unit::Percent) => {
+                    let fraction = Unit::Percent.to_base_value(b.value);
+                    UnitValue::new(a.value * (1.0 + fraction), a.unit.clone())
+                }
                 (Some(unit_a), Some(unit_b)) => {
                     if unit_a.is_compatible_for_addition(unit_b) {
                         let base_a = unit_a.to_base_value(a.value);
                         let base_b = unit_b.to_base_value(b.value);
                         let result_base = base_a + base_b;
 
-                        // Choose the smaller unit (larger value) for the result
-                        let result_unit = if unit_a.to_base_value(1.0) < unit_b.to_base_value(1.0) {
-                            unit_a
-                        } else {
-                            unit_b
-                        };
+                        let result_unit = addition_result_unit(unit_a, unit_b);
                         let result_value = result_unit.clone().from_base_value(result_base);
                         UnitValue::new(result_value, Some(result_unit.clone()))
                     } else {
@@ -844,20 +1683,21 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
             }
         }
         Token::Minus => {
-            // Subtraction: units must be compatible
+            // Subtraction: units must be compatible. Percent minus percent is in
+            // percentage points ("50% - 20%" is 30 %), while a percent taken off
+            // anything else is a reduction ("50 - 20%" is 40)
             match (&a.unit, &b.unit) {
+                (unit_a, Some(Unit::Percent)) if unit_a != &Some(Unit::Percent) => {
+                    let fraction = Unit::Percent.to_base_value(b.value);
+                    UnitValue::new(a.value * (1.0 - fraction), a.unit.clone())
+                }
                 (Some(unit_a), Some(unit_b)) => {
                     if unit_a.is_compatible_for_addition(unit_b) {
                         let base_a = unit_a.to_base_value(a.value);
                         let base_b = unit_b.to_base_value(b.value);
                         let result_base = base_a - base_b;
 
-                        // Choose the smaller unit (larger value) for the result
-                        let result_unit = if unit_a.to_base_value(1.0) < unit_b.to_base_value(1.0) {
-                            unit_a
-                        } else {
-                            unit_b
-                        };
+                        let result_unit = addition_result_unit(unit_a, unit_b);
                         let result_value = result_unit.clone().from_base_value(result_base);
                         UnitValue::new(result_value, Some(result_unit.clone()))
                     } else {
@@ -969,6 +1809,16 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
                     };
                     UnitValue::new(rate_value * time_in_seconds, Some(request_unit))
                 }
+                // Requests * Data/Request = Data (e.g., 1000 req * 2 KB/req = 2,000 KB)
+                (Some(count_unit), Some(Unit::RateUnit(size_unit, per_unit)))
+                | (Some(Unit::RateUnit(size_unit, per_unit)), Some(count_unit))
+                    if count_unit.unit_type() == UnitType::Request
+                        && size_unit.unit_type() == UnitType::Data
+                        && per_unit.unit_type() == UnitType::Request =>
+                {
+                    // Requests and queries count the same, so the values multiply directly
+                    UnitValue::new(a.value * b.value, Some(size_unit.as_ref().clone()))
+                }
                 // Data * Currency/Data Rate = Currency (e.g., 1 TiB * $5/GiB = $5120)
                 (Some(data_unit), Some(Unit::RateUnit(rate_numerator, rate_denominator)))
                     if data_unit.unit_type() == UnitType::Data
@@ -1015,10 +1865,9 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
                 {
                     // Check if this is a generic rate unit (exclude currency/data rates)
                     if let Unit::RateUnit(rate_data, rate_time) = rate_unit {
-                        // Skip currency/data rates (they should be handled above)
-                        if rate_data.unit_type() == UnitType::Currency
-                            && rate_time.unit_type() == UnitType::Data
-                        {
+                        // Skip rates that aren't per time, like $/GiB or KB/req (they
+                        // should be handled above)
+                        if rate_time.unit_type() != UnitType::Time {
                             return false;
                         }
                         let (time_value, rate_value) = if time_unit.unit_type() == UnitType::Time {
@@ -1146,6 +1995,30 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
                     );
                     UnitValue::new(a.value / b.value, Some(rate_unit))
                 }
+                // Data / Requests = Data/Request (e.g., 2 GB / 1000 req = 2 MB/req)
+                (Some(data_unit), Some(count_unit))
+                    if data_unit.unit_type() == UnitType::Data
+                        && count_unit.unit_type() == UnitType::Request =>
+                {
+                    let rate_unit =
+                        Unit::RateUnit(Box::new(data_unit.clone()), Box::new(count_unit.clone()));
+                    UnitValue::new(a.value / b.value, Some(rate_unit))
+                }
+                // Data / Data/Request = Requests (e.g., 2 GB / 2 KB/req = 1,000,000 req)
+                (Some(data_unit), Some(Unit::RateUnit(size_unit, per_unit)))
+                    if data_unit.unit_type() == UnitType::Data
+                        && per_unit.unit_type() == UnitType::Request
+                        && size_unit.unit_type() == UnitType::Data =>
+                {
+                    let size_base = size_unit.to_base_value(b.value);
+                    if size_base.abs() < FLOAT_EPSILON {
+                        return false;
+                    }
+                    UnitValue::new(
+                        data_unit.to_base_value(a.value) / size_base,
+                        Some(per_unit.as_ref().clone()),
+                    )
+                }
                 // Data / DataRate = Time
                 (Some(data_unit), Some(rate_unit))
                     if data_unit.unit_type() == UnitType::Data
@@ -1223,6 +2096,30 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
                     let time_in_seconds = data_in_bits / rate_in_bits_per_sec;
                     UnitValue::new(time_in_seconds, Some(Unit::Second))
                 }
+                // Requests / RequestRate = Time, Currency / CurrencyRate = Time
+                (Some(amount_unit), Some(Unit::RateUnit(rate_amount, rate_time)))
+                    if matches!(
+                        amount_unit.unit_type(),
+                        UnitType::Request | UnitType::Currency
+                    ) && amount_unit.unit_type() == rate_amount.unit_type()
+                        && rate_time.unit_type() == UnitType::Time =>
+                {
+                    // Cannot divide different currencies without exchange rates
+                    if amount_unit.unit_type() == UnitType::Currency
+                        && amount_unit != rate_amount.as_ref()
+                    {
+                        return false;
+                    }
+                    let rate_amount_base = rate_amount.to_base_value(b.value);
+                    if rate_amount_base.abs() < FLOAT_EPSILON {
+                        return false;
+                    }
+                    // The time comes out in the rate's own time unit, like generic data rates
+                    UnitValue::new(
+                        amount_unit.to_base_value(a.value) / rate_amount_base,
+                        Some(rate_time.as_ref().clone()),
+                    )
+                }
                 (Some(rate_unit), Some(time_unit))
                     if rate_unit.unit_type() == UnitType::RequestRate
                         && time_unit.unit_type() == UnitType::Time =>
@@ -1308,15 +2205,110 @@ fn apply_operator_with_units(stack: &mut Vec<UnitValue>, op: &Token) -> bool {
                 _ => return false, // Can't raise units to powers or use units as exponents
             }
         }
+        Token::Modulo => {
+            // Remainder of a floored division, so it has the sign of the divisor.
+            // A unit value divided by a compatible one keeps the left unit
+            // ("100 min mod 1 hour" is "40 min").
+            let divisor = match (&a.unit, &b.unit) {
+                (_, None) => b.value,
+                (Some(unit_a), Some(_)) => match b.to_unit(unit_a) {
+                    Some(converted) => converted.value,
+                    None => return false,
+                },
+                (None, Some(_)) => return false,
+            };
+            if divisor.abs() < FLOAT_EPSILON {
+                return false;
+            }
+            let remainder = a.value - divisor * (a.value / divisor).floor();
+            UnitValue::new(remainder, a.unit.clone())
+        }
+        Token::OutOf => {
+            // The share of the whole as a percentage, so both sides must be
+            // plain numbers or of the same kind ("750 GiB out of 1 TiB")
+            let whole = match (&a.unit, &b.unit) {
+                (None, None) => b.value,
+                (Some(unit_a), Some(_)) => match b.to_unit(unit_a) {
+                    Some(converted) => converted.value,
+                    None => return false,
+                },
+                _ => return false,
+            };
+            if whole.abs() < FLOAT_EPSILON {
+                return false;
+            }
+            UnitValue::new(a.value / whole * 100.0, Some(Unit::Percent))
+        }
+        _ if op.is_comparison() => match compare_unit_values(&a, &b, op) {
+            Some(outcome) => UnitValue::from_bool(outcome),
+            None => return false,
+        },
         _ => return false,
     };
 
+    // Overflow (e.g. "1e308 * 10") has no meaningful value, so the expression fails
+    if !result.value.is_finite() {
+        return false;
+    }
+    // Arithmetic on an approximate value is approximate too, while comparisons
+    // are plain true or false
+    if !op.is_comparison() {
+        result.approximate |= a.approximate || b.approximate;
+    }
+
+    record_step(|| format!("{} {} {} = {}", a.format(), op, b.format(), result.format()));
     stack.push(result);
     true
 }
 
+/// Compare two unit values, converting to a common unit first
+/// Returns None if the units are incompatible (e.g., "5 GiB > 3 hours")
+fn compare_unit_values(a: &UnitValue, b: &UnitValue, op: &Token) -> Option<bool> {
+    let ordering = a.cmp_compatible(b)?;
+    Some(match op {
+        Token::Greater => ordering.is_gt(),
+        Token::Less => ordering.is_lt(),
+        Token::GreaterEqual => ordering.is_ge(),
+        Token::LessEqual => ordering.is_le(),
+        Token::Equal => ordering.is_eq(),
+        _ => return None,
+    })
+}
+
+/// Choose the result unit for adding or subtracting two compatible units
+/// based on the current addition unit policy
+fn addition_result_unit<'a>(unit_a: &'a Unit, unit_b: &'a Unit) -> &'a Unit {
+    let a_is_smaller = unit_a.to_base_value(1.0) < unit_b.to_base_value(1.0);
+    match addition_unit_policy() {
+        AdditionUnitPolicy::Smaller if a_is_smaller => unit_a,
+        AdditionUnitPolicy::Smaller => unit_b,
+        AdditionUnitPolicy::Left => unit_a,
+        AdditionUnitPolicy::Larger if a_is_smaller => unit_b,
+        AdditionUnitPolicy::Larger => unit_a,
+    }
+}
+
+/// Express a byte size in bits (or a bit size in bytes) to match the left operand
+///
+/// Bits and bytes have different base values, so "1 GB + 8 Gb" is only added once
+/// both sides are in the same family. The result is in the left operand's unit.
+fn align_bits_and_bytes(a: &UnitValue, b: UnitValue) -> UnitValue {
+    if let (Some(unit_a), Some(unit_b)) = (&a.unit, &b.unit) {
+        if matches!(
+            (unit_a.unit_type(), unit_b.unit_type()),
+            (UnitType::Data, UnitType::Bit) | (UnitType::Bit, UnitType::Data)
+        ) {
+            if let Some(converted) = b.to_unit(unit_a) {
+                return converted;
+            }
+        }
+    }
+    b
+}
+
 /// Helper function to add two UnitValues with proper unit handling
 fn add_unit_values(a: &UnitValue, b: &UnitValue) -> Option<UnitValue> {
+    let b = &align_bits_and_bytes(a, b.clone());
     match (&a.unit, &b.unit) {
         (Some(unit_a), Some(unit_b)) => {
             if unit_a.is_compatible_for_addition(unit_b) {
@@ -1324,12 +2316,7 @@ fn add_unit_values(a: &UnitValue, b: &UnitValue) -> Option<UnitValue> {
                 let base_b = unit_b.to_base_value(b.value);
                 let result_base = base_a + base_b;
 
-                // Choose the smaller unit (larger value) for the result
-                let result_unit = if unit_a.to_base_value(1.0) < unit_b.to_base_value(1.0) {
-                    unit_a
-                } else {
-                    unit_b
-                };
+                let result_unit = addition_result_unit(unit_a, unit_b);
                 let result_value = result_unit.clone().from_base_value(result_base);
                 Some(UnitValue::new(result_value, Some(result_unit.clone())))
             } else {
@@ -1349,6 +2336,49 @@ fn add_unit_values(a: &UnitValue, b: &UnitValue) -> Option<UnitValue> {
     }
 }
 
+/// Sum and average of a set of line results, like the lines selected in visual mode
+#[derive(Debug, Clone)]
+pub struct ResultsSummary {
+    pub sum: UnitValue,
+    pub average: UnitValue,
+    /// Number of results that went into the sum
+    pub count: usize,
+}
+
+/// Add up line results the way sum_above() does
+///
+/// Lines without a result are skipped, as are results that can't be added to the
+/// ones before them (a duration after data sizes, say). Returns None if nothing
+/// could be summed.
+pub fn summarize_results<'a>(
+    results: impl IntoIterator<Item = &'a Option<String>>,
+) -> Option<ResultsSummary> {
+    let mut sum: Option<UnitValue> = None;
+    let mut count = 0;
+
+    for value in results
+        .into_iter()
+        .filter_map(|result| parse_result_string(result.as_deref()?))
+    {
+        let total = match &sum {
+            Some(total) => add_unit_values(total, &value),
+            None => Some(value),
+        };
+        if let Some(total) = total {
+            sum = Some(total);
+            count += 1;
+        }
+    }
+
+    let sum = sum?;
+    let average = UnitValue::new(sum.value / count as f64, sum.unit.clone());
+    Some(ResultsSummary {
+        sum,
+        average,
+        count,
+    })
+}
+
 /// Apply a function with context support (for functions like sum_above)
 fn apply_function_with_context(
     stack: &mut Vec<UnitValue>,
@@ -1378,36 +2408,47 @@ fn apply_function_with_context(
                 }
             }
         }
-        "sum_above" => {
-            // sum_above() doesn't take arguments from stack
-            // It sums all the results from lines above the current line
-            let mut total = UnitValue::new(0.0, None);
-            let mut has_values = false;
-
-            // Sum all previous results that can be summed
-            for (i, result_str) in previous_results.iter().enumerate() {
-                if i >= current_line {
-                    break; // Don't include current line or lines below
-                }
+        "nextpow2" | "prevpow2" => {
+            if stack.is_empty() {
+                return false;
+            }
+            let arg = stack.pop().unwrap();
 
-                if let Some(result_str) = result_str {
-                    if let Some(unit_value) = parse_result_string(result_str) {
-                        // Try to add this value to the total
-                        if let Some(new_total) = add_unit_values(&total, &unit_value) {
-                            total = new_total;
-                            has_values = true;
-                        }
-                        // If we can't add this value, skip it (different unit types)
-                    }
-                }
+            // Round to a power of two in the value's own unit (600 GiB -> 1024 GiB),
+            // so only plain numbers and data sizes make sense
+            let is_size = arg
+                .unit
+                .as_ref()
+                .is_none_or(|unit| matches!(unit.unit_type(), UnitType::Data | UnitType::Bit));
+            if !is_size || arg.value <= 0.0 {
+                return false;
             }
 
-            if !has_values {
-                // If no values could be summed, return 0
-                total = UnitValue::new(0.0, None);
+            let exponent = if func_name == "nextpow2" {
+                arg.value.log2().ceil()
+            } else {
+                arg.value.log2().floor()
+            };
+            UnitValue::new(exponent.exp2(), arg.unit)
+        }
+        "humanize" | "humanize10" => {
+            if stack.is_empty() {
+                return false;
             }
+            let arg = stack.pop().unwrap();
 
-            total
+            // humanize() uses base-2 units like GiB, humanize10() base-10 units like GB
+            match arg.humanize(func_name == "humanize") {
+                Some(humanized) => humanized,
+                None => return false,
+            }
+        }
+        "sum_above" => {
+            // sum_above() doesn't take arguments from stack
+            // It sums all the results from lines above the current line
+            // (0 if none of them can be summed)
+            summarize_results(previous_results.iter().take(current_line))
+                .map_or(UnitValue::new(0.0, None), |summary| summary.sum)
         }
         _ => return false, // Unknown function
     };
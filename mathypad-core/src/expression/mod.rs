@@ -11,14 +11,23 @@ mod tokens;
 #[cfg(test)]
 mod tests;
 
-pub use chumsky_parser::parse_expression_chumsky;
+pub use chumsky_parser::{
+    ParenMismatch, find_paren_mismatch, paren_mismatch_in, parse_expression_chumsky,
+    parse_expression_with_spans,
+};
 pub use evaluator::{
+    EvalResult, EvaluationError, ResultsSummary, evaluate_expression_value_with_context,
     evaluate_expression_with_context, evaluate_tokens_stream_with_context,
-    evaluate_tokens_with_units_and_context, evaluate_with_variables,
+    evaluate_tokens_with_units_and_context, evaluate_with_bindings, evaluate_with_steps,
+    evaluate_with_variables, evaluate_with_variables_using, find_circular_assignment_lines,
+    find_circular_assignment_lines_using, line_has_expression, line_has_expression_with,
     parse_and_evaluate_with_context, parse_result_string, resolve_line_reference,
+    summarize_results,
 };
 pub use parser::{
-    extract_line_references, is_valid_math_expression, is_valid_mathematical_expression,
-    parse_line_reference, tokenize_with_units, update_line_references_in_text,
+    extract_line_references, format_expression, is_valid_math_expression,
+    is_valid_mathematical_expression, parse_line_reference, remap_line_references_in_text,
+    split_trailing_conversion, split_unit_assertion, strip_comment, tokenize_with_units,
+    update_line_references_in_text,
 };
 pub use tokens::Token;
@@ -1,8 +1,11 @@
 //! Expression parsing and tokenization functions
 
-use super::chumsky_parser::parse_expression_chumsky;
+use super::chumsky_parser::{parse_expression_chumsky, parse_expression_with_spans};
 use super::tokens::Token;
-use crate::units::parse_unit;
+use crate::settings::{decimal_comma, swap_decimal_separators};
+use crate::units::{Unit, parse_unit};
+use std::borrow::Cow;
+use std::ops::Range;
 
 /// Parse a line reference string like "line1", "line2" etc.
 pub fn parse_line_reference(text: &str) -> Option<usize> {
@@ -115,16 +118,179 @@ pub fn update_line_references_in_text(text: &str, threshold: usize, offset: i32)
     result
 }
 
+/// Renumber line references after lines were reordered
+///
+/// `new_positions[i]` is the new (0-based) index of the line that was at index `i`,
+/// so "line3" follows the line it pointed to wherever it moved.
+pub fn remap_line_references_in_text(text: &str, new_positions: &[usize]) -> String {
+    let mut result = text.to_string();
+
+    // Process references in reverse order to maintain correct string positions
+    for (start_pos, end_pos, line_num) in extract_line_references(text).into_iter().rev() {
+        if let Some(&new_line) = new_positions.get(line_num) {
+            result.replace_range(start_pos..end_pos, &format!("line{}", new_line + 1));
+        }
+    }
+
+    result
+}
+
+/// Cut a '#' comment off a line, unless the '#' is inside quotes
+///
+/// "5 * 3 # three fives" is evaluated as "5 * 3", and a line starting with '#' is
+/// all comment.
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Split a trailing unit assertion like "line1 + line2 :: GiB" off an expression
+///
+/// Returns the expression before the last "::" and the asserted unit, or None if
+/// there is no assertion or the unit isn't recognized.
+pub fn split_unit_assertion(text: &str) -> Option<(&str, Unit)> {
+    let (expression, unit) = text.rsplit_once("::")?;
+    Some((expression, parse_unit(unit.trim())?))
+}
+
+/// Split a trailing conversion like "2 GiB + 512 MiB to MiB" off an expression
+///
+/// Returns the expression before the last "to" or "in", the keyword's token and
+/// the target unit, or None if the expression doesn't end in a known unit. The
+/// keyword can be in any case, like "TO".
+pub fn split_trailing_conversion(text: &str) -> Option<(&str, Token, Unit)> {
+    // ASCII lowercasing keeps byte offsets, so positions carry over to `text`
+    let lowercase = text.to_ascii_lowercase();
+    let (position, keyword) = [(" to ", Token::To), (" in ", Token::In)]
+        .into_iter()
+        .filter_map(|(word, token)| Some((lowercase.rfind(word)?, (word, token))))
+        .max_by_key(|(position, _)| *position)?;
+    let (word, token) = keyword;
+    let expression = text[..position].trim_end();
+    if expression.trim_start().is_empty() {
+        return None;
+    }
+    Some((
+        expression,
+        token,
+        parse_unit(text[position + word.len()..].trim())?,
+    ))
+}
+
 /// Tokenize any text into tokens - always succeeds, may include non-mathematical tokens
 pub fn tokenize_with_units(expr: &str) -> Option<Vec<Token>> {
+    let mut expr = Cow::Borrowed(expr);
+
+    // Operators pasted from documents or math keyboards ("2 × 3", "10 ÷ 2", "5 − 2")
+    // are read as their ASCII equivalents
+    if expr.contains(['×', '÷', '−']) {
+        expr = Cow::Owned(expr.replace('×', "*").replace('÷', "/").replace('−', "-"));
+    }
+
+    // The number parser reads default style, so European numbers are swapped first
+    if decimal_comma() {
+        expr = Cow::Owned(swap_decimal_separators(&expr));
+    }
+
     // Use the chumsky parser - now accepts any input
-    match parse_expression_chumsky(expr) {
+    match parse_expression_chumsky(&expr) {
         Ok(tokens) if tokens.is_empty() => None, // Only fail on truly empty input
         Ok(tokens) => Some(tokens),              // Accept any non-empty token sequence
         Err(_) => None,                          // Only fail on parse errors
     }
 }
 
+/// Re-emit an expression with consistent spacing, e.g. "5+3*GiB" as "5 + 3 * GiB"
+///
+/// Operators, "=" and conversion keywords get one space on each side, parentheses
+/// hug their contents and runs of spaces collapse to one. Numbers, units and words
+/// are kept as typed, as is any punctuation between them. Text that doesn't
+/// tokenize (like unbalanced parentheses) is returned unchanged.
+pub fn format_expression(expr: &str) -> String {
+    // Operators from math keyboards are padded on the left to their byte length,
+    // and swapping decimal separators keeps lengths, so spans still line up with `expr`
+    let mut source = expr
+        .replace('×', " *")
+        .replace('÷', " /")
+        .replace('−', "  -");
+    if decimal_comma() {
+        source = swap_decimal_separators(&source);
+    }
+    let tokens = match parse_expression_with_spans(&source) {
+        Ok(tokens) if !tokens.is_empty() => tokens,
+        _ => return expr.to_string(),
+    };
+
+    // Widen each span to whole characters of the original text
+    let spans: Vec<Range<usize>> = tokens
+        .iter()
+        .map(|(_, span)| {
+            let mut start = span.start;
+            while !expr.is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = span.end;
+            while !expr.is_char_boundary(end) {
+                end += 1;
+            }
+            start..end
+        })
+        .collect();
+
+    let mut formatted = expr[..spans[0].start].trim_start().to_string();
+    let mut in_conditional = false;
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        if i > 0 {
+            let gap = &expr[spans[i - 1].end..spans[i].start];
+            if !gap.trim().is_empty() {
+                // Keep punctuation like the comma in "5 MB, 3 MB" as it was
+                formatted.push_str(gap);
+            } else if needs_space(&tokens[i - 1].0, token, i, &tokens, in_conditional) {
+                formatted.push(' ');
+            } else if is_sign(i - 1, &tokens) && !gap.is_empty() {
+                // A sign set apart from its value ("- 5") stays apart
+                formatted.push(' ');
+            }
+        }
+        in_conditional |= matches!(token, Token::Question);
+
+        let text = &expr[spans[i].clone()];
+        formatted.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    formatted.push_str(expr[spans[spans.len() - 1].end..].trim_end());
+    formatted
+}
+
+/// Check if the "+" or "-" at `index` is a sign rather than an operator
+fn is_sign(index: usize, tokens: &[(Token, Range<usize>)]) -> bool {
+    matches!(tokens[index].0, Token::Plus | Token::Minus)
+        && (index == 0 || !tokens[index - 1].0.ends_value())
+}
+
+/// Decide whether two adjacent tokens are separated by a space when formatting
+fn needs_space(
+    prev: &Token,
+    next: &Token,
+    next_index: usize,
+    tokens: &[(Token, Range<usize>)],
+    in_conditional: bool,
+) -> bool {
+    match (prev, next) {
+        (Token::LeftParen, _) | (_, Token::RightParen) => false,
+        (Token::Function(_), Token::LeftParen) => false,
+        // "Rent: 5" is a label, only "a ? b : c" spaces out the colon
+        (_, Token::Colon) => in_conditional,
+        _ => !is_sign(next_index - 1, tokens),
+    }
+}
+
 /// Check if a sequence of tokens forms a valid mathematical expression
 pub fn is_valid_mathematical_expression(tokens: &[Token]) -> bool {
     if tokens.is_empty() {
@@ -141,6 +307,8 @@ pub fn is_valid_mathematical_expression(tokens: &[Token]) -> bool {
             Token::Number(_)
             | Token::NumberWithUnit(_, _)
             | Token::LineReference(_)
+            | Token::LineValue(_)
+            | Token::LineRange(_, _)
             | Token::Variable(_) => {
                 has_number_or_value = true;
                 consecutive_values += 1;
@@ -159,7 +327,18 @@ pub fn is_valid_mathematical_expression(tokens: &[Token]) -> bool {
                     }
                 }
             }
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Power => {
+            Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Power
+            | Token::Modulo
+            | Token::OutOf
+            | Token::Greater
+            | Token::Less
+            | Token::GreaterEqual
+            | Token::LessEqual
+            | Token::Equal => {
                 consecutive_operators += 1;
                 consecutive_values = 0;
 
@@ -172,7 +351,12 @@ pub fn is_valid_mathematical_expression(tokens: &[Token]) -> bool {
                 consecutive_operators = 0;
                 consecutive_values = 0;
             }
-            Token::To | Token::In | Token::Of => {
+            Token::Question | Token::Colon => {
+                // Conditional separators act like operators between values
+                consecutive_operators = 0;
+                consecutive_values = 0;
+            }
+            Token::To | Token::In | Token::Of | Token::As => {
                 // These are OK for conversions and percentage operations
                 consecutive_operators = 0;
                 consecutive_values = 0;
@@ -222,13 +406,33 @@ pub fn is_valid_math_expression(expr: &str) -> bool {
             '0'..='9' => {
                 has_number = true;
                 prev_was_operator = false;
-                // Skip through the whole number (including commas and decimals)
+                // Skip through the whole number (including commas, decimals and
+                // underscores between digits)
                 while i < chars.len()
-                    && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == ',')
+                    && (chars[i].is_ascii_digit()
+                        || chars[i] == '.'
+                        || chars[i] == ','
+                        || (chars[i] == '_'
+                            && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())))
                 {
                     i += 1;
                 }
 
+                // Skip a scientific notation exponent like "e3" or "E-6"
+                if chars.get(i).is_some_and(|c| matches!(c, 'e' | 'E')) {
+                    let digits_at = if chars.get(i + 1).is_some_and(|c| matches!(c, '+' | '-')) {
+                        i + 2
+                    } else {
+                        i + 1
+                    };
+                    if chars.get(digits_at).is_some_and(|c| c.is_ascii_digit()) {
+                        i = digits_at;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+
                 // Skip whitespace
                 while i < chars.len() && chars[i] == ' ' {
                     i += 1;
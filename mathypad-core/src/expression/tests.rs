@@ -63,9 +63,9 @@ fn test_exponentiation() {
     assert_eq!(evaluate_test_expression("5^1"), Some("5".to_string()));
     assert_eq!(evaluate_test_expression("0^5"), Some("0".to_string()));
 
-    // Negative bases - TODO: support negative numbers in parentheses
-    // assert_eq!(evaluate_test_expression("(-2)^2"), Some("4".to_string()));
-    // assert_eq!(evaluate_test_expression("(-2)^3"), Some("-8".to_string()));
+    // Negative bases
+    assert_eq!(evaluate_test_expression("(-2)^2"), Some("4".to_string()));
+    assert_eq!(evaluate_test_expression("(-2)^3"), Some("-8".to_string()));
 
     // Order of operations - exponentiation has higher precedence than multiplication
     assert_eq!(evaluate_test_expression("2 * 3^2"), Some("18".to_string()));
@@ -89,6 +89,53 @@ fn test_exponentiation() {
     assert_eq!(evaluate_test_expression("(2^3)^2"), Some("64".to_string())); // (2^3)^2 = 8^2 = 64
 }
 
+#[test]
+fn test_double_star_exponentiation() {
+    assert_eq!(
+        evaluate_test_expression("2 ** 10"),
+        Some("1,024".to_string())
+    );
+    assert_eq!(evaluate_test_expression("2**3**2"), Some("512".to_string()));
+    assert_eq!(evaluate_test_expression("4 ** 0.5"), Some("2".to_string()));
+
+    // Two separate stars are two multiplications, which is an error
+    assert_eq!(evaluate_test_expression("2 * * 3"), None);
+}
+
+#[test]
+fn test_compound_growth() {
+    // 1000 * 1.05^30 = 4321.942...
+    assert_eq!(
+        evaluate_test_expression("1000 * 1.05 ^ 30"),
+        Some("4,321.942".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1000 * 1.05 ^ 0.5"),
+        Some("1,024.695".to_string())
+    );
+
+    // The growth factor is dimensionless, so the amount keeps its currency
+    assert_eq!(
+        evaluate_test_expression("$1000 * 1.05 ^ 10"),
+        Some("1,628.895 $".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1.05 ^ 10 * $1000"),
+        Some("1,628.895 $".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("€500 * (1 + 0.07) ** 10"),
+        Some("983.576 €".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$1000 * 1.05 ^ 10 - $1000"),
+        Some("628.895 $".to_string())
+    );
+
+    // Raising an amount of money itself to a power has no meaning
+    assert_eq!(evaluate_test_expression("($1000 * 1.05) ^ 10"), None);
+}
+
 #[test]
 fn test_sqrt_function() {
     // Basic square roots
@@ -520,7 +567,7 @@ fn test_variable_conversions() {
     // Test variable conversion
     let (result, assignment) =
         evaluate_with_variables("storage to TB", &variables, &previous_results, 0);
-    assert_eq!(result, Some("1.1 TB".to_string()));
+    assert_eq!(result, Some("≈ 1.1 TB".to_string()));
     assert_eq!(assignment, None);
 
     // Test variable in complex conversion expression with generic rates
@@ -731,6 +778,64 @@ fn test_percentage_of_operations() {
     );
 }
 
+#[test]
+fn test_out_of_ratios() {
+    // A share of a data size, in whatever units each side uses
+    assert_eq!(
+        evaluate_test_expression("750 GiB out of 1 TiB"),
+        Some("73.242 %".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("512 MiB out of 2 GiB"),
+        Some("25 %".to_string())
+    );
+
+    // Plain numbers, with the whole left side as the part
+    assert_eq!(
+        evaluate_test_expression("3 out of 4"),
+        Some("75 %".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 + 2 out of 4"),
+        Some("75 %".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("45 out of 60 tests passed"),
+        Some("75 %".to_string())
+    );
+
+    // The parts must be of the same kind, and the whole can't be zero
+    assert_eq!(evaluate_test_expression("5 GiB out of 2 hours"), None);
+    assert_eq!(evaluate_test_expression("5 out of 2 GiB"), None);
+    assert_eq!(evaluate_test_expression("5 out of 0"), None);
+}
+
+#[test]
+fn test_bare_units_and_empty_parentheses() {
+    // A unit name without a number is a label, not a value
+    assert_eq!(evaluate_test_expression("GiB"), None);
+    assert_eq!(evaluate_test_expression("to MiB"), None);
+    assert_eq!(evaluate_test_expression("GiB + MiB"), None);
+    assert_eq!(evaluate_test_expression("Notes about GiB"), None);
+    assert!(!line_has_expression_with("hours of work", true));
+
+    // ...but it counts as one of the unit next to numbers, and converting one unit
+    // to another shows how many of one fit in the other
+    assert_eq!(
+        evaluate_test_expression("5 * GiB"),
+        Some("5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("GiB to MiB"),
+        Some("1,024 MiB".to_string())
+    );
+
+    // Empty parentheses have no value
+    assert_eq!(evaluate_test_expression("()"), None);
+    assert_eq!(evaluate_test_expression("() + 1"), None);
+    assert_eq!(evaluate_test_expression("5 * ()"), None);
+}
+
 #[test]
 fn test_percentage_with_variables() {
     use std::collections::HashMap;
@@ -831,7 +936,7 @@ fn test_generic_rates_real_world_scenarios() {
     // Storage growth projection
     assert_eq!(
         evaluate_test_expression("Growth: 100 MB/day * 365 days to GiB"),
-        Some("33.993 GiB".to_string())
+        Some("≈ 33.993 GiB".to_string())
     );
 
     // Video streaming data transfer calculation
@@ -869,6 +974,46 @@ fn test_percentage_edge_cases() {
     );
 }
 
+#[test]
+fn test_percentage_points_and_reductions() {
+    // Percent and percent add and subtract in percentage points
+    assert_eq!(
+        evaluate_test_expression("50% - 20%"),
+        Some("30 %".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("20% - 50%"),
+        Some("-30 %".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("50% + 20%"),
+        Some("70 %".to_string())
+    );
+
+    // A percent on its own changes the value by that share of it
+    assert_eq!(evaluate_test_expression("50 - 20%"), Some("40".to_string()));
+    assert_eq!(evaluate_test_expression("50 + 20%"), Some("60".to_string()));
+    assert_eq!(
+        evaluate_test_expression("50 - 20% - 10%"),
+        Some("36".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$50 - 20%"),
+        Some("40 $".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("50 GiB - 20%"),
+        Some("40 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 TB * 3 replicas + 20% overhead"),
+        Some("360 TB".to_string())
+    );
+
+    // A plain number still can't be taken off a percent
+    assert_eq!(evaluate_test_expression("50% - 20"), None);
+}
+
 #[test]
 fn test_k_suffix_functionality() {
     // Test basic k suffix
@@ -972,6 +1117,62 @@ fn test_k_suffix_edge_cases() {
     assert_eq!(evaluate_test_expression("0.001k"), Some("1".to_string()));
 }
 
+#[test]
+fn test_magnitudes_attached_to_units() {
+    // Scientific notation
+    assert_eq!(
+        evaluate_test_expression("1.5e6 req/s"),
+        Some("1,500,000 req/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1e3 req/s"),
+        Some("1,000 req/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2.5E-3 s to ms"),
+        Some("2.5 ms".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1e3 MB to GB"),
+        Some("1 GB".to_string())
+    );
+
+    // "M" for millions
+    assert_eq!(
+        evaluate_test_expression("2M req"),
+        Some("2,000,000 req".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2M"),
+        Some("2,000,000".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1.5M req/s"),
+        Some("1,500,000 req/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2M req/s to req/min"),
+        Some("120,000,000 req/min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$1.5M + $500k"),
+        Some("2,000,000 $".to_string())
+    );
+
+    // A letter right after the suffix is part of a unit
+    assert_eq!(evaluate_test_expression("5Mb"), Some("5 Mb".to_string()));
+    assert_eq!(evaluate_test_expression("2MiB"), Some("2 MiB".to_string()));
+    assert_eq!(
+        evaluate_test_expression("2MB to KB"),
+        Some("2,000 KB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("2KB"), Some("2 KB".to_string()));
+    assert_eq!(
+        evaluate_test_expression("2KiB to B"),
+        Some("2,048 B".to_string())
+    );
+}
+
 #[test]
 fn test_sum_above_basic() {
     // Test basic sum_above functionality
@@ -1185,3 +1386,1924 @@ fn test_sum_above_with_k_suffix() {
         Some("30,000".to_string())
     );
 }
+
+#[test]
+fn test_comparison_operators() {
+    // Plain numbers
+    assert_eq!(evaluate_test_expression("5 > 3"), Some("1".to_string()));
+    assert_eq!(evaluate_test_expression("5 < 3"), Some("0".to_string()));
+    assert_eq!(evaluate_test_expression("3 >= 3"), Some("1".to_string()));
+    assert_eq!(evaluate_test_expression("4 <= 3"), Some("0".to_string()));
+    assert_eq!(evaluate_test_expression("3 == 3"), Some("1".to_string()));
+    assert_eq!(evaluate_test_expression("3 == 4"), Some("0".to_string()));
+
+    // Comparisons have lower precedence than arithmetic
+    assert_eq!(
+        evaluate_test_expression("2 + 3 > 4 * 1"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(2 + 3) * 2 < 10"),
+        Some("0".to_string())
+    );
+}
+
+#[test]
+fn test_comparison_operators_with_units() {
+    // Greater than across compatible units
+    assert_eq!(
+        evaluate_test_expression("1 GiB > 1000 MiB"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 hours > 90 minutes"),
+        Some("1".to_string())
+    );
+
+    // Less than across compatible units
+    assert_eq!(
+        evaluate_test_expression("1 GB < 1 GiB"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 day < 23 hours"),
+        Some("0".to_string())
+    );
+
+    // Greater or equal / less or equal at the boundary
+    assert_eq!(
+        evaluate_test_expression("1 GiB >= 1024 MiB"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("60 seconds <= 1 minute"),
+        Some("1".to_string())
+    );
+
+    // Equality across units, including bits and bytes
+    assert_eq!(
+        evaluate_test_expression("1 KiB == 1024 B"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 byte == 8 bits"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 MB == 1 MiB"),
+        Some("0".to_string())
+    );
+
+    // Arithmetic on both sides before comparing
+    assert_eq!(
+        evaluate_test_expression("500 MiB + 600 MiB > 1 GiB"),
+        Some("1".to_string())
+    );
+}
+
+#[test]
+fn test_comparison_operators_incompatible_units() {
+    // Different unit types can't be compared
+    assert_eq!(evaluate_test_expression("1 GiB > 1 hour"), None);
+    assert_eq!(evaluate_test_expression("5 minutes < 3 MB"), None);
+    assert_eq!(evaluate_test_expression("1 GiB >= 1 req"), None);
+    assert_eq!(evaluate_test_expression("1 hour <= 1 KiB"), None);
+    assert_eq!(evaluate_test_expression("$5 == 5 GB"), None);
+
+    // Mixing unit values with plain numbers is rejected too
+    assert_eq!(evaluate_test_expression("5 GiB > 3"), None);
+}
+
+#[test]
+fn test_conditional_expressions() {
+    // True and false branches
+    assert_eq!(
+        evaluate_test_expression("1 GiB > 500 MiB ? 10 : 20"),
+        Some("10".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB < 500 MiB ? 10 : 20"),
+        Some("20".to_string())
+    );
+
+    // Branches can carry units and arithmetic
+    assert_eq!(
+        evaluate_test_expression("2 hours > 90 minutes ? 5 GiB * 2 : 1 GiB"),
+        Some("10 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3 == 4 ? 1 hour : 30 minutes + 15 minutes"),
+        Some("45 min".to_string())
+    );
+
+    // Any non-zero condition is truthy
+    assert_eq!(
+        evaluate_test_expression("5 - 5 ? 1 : 2"),
+        Some("2".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(2 + 1) ? 1 : 2"),
+        Some("1".to_string())
+    );
+
+    // Nested conditionals in either branch
+    assert_eq!(
+        evaluate_test_expression("1 > 2 ? 1 : 2 > 3 ? 2 : 3"),
+        Some("3".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 > 1 ? 3 > 2 ? 10 : 20 : 30"),
+        Some("10".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 > 1 ? 3 < 2 ? 10 : 20 : 30"),
+        Some("20".to_string())
+    );
+
+    // Incompatible units in the condition fail the whole expression
+    assert_eq!(evaluate_test_expression("1 GiB > 1 hour ? 1 : 2"), None);
+}
+
+#[test]
+fn test_conditional_with_variables() {
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("budget".to_string(), "500 GiB".to_string());
+
+    let (result, _) = evaluate_with_variables("budget >= 1 TiB ? 2 : 1", &variables, &[], 0);
+    assert_eq!(result, Some("1".to_string()));
+
+    let (result, assignment) =
+        evaluate_with_variables("servers = budget > 100 GiB ? 4 : 2", &variables, &[], 0);
+    assert_eq!(result, Some("4".to_string()));
+    assert_eq!(assignment, Some(("servers".to_string(), "4".to_string())));
+}
+
+#[test]
+fn test_addition_unit_policy() {
+    use crate::settings::{AdditionUnitPolicy, set_addition_unit_policy};
+
+    // Default keeps the smaller unit for backward compatibility
+    assert_eq!(
+        evaluate_test_expression("2 TB + 500 GB"),
+        Some("2,500 GB".to_string())
+    );
+
+    set_addition_unit_policy(AdditionUnitPolicy::Left);
+    assert_eq!(
+        evaluate_test_expression("2 TB + 500 GB"),
+        Some("2.5 TB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("500 GB + 2 TB"),
+        Some("2,500 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 TB - 500 GB"),
+        Some("1.5 TB".to_string())
+    );
+
+    set_addition_unit_policy(AdditionUnitPolicy::Larger);
+    assert_eq!(
+        evaluate_test_expression("2 TB + 500 GB"),
+        Some("2.5 TB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("500 GB + 2 TB"),
+        Some("2.5 TB".to_string())
+    );
+
+    set_addition_unit_policy(AdditionUnitPolicy::Smaller);
+    assert_eq!(
+        evaluate_test_expression("2 TB + 500 GB"),
+        Some("2,500 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("500 GB + 2 TB"),
+        Some("2,500 GB".to_string())
+    );
+
+    // Policy names are parsed case-insensitively
+    assert_eq!(
+        AdditionUnitPolicy::from_name("Left"),
+        Some(AdditionUnitPolicy::Left)
+    );
+    assert_eq!(AdditionUnitPolicy::from_name("biggest"), None);
+}
+
+#[test]
+fn test_as_unit_cast() {
+    // "as" attaches a unit to a plain number without converting it
+    assert_eq!(
+        evaluate_test_expression("42 as GiB"),
+        Some("42 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1024 * 4 as MB"),
+        Some("4,096 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(10 + 5) as minutes"),
+        Some("15 min".to_string())
+    );
+
+    // Unlike "to", "as" does not convert: it only works on plain numbers
+    assert_eq!(evaluate_test_expression("42 to GiB"), None);
+    assert_eq!(evaluate_test_expression("1 GiB as MiB"), None);
+    assert_eq!(evaluate_test_expression("5 GB + 1 GB as TB"), None);
+
+    // The cast value takes part in later lines like any other unit value
+    let previous_results = vec![evaluate_test_expression("3 * 4 as TB")];
+    assert_eq!(previous_results[0], Some("12 TB".to_string()));
+    assert_eq!(
+        evaluate_expression_with_context("line1 to GB", &previous_results, 1),
+        Some("12,000 GB".to_string())
+    );
+}
+
+#[test]
+fn test_as_unit_cast_with_variables() {
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("count".to_string(), "16".to_string());
+    variables.insert("size".to_string(), "2 GiB".to_string());
+
+    let (result, _) = evaluate_with_variables("count as GiB", &variables, &[], 0);
+    assert_eq!(result, Some("16 GiB".to_string()));
+
+    let (result, _) = evaluate_with_variables("size as MiB", &variables, &[], 0);
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_as_unit_cast_then_convert() {
+    // A bare number can be given a unit with "as" and then converted with "to"
+    assert_eq!(
+        evaluate_test_expression("3600 as s to min"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 as seconds to minutes"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 as s in min"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1.5 as h to min"),
+        Some("90 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(1800 * 2) as s to min"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 as s to min to h"),
+        Some("1 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2048 as MiB to GiB"),
+        Some("2 GiB".to_string())
+    );
+
+    // Without "as" there is no source unit, and values that have one aren't recast
+    assert_eq!(evaluate_test_expression("3600 to min"), None);
+    assert_eq!(evaluate_test_expression("3600 s as s to min"), None);
+    assert_eq!(evaluate_test_expression("3600 as s to GiB"), None);
+
+    // Line references and variables holding plain numbers work the same way
+    let previous_results = vec![Some("3,600".to_string())];
+    assert_eq!(
+        evaluate_expression_with_context("line1 as s to min", &previous_results, 1),
+        Some("60 min".to_string())
+    );
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("elapsed".to_string(), "5400".to_string());
+    let (result, _) = evaluate_with_variables("elapsed as s to h", &variables, &[], 0);
+    assert_eq!(result, Some("1.5 h".to_string()));
+}
+
+#[test]
+fn test_find_circular_assignment_lines() {
+    let lines = |text: &[&str]| text.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    // Two-variable cycle
+    let circular =
+        find_circular_assignment_lines(&lines(&["alpha = beta + 1", "beta = alpha + 1"]));
+    assert!(circular.contains(&0) && circular.contains(&1));
+
+    // Self reference without an earlier definition
+    let circular = find_circular_assignment_lines(&lines(&["x = x * 2"]));
+    assert!(circular.contains(&0));
+
+    // Longer cycle with an unrelated line
+    let circular = find_circular_assignment_lines(&lines(&[
+        "alpha = gamma",
+        "beta = alpha",
+        "gamma = beta",
+        "delta = 5 GiB",
+    ]));
+    assert_eq!(circular.len(), 3);
+    assert!(!circular.contains(&3));
+
+    // Building on an earlier definition is fine
+    let circular =
+        find_circular_assignment_lines(&lines(&["alpha = 5", "alpha = alpha + 1", "beta = alpha"]));
+    assert!(circular.is_empty());
+}
+
+#[test]
+fn test_assignment_vs_evaluate_marker() {
+    let mut variables = std::collections::HashMap::new();
+
+    // "x = 5" is an assignment
+    let (result, assignment) = evaluate_with_variables("x = 5", &variables, &[], 0);
+    assert_eq!(result, Some("5".to_string()));
+    assert_eq!(assignment, Some(("x".to_string(), "5".to_string())));
+
+    // "5 =" is an evaluate marker, not an assignment
+    let (result, assignment) = evaluate_with_variables("5 =", &variables, &[], 0);
+    assert_eq!(result, Some("5".to_string()));
+    assert_eq!(assignment, None);
+
+    let (result, assignment) = evaluate_with_variables("5 * 3 =", &variables, &[], 0);
+    assert_eq!(result, Some("15".to_string()));
+    assert_eq!(assignment, None);
+    assert_eq!(evaluate_test_expression("5 * 3 ="), Some("15".to_string()));
+    assert_eq!(
+        evaluate_test_expression("1 GiB + 512 MiB in MiB ="),
+        Some("1,536 MiB".to_string())
+    );
+
+    // The variable keeps the assignment's value while the result shows the evaluation
+    let (result, assignment) = evaluate_with_variables("result = 5 * 3", &variables, &[], 0);
+    assert_eq!(result, Some("15".to_string()));
+    assert_eq!(assignment, Some(("result".to_string(), "15".to_string())));
+    variables.insert("result".to_string(), "15".to_string());
+
+    // "result =" shows the current value instead of assigning
+    let (result, assignment) = evaluate_with_variables("result =", &variables, &[], 0);
+    assert_eq!(result, Some("15".to_string()));
+    assert_eq!(assignment, None);
+
+    // An assignment may also end with the evaluate marker
+    let (result, assignment) = evaluate_with_variables("total = result * 2 =", &variables, &[], 0);
+    assert_eq!(result, Some("30".to_string()));
+    assert_eq!(assignment, Some(("total".to_string(), "30".to_string())));
+}
+
+#[test]
+fn test_typeof() {
+    assert_eq!(
+        evaluate_test_expression("typeof(1 GiB / 1 s)"),
+        Some("DataRate".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(5 GiB + 10 MiB)"),
+        Some("Data".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(2 hours)"),
+        Some("Time".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(100 Mbps)"),
+        Some("BitRate".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(1000 req / 1 min)"),
+        Some("RequestRate".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof($5 * 3)"),
+        Some("Currency".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(1 GiB / 1 MiB)"),
+        Some("Dimensionless".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("typeof(2 + 3)"),
+        Some("Dimensionless".to_string())
+    );
+
+    // A failing inner expression has no type
+    assert_eq!(evaluate_test_expression("typeof(5 GiB + 3 hours)"), None);
+
+    // Variables and line references are resolved before reporting the type
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("bandwidth".to_string(), "10 MB/s".to_string());
+    let (result, assignment) = evaluate_with_variables("typeof(bandwidth)", &variables, &[], 0);
+    assert_eq!(result, Some("DataRate".to_string()));
+    assert_eq!(assignment, None);
+
+    let previous = vec![Some("30 min".to_string())];
+    assert_eq!(
+        evaluate_expression_with_context("typeof(line1 * 2)", &previous, 1),
+        Some("Time".to_string())
+    );
+}
+
+#[test]
+fn test_underscore_digit_grouping() {
+    assert_eq!(
+        evaluate_test_expression("1_000 + 1"),
+        Some("1,001".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1_000_000 GiB"),
+        Some("1,000,000 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2_048 MiB to GiB"),
+        Some("2 GiB".to_string())
+    );
+    assert!(is_valid_math_expression("1_000_000 * 2"));
+}
+
+#[test]
+fn test_line_reference_self_and_forward_rules() {
+    let previous = vec![
+        Some("2 GiB".to_string()),
+        Some("4,096 MiB".to_string()),
+        Some("8".to_string()),
+    ];
+
+    // A line can't reference itself, even when it has a stale result from a prior edit
+    assert_eq!(
+        evaluate_expression_with_context("line2 to GiB", &previous, 1),
+        None
+    );
+    // ...or a line below it
+    assert_eq!(
+        evaluate_expression_with_context("line3 * 2", &previous, 1),
+        None
+    );
+    // Lines above are fine
+    assert_eq!(
+        evaluate_expression_with_context("line1 to MiB", &previous, 1),
+        Some("2,048 MiB".to_string())
+    );
+
+    // `prev` always refers to the line directly above
+    assert_eq!(
+        evaluate_expression_with_context("prev to GiB", &previous, 2),
+        Some("4 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("prev + line1", &previous, 1),
+        Some("4 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("PREV * 2", &previous, 3),
+        Some("16".to_string())
+    );
+    // There is nothing above the first line
+    assert_eq!(
+        evaluate_expression_with_context("prev * 2", &previous, 0),
+        None
+    );
+
+    // `ans` is the nearest result above, skipping lines without one
+    let results = vec![Some("2 GiB".to_string()), None, Some("8".to_string()), None];
+    assert_eq!(
+        evaluate_expression_with_context("ans to MiB", &results, 2),
+        Some("2,048 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("ans * 2", &results, 4),
+        Some("16".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("prev * 2", &results, 4),
+        None
+    );
+    // Before any result, and in one-shot mode, there is no answer to recall
+    assert_eq!(
+        evaluate_expression_with_context("ans + 1", &results, 0),
+        None
+    );
+    assert_eq!(evaluate_expression_with_context("ans + 1", &[], 0), None);
+
+    let variables = std::collections::HashMap::new();
+    let (result, assignment) =
+        evaluate_with_variables("total = prev * 3", &variables, &previous, 3);
+    assert_eq!(result, Some("24".to_string()));
+    assert_eq!(assignment, Some(("total".to_string(), "24".to_string())));
+}
+
+#[test]
+fn test_strict_expression_mode() {
+    use crate::settings::set_strict_expressions;
+
+    // Off by default: expressions are found inside prose
+    assert_eq!(
+        evaluate_test_expression("cost is 5 * 3"),
+        Some("15".to_string())
+    );
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("price".to_string(), "5".to_string());
+    assert_eq!(
+        evaluate_with_variables("Cost: price * 3", &variables, &[], 0).0,
+        Some("15".to_string())
+    );
+
+    set_strict_expressions(true);
+    assert_eq!(evaluate_test_expression("cost is 5 * 3"), None);
+    assert_eq!(evaluate_test_expression("5 * 3 apples"), None);
+    assert_eq!(evaluate_test_expression("5 * 3"), Some("15".to_string()));
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB"),
+        Some("1,024 MiB".to_string())
+    );
+
+    // Variables and assignments still work when they make up the whole line
+    assert_eq!(
+        evaluate_with_variables("price * 3", &variables, &[], 0).0,
+        Some("15".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("Cost: price * 3", &variables, &[], 0).0,
+        None
+    );
+    assert_eq!(
+        evaluate_with_variables("total = price * 3", &variables, &[], 0).1,
+        Some(("total".to_string(), "15".to_string()))
+    );
+    set_strict_expressions(false);
+
+    assert_eq!(
+        evaluate_test_expression("cost is 5 * 3"),
+        Some("15".to_string())
+    );
+}
+
+#[test]
+fn test_decimal_comma_mode() {
+    use crate::settings::set_decimal_comma;
+
+    // By default a comma groups thousands
+    assert_eq!(evaluate_test_expression("3,14"), Some("314".to_string()));
+    assert_eq!(
+        evaluate_test_expression("3,14 + 0,86"),
+        Some("400".to_string())
+    );
+
+    set_decimal_comma(true);
+    assert_eq!(
+        evaluate_test_expression("3,14 + 0,86"),
+        Some("4".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1.000 * 2,5"),
+        Some("2.500".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1,5 GiB to MiB"),
+        Some("1.536 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("10 MB / 4"),
+        Some("2,5 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1234567,891"),
+        Some("1.234.567,891".to_string())
+    );
+
+    // Results read back from other lines use the same style
+    let previous_results = vec![Some("1.234,5 MB".to_string())];
+    assert_eq!(
+        evaluate_expression_with_context("line1 * 2", &previous_results, 1),
+        Some("2.469 MB".to_string())
+    );
+    set_decimal_comma(false);
+
+    assert_eq!(
+        evaluate_test_expression("10 MB / 4"),
+        Some("2.5 MB".to_string())
+    );
+}
+
+#[test]
+fn test_notation_modes_and_grouping() {
+    use crate::settings::{Notation, set_decimal_comma, set_notation};
+
+    // Grouped digits
+    set_notation(Notation::Plain);
+    assert_eq!(
+        evaluate_test_expression("1500000000"),
+        Some("1,500,000,000".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("12500"),
+        Some("12,500".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("123456789 GiB"),
+        Some("123,456,789 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2^60"),
+        Some("1,152,921,504,606,846,976".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1234.5678"),
+        Some("1,234.568".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("-45000"),
+        Some("-45,000".to_string())
+    );
+    assert_eq!(evaluate_test_expression("0.00042"), Some("0".to_string()));
+    assert_eq!(
+        evaluate_test_expression("9999.6"),
+        Some("9,999.6".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("999999.9"),
+        Some("999,999.9".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5"), Some("5".to_string()));
+    assert_eq!(evaluate_test_expression("0"), Some("0".to_string()));
+
+    // One digit before the point
+    set_notation(Notation::Scientific);
+    assert_eq!(
+        evaluate_test_expression("1500000000"),
+        Some("1.5e9".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("12500"),
+        Some("1.25e4".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("123456789 GiB"),
+        Some("1.235e8 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2^60"),
+        Some("1.153e18".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1234.5678"),
+        Some("1.235e3".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("-45000"),
+        Some("-4.5e4".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("0.00042"),
+        Some("4.2e-4".to_string())
+    );
+    assert_eq!(evaluate_test_expression("9999.6"), Some("1e4".to_string()));
+    assert_eq!(
+        evaluate_test_expression("999999.9"),
+        Some("1e6".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5"), Some("5e0".to_string()));
+    assert_eq!(evaluate_test_expression("0"), Some("0".to_string()));
+
+    // Exponents that are multiples of three
+    set_notation(Notation::Engineering);
+    assert_eq!(
+        evaluate_test_expression("1500000000"),
+        Some("1.5e9".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("12500"),
+        Some("12.5e3".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("123456789 GiB"),
+        Some("123.457e6 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2^60"),
+        Some("1.153e18".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1234.5678"),
+        Some("1.235e3".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("-45000"),
+        Some("-45e3".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("0.00042"),
+        Some("420e-6".to_string())
+    );
+    assert_eq!(evaluate_test_expression("9999.6"), Some("10e3".to_string()));
+    assert_eq!(
+        evaluate_test_expression("999999.9"),
+        Some("1e6".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5"), Some("5e0".to_string()));
+    assert_eq!(evaluate_test_expression("0"), Some("0".to_string()));
+
+    // Exponent results read back from other lines
+    set_notation(Notation::Scientific);
+    let previous_results = vec![evaluate_test_expression("3 * 5e9 B")];
+    assert_eq!(previous_results[0], Some("1.5e10 B".to_string()));
+    assert_eq!(
+        evaluate_expression_with_context("line1 to GB", &previous_results, 1),
+        Some("1.5e1 GB".to_string())
+    );
+
+    // European style writes the mantissa with a decimal comma and still no grouping
+    set_decimal_comma(true);
+    assert_eq!(
+        evaluate_test_expression("1234567"),
+        Some("1,235e6".to_string())
+    );
+    set_notation(Notation::Plain);
+    assert_eq!(
+        evaluate_test_expression("1234567"),
+        Some("1.234.567".to_string())
+    );
+    set_decimal_comma(false);
+
+    assert_eq!(Notation::from_name("SCI"), Some(Notation::Scientific));
+    assert_eq!(Notation::from_name("eng"), Some(Notation::Engineering));
+    assert_eq!(Notation::from_name("hex"), None);
+}
+
+#[test]
+fn test_assume_bytes_mode() {
+    use crate::settings::set_assume_bytes;
+
+    // Off by default: bare numbers stay dimensionless
+    assert_eq!(
+        evaluate_test_expression("1073741824"),
+        Some("1,073,741,824".to_string())
+    );
+    assert_eq!(evaluate_test_expression("1073741824 to GiB"), None);
+
+    set_assume_bytes(true);
+    assert_eq!(
+        evaluate_test_expression("1073741824"),
+        Some("1,073,741,824 B".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1073741824 to GiB"),
+        Some("1 GiB".to_string())
+    );
+    // Small factors and explicit units are unaffected
+    assert_eq!(
+        evaluate_test_expression("1073741824 * 2 to GiB"),
+        Some("2 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2048 MiB to GiB"),
+        Some("2 GiB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5 + 3"), Some("8".to_string()));
+    set_assume_bytes(false);
+
+    assert_eq!(
+        evaluate_test_expression("1073741824"),
+        Some("1,073,741,824".to_string())
+    );
+}
+
+#[test]
+fn test_mid_expression_conversion() {
+    use std::collections::HashMap;
+
+    // The conversion applies to everything on its left; the rest of the expression
+    // continues from the converted value and stays in the target unit
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB + 512 KiB"),
+        Some("1,024.5 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB * 2"),
+        Some("2,048 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB + 1 GiB to MiB * 2"),
+        Some("4,096 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 hour to minutes - 30 s"),
+        Some("59.5 min".to_string())
+    );
+
+    // Conversions chain left to right
+    assert_eq!(
+        evaluate_test_expression("5 GiB to MiB to KiB"),
+        Some("5,242,880 KiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB + 1 GiB to GiB"),
+        Some("2 GiB".to_string())
+    );
+
+    // Parentheses limit a conversion to the group
+    assert_eq!(
+        evaluate_test_expression("(1 GiB to MiB) * 2"),
+        Some("2,048 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 * (1 GiB to MiB) + 1 GiB"),
+        Some("3,072 MiB".to_string())
+    );
+
+    // Impossible conversions are rejected rather than silently dropping the rest
+    // of the expression
+    assert_eq!(evaluate_test_expression("(1 GiB to QPS) + 512 KiB"), None);
+    assert_eq!(evaluate_test_expression("1 GiB to MiB to QPS"), None);
+    assert_eq!(evaluate_test_expression("1 GiB to MiB + 5 seconds"), None);
+
+    // Same rules with variables
+    let mut variables = HashMap::new();
+    variables.insert("disk".to_string(), "1 GiB".to_string());
+    let (result, _) = evaluate_with_variables("disk to MiB + 512 KiB", &variables, &[], 0);
+    assert_eq!(result, Some("1,024.5 MiB".to_string()));
+    let (result, _) = evaluate_with_variables("(disk to MiB) * 2", &variables, &[], 0);
+    assert_eq!(result, Some("2,048 MiB".to_string()));
+    let (result, _) = evaluate_with_variables("disk to MiB to QPS", &variables, &[], 0);
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_parenthesized_conversions() {
+    use std::collections::HashMap;
+
+    // A parenthesized conversion is a sub-expression whose value is already converted
+    assert_eq!(
+        evaluate_test_expression("3 * (1 GiB to MiB)"),
+        Some("3,072 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(1 day to hours) / 2"),
+        Some("12 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(1 GiB in MiB) * 3"),
+        Some("3,072 MiB".to_string())
+    );
+
+    // Nested groups
+    assert_eq!(
+        evaluate_test_expression("2 * (3 * (1 GiB to MiB))"),
+        Some("6,144 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("((1 day to hours) + 2 hours) / 2"),
+        Some("13 h".to_string())
+    );
+    // Groups nested inside the conversion's own group
+    assert_eq!(
+        evaluate_test_expression("((1 + 1) * 1 GiB to MiB) * 3"),
+        Some("6,144 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 * ((2 + 2) * (1 day to hours))"),
+        Some("192 h".to_string())
+    );
+
+    // Variables and line references inside the group
+    let mut variables = HashMap::new();
+    variables.insert("uptime".to_string(), "2 days".to_string());
+    let (result, _) = evaluate_with_variables("(uptime to hours) / 4", &variables, &[], 0);
+    assert_eq!(result, Some("12 h".to_string()));
+    assert_eq!(
+        evaluate_expression_with_context("3 * (line1 to MiB)", &[Some("1 GiB".to_string())], 1),
+        Some("3,072 MiB".to_string())
+    );
+}
+
+#[test]
+fn test_same_precedence_associativity() {
+    use std::collections::HashMap;
+
+    // Left-associative chains, plain numbers and units
+    assert_eq!(
+        evaluate_test_expression("100 / 10 / 2"),
+        Some("5".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("20 - 5 - 3"),
+        Some("12".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("10 - 2 + 3"),
+        Some("11".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("8 / 2 * 4"),
+        Some("16".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 GiB / 10 / 2"),
+        Some("5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("20 GiB - 5 GiB - 3 GiB"),
+        Some("12 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB - 512 MiB - 256 MiB"),
+        Some("256 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 s / 60 / 2"),
+        Some("30 s".to_string())
+    );
+
+    // Power is right-associative
+    assert_eq!(
+        evaluate_test_expression("2 ^ 3 ^ 2"),
+        Some("512".to_string())
+    );
+
+    // The evaluator used for lines with variables agrees
+    let variables = HashMap::new();
+    assert_eq!(
+        evaluate_with_variables("100 / 10 / 2", &variables, &[], 0).0,
+        Some("5".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("20 - 5 - 3", &variables, &[], 0).0,
+        Some("12".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("10 - 2 + 3", &variables, &[], 0).0,
+        Some("11".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("8 / 2 * 4", &variables, &[], 0).0,
+        Some("16".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("100 GiB / 10 / 2", &variables, &[], 0).0,
+        Some("5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("20 GiB - 5 GiB - 3 GiB", &variables, &[], 0).0,
+        Some("12 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("1 GiB - 512 MiB - 256 MiB", &variables, &[], 0).0,
+        Some("256 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("3600 s / 60 / 2", &variables, &[], 0).0,
+        Some("30 s".to_string())
+    );
+    assert_eq!(
+        evaluate_with_variables("2 ^ 3 ^ 2", &variables, &[], 0).0,
+        Some("512".to_string())
+    );
+
+    // Same through line references and variables
+    let previous = vec![Some("100".to_string()), Some("20 GiB".to_string())];
+    assert_eq!(
+        evaluate_expression_with_context("line1 / 10 / 2", &previous, 2),
+        Some("5".to_string())
+    );
+    let mut variables = HashMap::new();
+    variables.insert("total".to_string(), "20 GiB".to_string());
+    let (result, _) = evaluate_with_variables("total - 5 GiB - 3 GiB", &variables, &previous, 2);
+    assert_eq!(result, Some("12 GiB".to_string()));
+}
+
+#[test]
+fn test_result_strings_round_trip() {
+    use crate::rate_unit;
+    use crate::units::UnitValue;
+
+    // Every kind of result parses back to the value it was formatted from
+    let values = [
+        UnitValue::new(1536.0, Some(Unit::GiB)),
+        UnitValue::new(-3.5, Some(Unit::Tb)),
+        UnitValue::new(2.5, Some(rate_unit!(Unit::GiB, Unit::Second))),
+        UnitValue::new(100.0, Some(rate_unit!(Unit::Mb, Unit::Second))),
+        UnitValue::new(8.333, Some(rate_unit!(Unit::Query, Unit::Second))),
+        UnitValue::new(1234.5, Some(Unit::USD)),
+        UnitValue::new(-20.0, Some(Unit::EUR)),
+        UnitValue::new(240.0, Some(rate_unit!(Unit::USD, Unit::Day))),
+        UnitValue::new(0.05, Some(rate_unit!(Unit::USD, Unit::GiB))),
+        UnitValue::new(73.25, Some(Unit::Percent)),
+        UnitValue::new(1_000_000.0, None),
+    ];
+    for value in values {
+        let formatted = value.format();
+        let parsed = parse_result_string(&formatted).unwrap();
+        assert_eq!(parsed.unit, value.unit, "{}", formatted);
+        assert_eq!(parsed.value, value.value, "{}", formatted);
+    }
+
+    // Approximate conversions read as their value
+    let approximate = UnitValue::new(1.0, Some(Unit::GB))
+        .to_unit(&Unit::GiB)
+        .unwrap()
+        .format();
+    assert_eq!(approximate, "≈ 0.931 GiB");
+    assert_eq!(parse_result_string(&approximate).unwrap().value, 0.931);
+
+    // Values written the way they are typed
+    let cases = [
+        ("$5/h", 5.0, Some(rate_unit!(Unit::USD, Unit::Hour))),
+        ("$1,234.50", 1234.5, Some(Unit::USD)),
+        ("-€20", -20.0, Some(Unit::EUR)),
+        ("5 MB / hour", 5.0, Some(rate_unit!(Unit::MB, Unit::Hour))),
+        ("1.5e9 B", 1.5e9, Some(Unit::Byte)),
+        ("2.5E-3", 0.0025, None),
+        ("≈0.931 GiB", 0.931, Some(Unit::GiB)),
+    ];
+    for (text, value, unit) in cases {
+        let parsed = parse_result_string(text).unwrap();
+        assert_eq!(parsed.value, value, "{}", text);
+        assert_eq!(parsed.unit, unit, "{}", text);
+    }
+
+    assert!(parse_result_string("5 apples").is_none());
+    assert!(parse_result_string("$").is_none());
+    assert!(parse_result_string("$5 GiB").is_none());
+    assert!(parse_result_string("Data").is_none());
+}
+
+#[test]
+fn test_line_references_match_inline_values() {
+    // Every unit's formatted result parses back to the same unit, so a line
+    // reference behaves exactly like writing the value inline
+    for (_, names) in crate::units::supported_units() {
+        for name in names {
+            let unit = crate::units::parse_unit(&name).unwrap();
+            if unit == Unit::CNY {
+                continue; // Shares the "¥" symbol with JPY
+            }
+            let formatted = crate::units::UnitValue::new(-1234.5, Some(unit.clone())).format();
+            let parsed = parse_result_string(&formatted).unwrap();
+            assert_eq!(parsed.unit, Some(unit), "{} ({})", name, formatted);
+            assert_eq!(parsed.value, -1234.5, "{} ({})", name, formatted);
+        }
+    }
+
+    // Combining line results gives the same answer as the inline expression
+    let cases = [
+        ("10 GiB/s", "4 GiB/s", "-", "6 GiB/s"),
+        ("10 GiB/s", "2", "*", "20 GiB/s"),
+        ("10 GiB/s", "1 minute", "*", "600 GiB"),
+        ("100 Gib/s", "1 s", "*", "100 Gib"),
+        ("$5/h", "8 hours", "*", "40 $"),
+        ("$5/h", "$3/h", "-", "2 $/h"),
+        ("$5", "$12", "-", "-7 $"),
+        ("2 GiB", "5 GiB", "-", "-3 GiB"),
+        ("10", "4 GiB", "*", "40 GiB"),
+        ("50 req/s", "2 minutes", "*", "6,000 req"),
+    ];
+    for (first, second, op, expected) in cases {
+        let previous = vec![
+            evaluate_test_expression(first),
+            evaluate_test_expression(second),
+        ];
+        let inline = evaluate_test_expression(&format!("{} {} {}", first, op, second));
+        let referenced =
+            evaluate_expression_with_context(&format!("line1 {} line2", op), &previous, 2);
+        assert_eq!(
+            inline,
+            Some(expected.to_string()),
+            "{} {} {}",
+            first,
+            op,
+            second
+        );
+        assert_eq!(referenced, inline, "{} {} {}", first, op, second);
+    }
+
+    // Mixing a unit with a plain number fails the same way in both forms
+    let previous = vec![Some("10 GiB".to_string()), Some("5".to_string())];
+    assert_eq!(evaluate_test_expression("10 GiB - 5"), None);
+    assert_eq!(
+        evaluate_expression_with_context("line1 - line2", &previous, 2),
+        None
+    );
+}
+
+#[test]
+fn test_evaluate_with_steps() {
+    use std::collections::HashMap;
+
+    let (steps, result) =
+        evaluate_with_steps("(1 GiB + 1 GiB) / 2 to MiB", &HashMap::new(), &[], 0);
+    assert_eq!(
+        steps,
+        vec![
+            "Tokens: ( 1 GiB + 1 GiB ) / 2 to MiB",
+            "1 GiB + 1 GiB = 2 GiB",
+            "2 GiB / 2 = 1 GiB",
+            "1 GiB to MiB = 1,024 MiB",
+        ]
+    );
+    assert_eq!(result, Some("1,024 MiB".to_string()));
+
+    // Operators are recorded in evaluation order, with variables and line references resolved
+    let mut variables = HashMap::new();
+    variables.insert("servers".to_string(), "4".to_string());
+    let previous = vec![Some("100 MB/s".to_string())];
+    let (steps, result) = evaluate_with_steps("servers * line1 * 1 hour", &variables, &previous, 1);
+    assert_eq!(
+        steps,
+        vec![
+            "Tokens: servers * line1 * 1 h",
+            "4 * 100 MB/s = 400 MB/s",
+            "400 MB/s * 1 h = 1,440,000 MB",
+        ]
+    );
+    assert_eq!(result, Some("1,440,000 MB".to_string()));
+
+    // Failed evaluations still show how far they got
+    let (steps, result) = evaluate_with_steps("1 GiB + 1 hour", &HashMap::new(), &[], 0);
+    assert_eq!(steps, vec!["Tokens: 1 GiB + 1 h"]);
+    assert_eq!(result, None);
+}
+
+#[test]
+fn test_exponent_and_keyword_ambiguity() {
+    // Exabyte units and scientific notation
+    assert_eq!(evaluate_test_expression("1 EB"), Some("1 EB".to_string()));
+    assert_eq!(evaluate_test_expression("1 EiB"), Some("1 EiB".to_string()));
+    assert_eq!(evaluate_test_expression("1eb"), Some("1 EB".to_string()));
+    assert_eq!(
+        evaluate_test_expression("1 eb to pb"),
+        Some("1,000 PB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 EiB to eb"),
+        Some("≈ 1.153 EB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("1e3"), Some("1,000".to_string()));
+    assert_eq!(
+        evaluate_test_expression("1E3 + 1"),
+        Some("1,001".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2.5e-3 s to ms"),
+        Some("2.5 ms".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2e3 GiB to TiB"),
+        Some("1.953 TiB".to_string())
+    );
+
+    // "in" as a keyword; "inch" is not a unit, so it's just a word
+    assert_eq!(
+        evaluate_test_expression("1 GiB in MiB"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 Tb in TB"),
+        Some("0.125 TB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5 inch"), Some("5".to_string()));
+
+    // "to" as a keyword next to terabyte units
+    assert_eq!(
+        evaluate_test_expression("1 TB to tb"),
+        Some("1 TB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 Tb to Gb"),
+        Some("2,000 Gb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB tomorrow"),
+        Some("1 GiB".to_string())
+    );
+}
+
+#[test]
+fn test_power_of_two_rounding() {
+    assert_eq!(
+        evaluate_test_expression("nextpow2(600 GiB)"),
+        Some("1,024 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("prevpow2(600 GiB)"),
+        Some("512 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("nextpow2(3 Gb)"),
+        Some("4 Gb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("nextpow2(100)"),
+        Some("128".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("prevpow2(100)"),
+        Some("64".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("nextpow2(0.3)"),
+        Some("0.5".to_string())
+    );
+
+    // Exact powers of two are unchanged
+    assert_eq!(
+        evaluate_test_expression("nextpow2(512 MiB)"),
+        Some("512 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("prevpow2(512 MiB)"),
+        Some("512 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("nextpow2(1)"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("prevpow2(1024)"),
+        Some("1,024".to_string())
+    );
+
+    // Works on the result of an expression
+    assert_eq!(
+        evaluate_test_expression("nextpow2(3 * 200 GiB) to TiB"),
+        Some("1 TiB".to_string())
+    );
+
+    // Other units aren't rounded, only the argument itself is evaluated
+    assert_eq!(
+        evaluate_test_expression("nextpow2(5 hours)"),
+        Some("5 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("prevpow2(100 GiB/s)"),
+        Some("100 GiB/s".to_string())
+    );
+}
+
+#[test]
+fn test_mixed_bit_and_byte_arithmetic() {
+    assert_eq!(
+        evaluate_test_expression("1 GB + 8 Gb"),
+        Some("2 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GB + 8 Gb to GB"),
+        Some("2 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 MB + 4 Mb to Mb"),
+        Some("12 Mb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 MB + 4 Mb to MB"),
+        Some("1.5 MB".to_string())
+    );
+
+    // Without a target the left operand decides whether the result is bits or bytes
+    assert_eq!(
+        evaluate_test_expression("1 Gb + 1 GB"),
+        Some("9 Gb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 GiB - 8 Gib"),
+        Some("1 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3 GB - 8 Gb to Gb"),
+        Some("16 Gb".to_string())
+    );
+
+    // Sizes still can't be added to rates
+    assert_eq!(evaluate_test_expression("1 GB + 8 Gb/s"), None);
+}
+
+#[test]
+fn test_bandwidth_delay_product() {
+    // Bit rate * round trip time = bits in flight
+    assert_eq!(
+        evaluate_test_expression("100 Mbps * 50 ms"),
+        Some("5 Mb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("50 ms * 100 Mbps"),
+        Some("5 Mb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 Mbps * 50 ms to KB"),
+        Some("625 KB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 Mbps * 50 ms to MB"),
+        Some("0.625 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 Gbps * 1 ms"),
+        Some("0.001 Gb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 Gbps * 1 ms to Mb"),
+        Some("1 Mb".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 Gbps * 1 ms to KB"),
+        Some("125 KB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("10 Gbps * 80 ms to MiB"),
+        Some("≈ 95.367 MiB".to_string())
+    );
+}
+
+#[test]
+fn test_transfer_time_conversions() {
+    // Data / rate = time, which a trailing conversion then applies to
+    assert_eq!(
+        evaluate_test_expression("1 TB / (100 MB/s)"),
+        Some("10,000 s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 TB / (100 MB/s) to minutes"),
+        Some("166.667 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB / (50 MiB/s)"),
+        Some("20.48 s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB / (50 MiB/s) to minutes"),
+        Some("0.341 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 TB / (100 MB/min) to hours"),
+        Some("166.667 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 TB / (800 Mbps) to hours"),
+        Some("2.778 h".to_string())
+    );
+
+    assert_eq!(evaluate_test_expression("1 TB / (0 MB/s)"), None);
+}
+
+#[test]
+fn test_amount_divided_by_rate_is_time() {
+    // The inverses of rate * time = amount
+    assert_eq!(
+        evaluate_test_expression("1 GB / (10 MB/s)"),
+        Some("100 s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1000 req / (10 req/s)"),
+        Some("100 s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("36000 req / (10 req/s) to hours"),
+        Some("1 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1000 queries / (10 QPS)"),
+        Some("100 s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("6000 req / (100 req/min)"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$100 / ($5/hour)"),
+        Some("20 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$100 / ($5/month) to years"),
+        Some("1.667 year".to_string())
+    );
+
+    assert_eq!(evaluate_test_expression("1000 req / (0 req/s)"), None);
+    assert_eq!(evaluate_test_expression("€100 / ($5/hour)"), None);
+    assert_eq!(evaluate_test_expression("1000 req / (10 MB/s)"), None);
+}
+
+#[test]
+fn test_evaluate_with_bindings() {
+    use crate::units::UnitValue;
+    use std::collections::HashMap;
+
+    let mut bindings = HashMap::new();
+    bindings.insert("x".to_string(), UnitValue::new(5.0, Some(Unit::GiB)));
+    bindings.insert("n".to_string(), UnitValue::new(3.0, None));
+    bindings.insert(
+        "rate".to_string(),
+        UnitValue::new(0.1234567, Some(crate::rate_unit!(Unit::GB, Unit::Second))),
+    );
+
+    let result = evaluate_with_bindings("x * 2 to MiB", &bindings).unwrap();
+    assert_eq!(result.value.value, 10_240.0);
+    assert_eq!(result.value.unit, Some(Unit::MiB));
+    assert_eq!(result.assigned_variable, None);
+
+    let result = evaluate_with_bindings("x * n", &bindings).unwrap();
+    assert_eq!(result.value.format(), "15 GiB");
+
+    // Bound values aren't rounded the way displayed results are
+    let result = evaluate_with_bindings("rate * 1 s to MB", &bindings).unwrap();
+    assert!((result.value.value - 123.4567).abs() < 1e-9);
+
+    let result = evaluate_with_bindings("total = x + 1 GiB", &bindings).unwrap();
+    assert_eq!(result.value.format(), "6 GiB");
+    assert_eq!(result.assigned_variable, Some("total".to_string()));
+
+    // Unbound variables and line references have nothing to resolve to
+    assert!(evaluate_with_bindings("y * 2", &bindings).is_none());
+    assert!(evaluate_with_bindings("line1 * 2", &bindings).is_none());
+    assert!(evaluate_with_bindings("x * 2", &HashMap::new()).is_none());
+}
+
+#[test]
+fn test_unicode_operators() {
+    assert_eq!(evaluate_test_expression("2 × 3"), Some("6".to_string()));
+    assert_eq!(evaluate_test_expression("10 ÷ 2"), Some("5".to_string()));
+    assert_eq!(evaluate_test_expression("5 − 2"), Some("3".to_string()));
+    assert_eq!(evaluate_test_expression("2 − 5"), Some("-3".to_string()));
+    assert_eq!(evaluate_test_expression("2×3÷4"), Some("1.5".to_string()));
+    assert_eq!(
+        evaluate_test_expression("1 GiB × 3 to MiB"),
+        Some("3,072 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("10 GB ÷ 2 s"),
+        Some("5 GB/s".to_string())
+    );
+}
+
+#[test]
+fn test_in_keyword_context() {
+    // "in" followed by a unit converts, like "to"
+    // (there are no length units, so "in" is never read as inches)
+    assert_eq!(
+        evaluate_test_expression("5 GB in MB"),
+        Some("5,000 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("5 GiB in MiB"),
+        Some("5,120 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 hour in minutes"),
+        Some("60 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 QPS in req/min"),
+        Some("6,000 req/min".to_string())
+    );
+
+    // "in" in prose, not followed by a unit, is just a word
+    assert_eq!(
+        evaluate_test_expression("spent 5 GB in total"),
+        Some("5 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("in 2 hours"),
+        Some("2 h".to_string())
+    );
+}
+
+#[test]
+fn test_conversion_keywords_in_any_case() {
+    assert_eq!(
+        evaluate_test_expression("1 GiB TO MiB"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB In MiB"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("5 MB IN KB"),
+        Some("5,000 KB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("10 OUT OF 40"),
+        Some("25 %".to_string())
+    );
+    assert_eq!(evaluate_test_expression("17 MOD 5"), Some("2".to_string()));
+
+    // Punctuation after the target unit doesn't hide the conversion
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB,"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB in MiB."),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("Total: 1 GiB TO MiB, roughly"),
+        Some("1,024 MiB".to_string())
+    );
+
+    // Words that merely start with a keyword are still prose
+    assert_eq!(
+        evaluate_test_expression("Tomorrow 5 + 3"),
+        Some("8".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("Into the night 5 GiB"),
+        Some("5 GiB".to_string())
+    );
+
+    let (expression, keyword, unit) = split_trailing_conversion("2 GiB + 1 GiB TO MiB").unwrap();
+    assert_eq!(expression, "2 GiB + 1 GiB");
+    assert!(matches!(keyword, Token::To));
+    assert_eq!(unit, Unit::MiB);
+}
+
+#[test]
+fn test_line_reference_value_accessor() {
+    let previous = vec![
+        Some("10 GiB".to_string()),
+        Some("8".to_string()),
+        Some("4,096 MiB".to_string()),
+        None,
+    ];
+
+    // ".value" takes the number from a result and drops its unit
+    assert_eq!(
+        evaluate_expression_with_context("line1.value", &previous, 4),
+        Some("10".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("line1.value * 2", &previous, 4),
+        Some("20".to_string())
+    );
+    assert_eq!(
+        evaluate_expression_with_context("line3.value + line1.value", &previous, 4),
+        Some("4,106".to_string())
+    );
+    // The number can be given a new unit
+    assert_eq!(
+        evaluate_expression_with_context("line1.value as MB", &previous, 4),
+        Some("10 MB".to_string())
+    );
+    // Dimensionless results are unchanged
+    assert_eq!(
+        evaluate_expression_with_context("line2.value * 2", &previous, 4),
+        Some("16".to_string())
+    );
+    // Without ".value" the unit is kept
+    assert_eq!(
+        evaluate_expression_with_context("line1 * 2", &previous, 4),
+        Some("20 GiB".to_string())
+    );
+
+    // Lines without a result, or below the current one, don't resolve
+    assert_eq!(
+        evaluate_expression_with_context("line4.value * 2", &previous, 4),
+        None
+    );
+    assert_eq!(
+        evaluate_expression_with_context("line3.value", &previous, 1),
+        None
+    );
+
+    let variables = std::collections::HashMap::new();
+    let (result, _) = evaluate_with_variables("total = line1.value * 3", &variables, &previous, 4);
+    assert_eq!(result, Some("30".to_string()));
+}
+
+#[test]
+fn test_unary_signs() {
+    // A sign written directly in front of a number is part of the number
+    assert_eq!(evaluate_test_expression("+5 + 3"), Some("8".to_string()));
+    assert_eq!(evaluate_test_expression("-5 + 2"), Some("-3".to_string()));
+    assert_eq!(evaluate_test_expression("+5"), Some("5".to_string()));
+    assert_eq!(evaluate_test_expression("2 * -3"), Some("-6".to_string()));
+    assert_eq!(evaluate_test_expression("2 * +3"), Some("6".to_string()));
+    assert_eq!(evaluate_test_expression("10 - -2"), Some("12".to_string()));
+    assert_eq!(
+        evaluate_test_expression("-2 GiB + 3 GiB"),
+        Some("1 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("-$5 + $2"),
+        Some("-3 $".to_string())
+    );
+
+    // After a value a sign is still an operator, with or without a space
+    assert_eq!(evaluate_test_expression("10 -5"), Some("5".to_string()));
+    assert_eq!(evaluate_test_expression("(4) -1"), Some("3".to_string()));
+
+    // Other leading operators and doubled operators are still invalid
+    assert_eq!(evaluate_test_expression("* 5"), None);
+    assert_eq!(evaluate_test_expression("/ 5"), None);
+    assert_eq!(evaluate_test_expression("1 + + 2"), None);
+
+    // A sign binds looser than a power, so this needs parentheses
+    assert_eq!(evaluate_test_expression("-2^2"), None);
+
+    let mut variables = std::collections::HashMap::new();
+    let (result, assignment) = evaluate_with_variables("x = -5", &variables, &[], 0);
+    assert_eq!(result, Some("-5".to_string()));
+    assert_eq!(assignment, Some(("x".to_string(), "-5".to_string())));
+    variables.insert("x".to_string(), "-5".to_string());
+    let (result, _) = evaluate_with_variables("x * -2", &variables, &[], 1);
+    assert_eq!(result, Some("10".to_string()));
+}
+
+#[test]
+fn test_humanize_data_sizes() {
+    // Base-2 units by default
+    assert_eq!(
+        evaluate_test_expression("humanize(1536 MiB)"),
+        Some("1.5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(999 bytes)"),
+        Some("999 B".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(1024 bytes)"),
+        Some("1 KiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(0.5 GiB)"),
+        Some("512 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(3 TB)"),
+        Some("2.728 TiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(4096 EiB)"),
+        Some("4,096 EiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize(0)"),
+        Some("0 B".to_string())
+    );
+
+    // Plain numbers are a byte count
+    assert_eq!(
+        evaluate_test_expression("humanize(1536)"),
+        Some("1.5 KiB".to_string())
+    );
+
+    // Base-10 units with humanize10
+    assert_eq!(
+        evaluate_test_expression("humanize10(1536 MiB)"),
+        Some("1.611 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize10(2500000 KB)"),
+        Some("2.5 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize10(999 bytes)"),
+        Some("999 B".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize10(1 KiB)"),
+        Some("1.024 KB".to_string())
+    );
+
+    // Bits stay bits
+    assert_eq!(
+        evaluate_test_expression("humanize(8192 bit)"),
+        Some("8 Kib".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("humanize10(1500000 bit)"),
+        Some("1.5 Mb".to_string())
+    );
+
+    // Works on the result of an expression
+    assert_eq!(
+        evaluate_test_expression("humanize(512 MiB * 6)"),
+        Some("3 GiB".to_string())
+    );
+
+    // Other units aren't rescaled, only the argument itself is evaluated
+    assert_eq!(
+        evaluate_test_expression("humanize(90 seconds)"),
+        Some("90 s".to_string())
+    );
+}
+
+#[test]
+fn test_summarize_results() {
+    let results = vec![
+        Some("1 GiB".to_string()),
+        None,
+        Some("512 MiB".to_string()),
+        Some("1.5 GiB".to_string()),
+    ];
+    // Mixed units are added up the same way as "1 GiB + 512 MiB"
+    let summary = summarize_results(&results).unwrap();
+    assert_eq!(summary.sum.format(), "3,072 MiB");
+    assert_eq!(summary.average.format(), "1,024 MiB");
+    assert_eq!(summary.count, 3);
+
+    // Plain numbers
+    let results = vec![Some("10".to_string()), Some("20".to_string())];
+    let summary = summarize_results(&results).unwrap();
+    assert_eq!(summary.sum.format(), "30");
+    assert_eq!(summary.average.format(), "15");
+
+    // Results that can't be added to the others are left out
+    let results = vec![
+        Some("40 $/h".to_string()),
+        Some("3 h".to_string()),
+        Some("60 $/h".to_string()),
+    ];
+    let summary = summarize_results(&results).unwrap();
+    assert_eq!(summary.sum.format(), "100 $/h");
+    assert_eq!(summary.average.format(), "50 $/h");
+    assert_eq!(summary.count, 2);
+
+    // Nothing to sum
+    assert!(summarize_results(&[None, None]).is_none());
+    assert!(summarize_results(&[]).is_none());
+}
+
+#[test]
+fn test_d_for_days_after_numbers() {
+    assert_eq!(
+        evaluate_test_expression("5d to hours"),
+        Some("120 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("5 d to hours"),
+        Some("120 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1.5d to min"),
+        Some("2,160 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2d + 12h"),
+        Some("60 h".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5d"), Some("5 day".to_string()));
+
+    // Units starting with "d" are unaffected
+    assert_eq!(
+        evaluate_test_expression("5 days to hours"),
+        Some("120 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("5 dollars"),
+        Some("5 $".to_string())
+    );
+
+    // Away from a number "d" isn't a unit, so it can still be a variable
+    assert_eq!(crate::units::parse_unit("d"), None);
+    let mut variables = std::collections::HashMap::new();
+    variables.insert("d".to_string(), "3".to_string());
+    let (result, _) = evaluate_with_variables("d * 2", &variables, &[], 0);
+    assert_eq!(result, Some("6".to_string()));
+    let (result, assignment) = evaluate_with_variables("d = 4", &variables, &[], 0);
+    assert_eq!(result, Some("4".to_string()));
+    assert_eq!(assignment, Some(("d".to_string(), "4".to_string())));
+}
+
+#[test]
+fn test_format_expression() {
+    assert_eq!(format_expression("5+3*GiB"), "5 + 3 * GiB");
+    assert_eq!(format_expression("(1+2)*3"), "(1 + 2) * 3");
+    assert_eq!(format_expression("sqrt( 16 )+1"), "sqrt(16) + 1");
+    assert_eq!(format_expression("x=5"), "x = 5");
+    assert_eq!(format_expression("5 GiB to MiB"), "5 GiB to MiB");
+    assert_eq!(format_expression(" 2^10 "), "2 ^ 10");
+    assert_eq!(
+        format_expression("Rent: $1,200+$300"),
+        "Rent: $1,200 + $300"
+    );
+    assert_eq!(format_expression("-5+2"), "-5 + 2");
+    assert_eq!(format_expression("3*-2"), "3 * -2");
+    assert_eq!(format_expression("5×3"), "5 × 3");
+    assert_eq!(format_expression("5 + 3 * GiB"), "5 + 3 * GiB");
+
+    // Text that doesn't tokenize is returned unchanged
+    assert_eq!(format_expression("(1+2"), "(1+2");
+    assert_eq!(format_expression(""), "");
+}
+
+#[test]
+fn test_line_has_expression() {
+    assert!(!line_has_expression("hello"));
+    assert!(!line_has_expression(""));
+    assert!(!line_has_expression("42"));
+    assert!(!line_has_expression("5 GiB"));
+    assert!(line_has_expression("5 + 3"));
+    assert!(line_has_expression("5 MiB to KiB"));
+    assert!(line_has_expression("Total: 5 + 3 apples"));
+    assert!(line_has_expression("sqrt(16)"));
+    assert!(!line_has_expression("5 / 0"));
+
+    // Lone values count when asked to
+    assert!(line_has_expression_with("42", true));
+    assert!(line_has_expression_with("5 GiB", true));
+    assert!(!line_has_expression_with("hello", true));
+}
+
+#[test]
+fn test_mod_keyword() {
+    assert_eq!(evaluate_test_expression("17 mod 5"), Some("2".to_string()));
+    assert_eq!(evaluate_test_expression("-7 mod 3"), Some("2".to_string()));
+    assert_eq!(
+        evaluate_test_expression("2 + 17 mod 5 * 2"),
+        Some("6".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(10 + 7) mod (2 + 3)"),
+        Some("2".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5 mod 0"), None);
+
+    // Unit values keep the left unit
+    assert_eq!(
+        evaluate_test_expression("17 GiB mod 5"),
+        Some("2 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("100 min mod 1 hour"),
+        Some("40 min".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5 GiB mod 1 hour"), None);
+
+    // "mo" is still a month, and prose around "mod" is skipped as before
+    assert_eq!(
+        evaluate_test_expression("5 mo"),
+        Some("5 month".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("ask the mod 5 times"),
+        Some("5".to_string())
+    );
+}
+
+#[test]
+fn test_inline_comments_are_not_evaluated() {
+    assert_eq!(
+        evaluate_test_expression("5 * 3 # three fives"),
+        Some("15".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("5 * 3 # 2 extra"),
+        Some("15".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB # for the cache"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("# Budget for 2024"), None);
+    assert_eq!(evaluate_test_expression("## 5 GiB"), None);
+
+    // A '#' inside quotes doesn't start a comment
+    assert_eq!(
+        evaluate_test_expression("Room \"#A\" 5 + 3"),
+        Some("8".to_string())
+    );
+
+    assert_eq!(strip_comment("5 * 3 # three fives"), "5 * 3 ");
+    assert_eq!(strip_comment("no comment"), "no comment");
+}
+
+#[test]
+fn test_fractions_written_as_words() {
+    assert_eq!(
+        evaluate_test_expression("half of 1 GiB"),
+        Some("0.5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("Half of 1 GiB"),
+        Some("0.5 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("a quarter of 1 hour"),
+        Some("0.25 h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("a third of 90 min"),
+        Some("30 min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("half of $250"),
+        Some("125 $".to_string())
+    );
+
+    // Elsewhere the words are prose or units as before
+    assert_eq!(
+        evaluate_test_expression("the other half 5 + 3"),
+        Some("8".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("halfway there 5 + 3"),
+        Some("8".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 quarter to months"),
+        Some("3 month".to_string())
+    );
+    assert_eq!(evaluate_test_expression("third"), None);
+
+    // Half of 1 GiB is 512 MiB
+    let half = evaluate_expression_value_with_context("half of 1 GiB", &[], 0).unwrap();
+    assert_eq!(half.to_unit(&Unit::MiB).unwrap().value, 512.0);
+}
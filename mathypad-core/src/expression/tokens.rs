@@ -1,6 +1,7 @@
 //! Token definitions for mathematical expressions
 
-use crate::units::Unit;
+use crate::units::{Unit, UnitValue};
+use std::fmt;
 
 /// Tokens for mathematical expressions with unit support
 #[derive(Debug, Clone)]
@@ -12,13 +13,105 @@ pub enum Token {
     Multiply,
     Divide,
     Power,
+    Modulo, // for remainders like "17 mod 5"
     LeftParen,
     RightParen,
-    To,                   // for conversions like "to KiB"
-    In,                   // for conversions like "in KiB"
-    Of,                   // for percentage operations like "10% of 50"
-    LineReference(usize), // for referencing other lines like "line1", "line2"
-    Variable(String),     // for variable references like "servers", "ram"
-    Assign,               // for assignment operator "="
-    Function(String),     // for function calls like "sqrt", "sin", "cos"
+    To,                      // for conversions like "to KiB"
+    In,                      // for conversions like "in KiB"
+    Of,                      // for percentage operations like "10% of 50"
+    OutOf,                   // for ratios as percentages like "750 GiB out of 1 TiB"
+    As,                      // for attaching a unit to a plain number like "42 as GiB"
+    LineReference(usize),    // for referencing other lines like "line1", "line2"
+    LineValue(usize),        // for a line's number without its unit like "line1.value"
+    LineRange(usize, usize), // for ranges of lines like "line1..line10"
+    Variable(String),        // for variable references like "servers", "ram"
+    Assign,                  // for assignment operator "="
+    Function(String),        // for function calls like "sqrt", "sin", "cos"
+    Greater,                 // for comparisons like "1 GiB > 1000 MiB"
+    Less,                    // for comparisons like "90 min < 2 hours"
+    GreaterEqual,            // for comparisons with ">="
+    LessEqual,               // for comparisons with "<="
+    Equal,                   // for equality comparisons with "=="
+    Question,                // for conditionals like "a > b ? 1 : 2"
+    Colon,                   // separates the branches of a conditional
+}
+
+impl Token {
+    /// Check if this token introduces a target unit ("to", "in" or "as")
+    pub fn is_conversion(&self) -> bool {
+        matches!(self, Token::To | Token::In | Token::As)
+    }
+
+    /// Check if this token is a comparison operator (">", "<", ">=", "<=", "==")
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Token::Greater | Token::Less | Token::GreaterEqual | Token::LessEqual | Token::Equal
+        )
+    }
+
+    /// Check if this token is a binary operator (arithmetic or comparison)
+    pub fn is_binary_operator(&self) -> bool {
+        matches!(
+            self,
+            Token::Plus
+                | Token::Minus
+                | Token::Multiply
+                | Token::Divide
+                | Token::Power
+                | Token::Modulo
+                | Token::OutOf
+        ) || self.is_comparison()
+    }
+
+    /// Check if this token can end a value, so a "+" or "-" after it is an operator
+    /// rather than a sign
+    pub fn ends_value(&self) -> bool {
+        matches!(
+            self,
+            Token::Number(_)
+                | Token::NumberWithUnit(_, _)
+                | Token::LineReference(_)
+                | Token::LineValue(_)
+                | Token::Variable(_)
+                | Token::RightParen
+        )
+    }
+}
+
+impl fmt::Display for Token {
+    /// Write the token roughly as it would be typed, e.g. "1 GiB", "+" or "line2"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", UnitValue::new(*n, None).format()),
+            Token::NumberWithUnit(n, unit) => {
+                write!(f, "{}", UnitValue::new(*n, Some(unit.clone())).format())
+            }
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Multiply => write!(f, "*"),
+            Token::Divide => write!(f, "/"),
+            Token::Power => write!(f, "^"),
+            Token::Modulo => write!(f, "mod"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::To => write!(f, "to"),
+            Token::In => write!(f, "in"),
+            Token::Of => write!(f, "of"),
+            Token::OutOf => write!(f, "out of"),
+            Token::As => write!(f, "as"),
+            Token::LineReference(index) => write!(f, "line{}", index + 1),
+            Token::LineValue(index) => write!(f, "line{}.value", index + 1),
+            Token::LineRange(start, end) => write!(f, "line{}..line{}", start + 1, end + 1),
+            Token::Variable(name) | Token::Function(name) => write!(f, "{}", name),
+            Token::Assign => write!(f, "="),
+            Token::Greater => write!(f, ">"),
+            Token::Less => write!(f, "<"),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::LessEqual => write!(f, "<="),
+            Token::Equal => write!(f, "=="),
+            Token::Question => write!(f, "?"),
+            Token::Colon => write!(f, ":"),
+        }
+    }
 }
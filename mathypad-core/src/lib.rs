@@ -5,6 +5,7 @@
 
 pub mod core;
 pub mod expression;
+pub mod settings;
 pub mod units;
 
 // Constants used throughout the application
@@ -12,8 +13,12 @@ pub const MAX_INTEGER_FOR_FORMATTING: f64 = 1e15;
 pub const FLOAT_EPSILON: f64 = f64::EPSILON;
 
 // Re-export commonly used types for convenience
+pub use core::{EngineBuilder, MathypadEngine};
 pub use expression::{
-    evaluator::{evaluate_expression_with_context, evaluate_with_variables},
+    evaluator::{
+        EvalResult, evaluate_expression_with_context, evaluate_with_bindings,
+        evaluate_with_variables, line_has_expression, line_has_expression_with,
+    },
     parser::*,
 };
 pub use units::{Unit, UnitType, UnitValue, parse_unit};
@@ -0,0 +1,250 @@
+//! Evaluation settings that change how results are computed
+//!
+//! Settings are stored per thread so the editor can change them at runtime
+//! (e.g., via `:addunit left`) without threading them through every evaluator call.
+
+use std::cell::Cell;
+
+/// Which unit to keep when adding or subtracting values with compatible units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdditionUnitPolicy {
+    /// Use the smaller unit, e.g. "2 TB + 500 GB" = "2,500 GB"
+    #[default]
+    Smaller,
+    /// Keep the left operand's unit, e.g. "2 TB + 500 GB" = "2.5 TB"
+    Left,
+    /// Use the larger unit, e.g. "500 GB + 2 TB" = "2.5 TB"
+    Larger,
+}
+
+impl AdditionUnitPolicy {
+    /// Parse a policy name as typed in the `:addunit` command
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "smaller" => Some(AdditionUnitPolicy::Smaller),
+            "left" => Some(AdditionUnitPolicy::Left),
+            "larger" => Some(AdditionUnitPolicy::Larger),
+            _ => None,
+        }
+    }
+}
+
+/// How result numbers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    /// Digits grouped in thousands, e.g. "1,500,000,000"
+    #[default]
+    Plain,
+    /// One digit before the point and an exponent, e.g. "1.5e9"
+    Scientific,
+    /// Exponents that are multiples of 3, e.g. "1.5e9" or "12.5e3"
+    Engineering,
+}
+
+impl Notation {
+    /// Parse a notation name as typed in the `:notation` command
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "plain" => Some(Notation::Plain),
+            "scientific" | "sci" => Some(Notation::Scientific),
+            "engineering" | "eng" => Some(Notation::Engineering),
+            _ => None,
+        }
+    }
+}
+
+/// A group of related units that can be turned off
+///
+/// With a family off its unit names aren't read as units, so e.g. "5 min" is just
+/// the number 5 followed by a word. Rates need both of their families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitFamily {
+    /// Durations like "ms", "hours" and "months"
+    Time,
+    /// Bits and bytes, like "Mb" and "GiB"
+    Data,
+    /// Request counts like "req" and "queries"
+    Requests,
+    /// Money like "$" and "EUR"
+    Currency,
+    /// Pressures like "Pa" and "psi"
+    Pressure,
+    /// "%"
+    Percent,
+}
+
+impl UnitFamily {
+    /// Every family, in declaration order
+    pub const ALL: [UnitFamily; 6] = [
+        UnitFamily::Time,
+        UnitFamily::Data,
+        UnitFamily::Requests,
+        UnitFamily::Currency,
+        UnitFamily::Pressure,
+        UnitFamily::Percent,
+    ];
+
+    /// Parse a family name as typed in the `:units` command
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "time" => Some(UnitFamily::Time),
+            "data" => Some(UnitFamily::Data),
+            "requests" | "request" => Some(UnitFamily::Requests),
+            "currency" | "money" => Some(UnitFamily::Currency),
+            "pressure" => Some(UnitFamily::Pressure),
+            "percent" | "percentage" => Some(UnitFamily::Percent),
+            _ => None,
+        }
+    }
+
+    /// This family's bit in the set of disabled families
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Default number of `FLOAT_EPSILON`s (relative to the value) that a result may be
+/// away from an integer and still be treated as one, e.g. "(0.1 + 0.2) * 10" = "3"
+pub const DEFAULT_INTEGER_TOLERANCE_ULPS: f64 = 64.0;
+
+/// Default number of decimal places results are rounded to, e.g. "73.242 %"
+pub const DEFAULT_DECIMAL_PLACES: usize = 3;
+
+/// Smallest bare number treated as a byte count when `assume_bytes` is on
+pub const ASSUME_BYTES_THRESHOLD: f64 = 1024.0;
+
+thread_local! {
+    static ADDITION_UNIT_POLICY: Cell<AdditionUnitPolicy> = Cell::new(AdditionUnitPolicy::default());
+    static ASSUME_BYTES: Cell<bool> = const { Cell::new(false) };
+    static STRICT_EXPRESSIONS: Cell<bool> = const { Cell::new(false) };
+    static DECIMAL_COMMA: Cell<bool> = const { Cell::new(false) };
+    static NOTATION: Cell<Notation> = Cell::new(Notation::default());
+    static INTEGER_TOLERANCE_ULPS: Cell<f64> = const { Cell::new(DEFAULT_INTEGER_TOLERANCE_ULPS) };
+    static DECIMAL_PLACES: Cell<usize> = const { Cell::new(DEFAULT_DECIMAL_PLACES) };
+    static DISABLED_UNIT_FAMILIES: Cell<u8> = const { Cell::new(0) };
+}
+
+/// Get the current addition result unit policy
+pub fn addition_unit_policy() -> AdditionUnitPolicy {
+    ADDITION_UNIT_POLICY.with(|policy| policy.get())
+}
+
+/// Set the addition result unit policy used by subsequent evaluations
+pub fn set_addition_unit_policy(policy: AdditionUnitPolicy) {
+    ADDITION_UNIT_POLICY.with(|current| current.set(policy));
+}
+
+/// Whether large bare numbers are read as byte counts (off by default)
+///
+/// When on, whole numbers of at least `ASSUME_BYTES_THRESHOLD` without a unit
+/// (e.g., a pasted "1073741824") are tokenized as bytes. Smaller numbers stay
+/// dimensionless so factors like "* 2" keep working.
+pub fn assume_bytes() -> bool {
+    ASSUME_BYTES.with(|enabled| enabled.get())
+}
+
+/// Turn reading large bare numbers as bytes on or off (`:assume bytes`/`:assume none`)
+pub fn set_assume_bytes(enabled: bool) {
+    ASSUME_BYTES.with(|current| current.set(enabled));
+}
+
+/// Whether a line must be entirely an expression to get a result (off by default)
+///
+/// Normally expressions are found inside prose, so "cost is 5 * 3" shows 15. In
+/// strict mode that line has no result, while "5 * 3" on its own still does.
+pub fn strict_expressions() -> bool {
+    STRICT_EXPRESSIONS.with(|enabled| enabled.get())
+}
+
+/// Turn strict expression mode on or off (`:strict on`/`:strict off`)
+pub fn set_strict_expressions(enabled: bool) {
+    STRICT_EXPRESSIONS.with(|current| current.set(enabled));
+}
+
+/// Whether numbers use European style, with a decimal comma (off by default)
+///
+/// When on, "3,14" is read as 3.14 and "1.000" as a thousand, and results are shown
+/// the same way (e.g., "1.234,5 MB").
+pub fn decimal_comma() -> bool {
+    DECIMAL_COMMA.with(|enabled| enabled.get())
+}
+
+/// Switch between European and default number style (`:decimal comma`/`:decimal point`)
+pub fn set_decimal_comma(enabled: bool) {
+    DECIMAL_COMMA.with(|current| current.set(enabled));
+}
+
+/// Get how result numbers are written (plain by default)
+pub fn notation() -> Notation {
+    NOTATION.with(|notation| notation.get())
+}
+
+/// Set how result numbers are written (`:notation plain|scientific|engineering`)
+pub fn set_notation(notation: Notation) {
+    NOTATION.with(|current| current.set(notation));
+}
+
+/// Get the number of decimal places plain results are rounded to
+///
+/// Trailing zeros are dropped, so with 3 places 2.5 is still written "2.5".
+pub fn decimal_places() -> usize {
+    DECIMAL_PLACES.with(|places| places.get())
+}
+
+/// Set the number of decimal places plain results are rounded to
+pub fn set_decimal_places(places: usize) {
+    DECIMAL_PLACES.with(|current| current.set(places));
+}
+
+/// Whether units of a family are read as units (all families are on by default)
+pub fn unit_family_enabled(family: UnitFamily) -> bool {
+    DISABLED_UNIT_FAMILIES.with(|disabled| disabled.get() & family.bit() == 0)
+}
+
+/// Turn a unit family on or off (`:units enable|disable <family>`)
+pub fn set_unit_family_enabled(family: UnitFamily, enabled: bool) {
+    DISABLED_UNIT_FAMILIES.with(|disabled| {
+        if enabled {
+            disabled.set(disabled.get() & !family.bit());
+        } else {
+            disabled.set(disabled.get() | family.bit());
+        }
+    });
+}
+
+/// Swap "," and "." wherever they sit between two digits
+///
+/// Converts numbers between European ("1.234,5") and default ("1,234.5") style,
+/// leaving other punctuation like "5, 6" or "line1." alone.
+pub fn swap_decimal_separators(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let is_digit_at = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_ascii_digit())
+    };
+
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| match c {
+            ',' | '.' if is_digit_at(i.checked_sub(1)) && is_digit_at(Some(i + 1)) => {
+                if c == ',' {
+                    '.'
+                } else {
+                    ','
+                }
+            }
+            c => c,
+        })
+        .collect()
+}
+
+/// Get the tolerance, in `FLOAT_EPSILON`s, for snapping results to integers
+pub fn integer_tolerance_ulps() -> f64 {
+    INTEGER_TOLERANCE_ULPS.with(|ulps| ulps.get())
+}
+
+/// Set the tolerance, in `FLOAT_EPSILON`s, for snapping results to integers (0 disables snapping)
+pub fn set_integer_tolerance_ulps(ulps: f64) {
+    INTEGER_TOLERANCE_ULPS.with(|current| current.set(ulps.max(0.0)));
+}
@@ -12,6 +12,7 @@ mod value;
 #[cfg(test)]
 mod tests;
 
-pub use parser::parse_unit;
+pub use parser::{find_unit_suggestion, parse_unit, suggest_unit, supported_units};
 pub use types::{Unit, UnitConversionError, UnitType};
 pub use value::UnitValue;
+pub(crate) use value::format_number_with_commas;
@@ -3,107 +3,62 @@
 use super::types::Unit;
 use crate::UnitType;
 use crate::rate_unit;
+use std::borrow::Cow;
 
 /// Parse a unit string into a Unit enum variant
+///
+/// Units of a family that is turned off (see `set_unit_family_enabled`) aren't parsed.
 pub fn parse_unit(text: &str) -> Option<Unit> {
+    parse_any_unit(text).filter(Unit::is_enabled)
+}
+
+/// Parse a unit string, whether or not its family is turned on
+fn parse_any_unit(text: &str) -> Option<Unit> {
+    // The micro sign (or Greek mu) is interchangeable with the ASCII "u" prefix,
+    // so every micro unit only needs its "u" spelling (e.g., "µs" parses as "us")
+    if let Some(rest) = text.strip_prefix(['µ', 'μ']) {
+        return parse_any_unit(&format!("u{}", rest));
+    }
+
     // First try case-sensitive matching for bits vs bytes disambiguation
-    match text {
-        // Bit units (lowercase 'b' for bits)
-        "bit" | "bits" => return Some(Unit::Bit),
-        "Kb" => return Some(Unit::Kb),
-        "Mb" => return Some(Unit::Mb),
-        "Gb" => return Some(Unit::Gb),
-        "Tb" => return Some(Unit::Tb),
-        "Pb" => return Some(Unit::Pb),
-        "Eb" => return Some(Unit::Eb),
-        "Kib" => return Some(Unit::Kib),
-        "Mib" => return Some(Unit::Mib),
-        "Gib" => return Some(Unit::Gib),
-        "Tib" => return Some(Unit::Tib),
-        "Pib" => return Some(Unit::Pib),
-        "Eib" => return Some(Unit::Eib),
-
-        // Byte units (uppercase 'B' for bytes)
-        "B" | "byte" | "bytes" => return Some(Unit::Byte),
-        "KB" => return Some(Unit::KB),
-        "MB" => return Some(Unit::MB),
-        "GB" => return Some(Unit::GB),
-        "TB" => return Some(Unit::TB),
-        "PB" => return Some(Unit::PB),
-        "EB" => return Some(Unit::EB),
-        "KiB" => return Some(Unit::KiB),
-        "MiB" => return Some(Unit::MiB),
-        "GiB" => return Some(Unit::GiB),
-        "TiB" => return Some(Unit::TiB),
-        "PiB" => return Some(Unit::PiB),
-        "EiB" => return Some(Unit::EiB),
-
-        // Traditional rate unit patterns - create generic rates
-        "bps" | "bit/s" | "bits/s" => {
-            return Some(rate_unit!(Unit::Bit, Unit::Second));
-        }
-        "Kbps" | "Kb/s" => return Some(rate_unit!(Unit::Kb, Unit::Second)),
-        "Mbps" | "Mb/s" => return Some(rate_unit!(Unit::Mb, Unit::Second)),
-        "Gbps" | "Gb/s" => return Some(rate_unit!(Unit::Gb, Unit::Second)),
-        "Tbps" | "Tb/s" => return Some(rate_unit!(Unit::Tb, Unit::Second)),
-        "Pbps" | "Pb/s" => return Some(rate_unit!(Unit::Pb, Unit::Second)),
-        "Ebps" | "Eb/s" => return Some(rate_unit!(Unit::Eb, Unit::Second)),
-        "Kibps" | "Kib/s" => {
-            return Some(rate_unit!(Unit::Kib, Unit::Second));
-        }
-        "Mibps" | "Mib/s" => {
-            return Some(rate_unit!(Unit::Mib, Unit::Second));
-        }
-        "Gibps" | "Gib/s" => {
-            return Some(rate_unit!(Unit::Gib, Unit::Second));
-        }
-        "Tibps" | "Tib/s" => {
-            return Some(rate_unit!(Unit::Tib, Unit::Second));
-        }
-        "Pibps" | "Pib/s" => {
-            return Some(rate_unit!(Unit::Pib, Unit::Second));
-        }
-        "Eibps" | "Eib/s" => {
-            return Some(rate_unit!(Unit::Eib, Unit::Second));
-        }
+    // Lowercase 'b' for bits, uppercase 'B' for bytes
+    if let Some((_, unit)) = BASE_DATA_UNITS.iter().find(|(name, _)| *name == text) {
+        return Some(unit.clone());
+    }
 
-        // Byte rates (uppercase 'B/s' for bytes per second)
-        "B/s" => return Some(rate_unit!(Unit::Byte, Unit::Second)),
-        "KB/s" => return Some(rate_unit!(Unit::KB, Unit::Second)),
-        "MB/s" => return Some(rate_unit!(Unit::MB, Unit::Second)),
-        "GB/s" => return Some(rate_unit!(Unit::GB, Unit::Second)),
-        "TB/s" => return Some(rate_unit!(Unit::TB, Unit::Second)),
-        "PB/s" => return Some(rate_unit!(Unit::PB, Unit::Second)),
-        "EB/s" => return Some(rate_unit!(Unit::EB, Unit::Second)),
-        "KiB/s" => return Some(rate_unit!(Unit::KiB, Unit::Second)),
-        "MiB/s" => return Some(rate_unit!(Unit::MiB, Unit::Second)),
-        "GiB/s" => return Some(rate_unit!(Unit::GiB, Unit::Second)),
-        "TiB/s" => return Some(rate_unit!(Unit::TiB, Unit::Second)),
-        "PiB/s" => return Some(rate_unit!(Unit::PiB, Unit::Second)),
-        "EiB/s" => return Some(rate_unit!(Unit::EiB, Unit::Second)),
+    // Prefixed units like "GiB", "Mb", "Gbps" and "kPa"
+    if let Some(unit) = parse_prefixed_unit(text) {
+        return Some(unit);
+    }
 
-        _ => {} // Fall through to case-insensitive matching
+    // Rates of prefixed data units keep their case-sensitive meaning, so "Gib/s" is
+    // gibibits per second even though the legacy lowercase "gib/s" means bytes
+    if let Some((numerator, _)) = text.split_once('/') {
+        if parse_prefixed_unit(numerator.trim()).is_some() {
+            return parse_rate_unit(text);
+        }
     }
 
     // Case-insensitive matching for remaining units
-    match text.to_lowercase().as_str() {
-        "ns" | "nanosec" | "nanosecond" | "nanoseconds" => Some(Unit::Nanosecond),
-        "us" | "µs" | "microsec" | "microsecond" | "microseconds" => Some(Unit::Microsecond),
-        "ms" | "millisec" | "millisecond" | "milliseconds" => Some(Unit::Millisecond),
-        "s" | "sec" | "second" | "seconds" => Some(Unit::Second),
-        "min" | "minute" | "minutes" => Some(Unit::Minute),
-        "h" | "hr" | "hour" | "hours" => Some(Unit::Hour),
-        "day" | "days" => Some(Unit::Day),
-        "week" | "weeks" | "wk" | "wks" => Some(Unit::Week),
-        "month" | "months" | "mo" | "mos" => Some(Unit::Month),
-        "quarter" | "quarters" | "qtr" | "qtrs" => Some(Unit::Quarter),
-        "year" | "years" | "yr" | "yrs" => Some(Unit::Year),
+    let lowercase = text.to_lowercase();
+    if let Some((_, unit)) = NAMED_UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&lowercase.as_str()))
+    {
+        return Some(unit.clone());
+    }
+    if let Some((_, numerator, denominator)) = RATE_ABBREVIATIONS
+        .iter()
+        .find(|(name, ..)| *name == lowercase)
+    {
+        return Some(rate_unit!(numerator.clone(), denominator.clone()));
+    }
 
+    match lowercase.as_str() {
         // Case-insensitive parsing (backwards compatibility)
         // For ambiguous lowercase units, follow networking conventions:
         // - Byte units (kb, mb, gb) default to bytes
         // - Bit units (kib, mib, gib when lowercase) default to base 10 bits for simplicity
-        "b" | "byte" | "bytes" => Some(Unit::Byte),
         "kb" => Some(Unit::KB), // Kilobytes
         "mb" => Some(Unit::MB), // Megabytes
         "gb" => Some(Unit::GB), // Gigabytes
@@ -122,24 +77,14 @@ pub fn parse_unit(text: &str) -> Option<Unit> {
 
         // Case-insensitive rate parsing - create generic rates
         // For "bps" suffix: bits take precedence (network convention)
-        // For "/s" suffix: bytes take precedence (file transfer convention)
-        "b/s" | "bytes/s" => Some(rate_unit!(Unit::Byte, Unit::Second)),
-        "kb/s" => Some(rate_unit!(Unit::KB, Unit::Second)),
-        "mb/s" => Some(rate_unit!(Unit::MB, Unit::Second)),
-        "gb/s" => Some(rate_unit!(Unit::GB, Unit::Second)),
-        "tb/s" => Some(rate_unit!(Unit::TB, Unit::Second)),
-        "pb/s" => Some(rate_unit!(Unit::PB, Unit::Second)),
-        "eb/s" => Some(rate_unit!(Unit::EB, Unit::Second)),
+        // For "/s" suffix: bytes take precedence (file transfer convention), so the
+        // lowercase binary prefixes that mean bits on their own mean bytes here
         "kib/s" => Some(rate_unit!(Unit::KiB, Unit::Second)),
         "mib/s" => Some(rate_unit!(Unit::MiB, Unit::Second)),
         "gib/s" => Some(rate_unit!(Unit::GiB, Unit::Second)),
-        "tib/s" => Some(rate_unit!(Unit::TiB, Unit::Second)),
-        "pib/s" => Some(rate_unit!(Unit::PiB, Unit::Second)),
-        "eib/s" => Some(rate_unit!(Unit::EiB, Unit::Second)),
 
         // For "bps" suffix: default to bits (network convention)
         // Exception: very large units (PB/EB) default to bytes for backwards compatibility
-        "bps" => Some(rate_unit!(Unit::Bit, Unit::Second)),
         "kbps" => Some(rate_unit!(Unit::Kb, Unit::Second)),
         "mbps" => Some(rate_unit!(Unit::Mb, Unit::Second)),
         "gbps" => Some(rate_unit!(Unit::Gb, Unit::Second)),
@@ -153,61 +98,324 @@ pub fn parse_unit(text: &str) -> Option<Unit> {
         "pibps" => Some(rate_unit!(Unit::PiB, Unit::Second)), // Exception: PiB default to bytes
         "eibps" => Some(rate_unit!(Unit::EiB, Unit::Second)), // Exception: EiB default to bytes
 
-        "req" | "request" | "requests" => Some(Unit::Request),
-        "query" | "queries" => Some(Unit::Query),
+        _ => parse_rate_unit(text),
+    }
+}
 
-        "req/s" | "requests/s" | "rps" => Some(Unit::RateUnit(
-            Box::new(Unit::Request),
-            Box::new(Unit::Second),
-        )),
-        "req/min" | "req/minute" | "requests/min" | "requests/minute" | "rpm" => {
-            Some(rate_unit!(Unit::Request, Unit::Minute))
-        }
-        "req/h" | "req/hour" | "requests/h" | "requests/hour" | "rph" => Some(Unit::RateUnit(
-            Box::new(Unit::Request),
-            Box::new(Unit::Hour),
-        )),
-        "qps" | "queries/s" | "queries/sec" => Some(Unit::RateUnit(
-            Box::new(Unit::Query),
-            Box::new(Unit::Second),
-        )),
-        "qpm" | "queries/min" | "queries/minute" => Some(Unit::RateUnit(
-            Box::new(Unit::Query),
-            Box::new(Unit::Minute),
-        )),
-        "qph" | "queries/h" | "queries/hour" => Some(rate_unit!(Unit::Query, Unit::Hour)),
-
-        "%" | "percent" | "percentage" => Some(Unit::Percent),
-
-        // Currency symbols and codes
-        "$" | "usd" | "dollar" | "dollars" => Some(Unit::USD),
-        "€" | "eur" | "euro" | "euros" => Some(Unit::EUR),
-        "£" | "gbp" | "pound" | "pounds" | "sterling" => Some(Unit::GBP),
-        "¥" | "jpy" | "yen" => Some(Unit::JPY),
-        "cny" | "yuan" | "rmb" => Some(Unit::CNY),
-        "c$" | "cad" | "canadian" => Some(Unit::CAD),
-        "a$" | "aud" | "australian" => Some(Unit::AUD),
-        "chf" | "franc" => Some(Unit::CHF),
-        "₹" | "inr" | "rupee" | "rupees" => Some(Unit::INR),
-        "₩" | "krw" | "won" => Some(Unit::KRW),
-
-        _ => {
-            let mut rate_type = None;
-            if let Some(slash_pos) = text.find('/') {
-                // Use split_at which ensures we split at a valid char boundary
-                let (left_part, right_part) = text.split_at(slash_pos);
-                // Skip the '/' character safely
-                if let Some(right_part) = right_part.strip_prefix('/') {
-                    let left_unit = parse_unit(left_part);
-                    let right_unit = parse_unit(right_part);
-                    if let (Some(left_unit), Some(right_unit)) = (left_unit, right_unit) {
-                        if right_unit.unit_type() == UnitType::Time {
-                            rate_type = Some(rate_unit!(left_unit, right_unit))
-                        }
-                    }
+/// Case-sensitive names of the unprefixed data units
+const BASE_DATA_UNITS: &[(&str, Unit)] = &[
+    ("B", Unit::Byte),
+    ("byte", Unit::Byte),
+    ("bytes", Unit::Byte),
+    ("bit", Unit::Bit),
+    ("bits", Unit::Bit),
+];
+
+/// Case-insensitive spellings of the units that aren't built from a prefix or a rate
+const NAMED_UNITS: &[(&[&str], Unit)] = &[
+    // Time units
+    (
+        &["ns", "nanosec", "nanosecond", "nanoseconds"],
+        Unit::Nanosecond,
+    ),
+    (
+        &["us", "usec", "microsec", "microsecond", "microseconds"],
+        Unit::Microsecond,
+    ),
+    (
+        &["ms", "millisec", "millisecond", "milliseconds"],
+        Unit::Millisecond,
+    ),
+    (&["s", "sec", "second", "seconds"], Unit::Second),
+    (&["min", "minute", "minutes"], Unit::Minute),
+    (&["h", "hr", "hour", "hours"], Unit::Hour),
+    (&["day", "days"], Unit::Day),
+    (&["week", "weeks", "wk", "wks"], Unit::Week),
+    (&["month", "months", "mo", "mos"], Unit::Month),
+    (&["quarter", "quarters", "qtr", "qtrs"], Unit::Quarter),
+    (&["year", "years", "yr", "yrs"], Unit::Year),
+    // Bytes (lowercase "b" defaults to bytes)
+    (&["b", "byte", "bytes"], Unit::Byte),
+    // Requests
+    (&["req", "reqs", "request", "requests"], Unit::Request),
+    (&["query", "queries"], Unit::Query),
+    (&["%", "percent", "percentage"], Unit::Percent),
+    // Pressure ("kPa" and "MPa" are prefixed units; "ksi" is kilo-psi without the
+    // "p", so it isn't a prefix and a base)
+    (&["pa", "pascal", "pascals"], Unit::Pascal),
+    (&["kilopascal", "kilopascals"], Unit::Kilopascal),
+    (&["megapascal", "megapascals"], Unit::Megapascal),
+    (&["bar", "bars"], Unit::Bar),
+    (&["psi"], Unit::Psi),
+    (&["ksi"], Unit::Ksi),
+    (&["atm", "atmosphere", "atmospheres"], Unit::Atmosphere),
+    // Currency symbols and codes
+    (&["$", "usd", "dollar", "dollars"], Unit::USD),
+    (&["€", "eur", "euro", "euros"], Unit::EUR),
+    (&["£", "gbp", "pound", "pounds", "sterling"], Unit::GBP),
+    (&["¥", "jpy", "yen"], Unit::JPY),
+    (&["cny", "yuan", "rmb"], Unit::CNY),
+    (&["c$", "cad", "canadian"], Unit::CAD),
+    (&["a$", "aud", "australian"], Unit::AUD),
+    (&["chf", "franc"], Unit::CHF),
+    (&["₹", "inr", "rupee", "rupees"], Unit::INR),
+    (&["₩", "krw", "won"], Unit::KRW),
+];
+
+/// Case-insensitive abbreviations for common rates (rates written with "/" are
+/// parsed by parse_rate_unit)
+const RATE_ABBREVIATIONS: &[(&str, Unit, Unit)] = &[
+    ("bps", Unit::Bit, Unit::Second),
+    ("rps", Unit::Request, Unit::Second),
+    ("rpm", Unit::Request, Unit::Minute),
+    ("rph", Unit::Request, Unit::Hour),
+    ("qps", Unit::Query, Unit::Second),
+    ("qpm", Unit::Query, Unit::Minute),
+    ("qph", Unit::Query, Unit::Hour),
+];
+
+/// List every unit name `parse_unit` accepts, grouped by unit type
+///
+/// Names come from the same tables `parse_unit` uses, so the list can't drift.
+/// Legacy lowercase aliases (like "gib" for gigabits) and rates built with "/" are
+/// not listed.
+pub fn supported_units() -> Vec<(UnitType, Vec<Cow<'static, str>>)> {
+    let mut names: Vec<Cow<'static, str>> = Vec::new();
+    names.extend(BASE_DATA_UNITS.iter().map(|(name, _)| Cow::Borrowed(*name)));
+    for (prefix, ..) in DATA_PREFIXES {
+        names.push(Cow::Owned(format!("{}B", prefix)));
+        names.push(Cow::Owned(format!("{}b", prefix)));
+        names.push(Cow::Owned(format!("{}bps", prefix)));
+    }
+    for (prefix, _) in PASCAL_PREFIXES {
+        names.push(Cow::Owned(format!("{}Pa", prefix)));
+    }
+    for (spellings, _) in NAMED_UNITS {
+        names.extend(spellings.iter().map(|name| Cow::Borrowed(*name)));
+    }
+    names.extend(
+        RATE_ABBREVIATIONS
+            .iter()
+            .map(|(name, ..)| Cow::Borrowed(*name)),
+    );
+
+    let mut groups: Vec<(UnitType, Vec<Cow<'static, str>>)> = Vec::new();
+    for name in names {
+        let Some(unit) = parse_unit(&name) else {
+            continue;
+        };
+        let unit_type = unit.unit_type();
+        match groups
+            .iter_mut()
+            .find(|(group_type, _)| *group_type == unit_type)
+        {
+            Some((_, group)) => {
+                if !group.contains(&name) {
+                    group.push(name);
                 }
             }
-            rate_type
+            None => groups.push((unit_type, vec![name])),
         }
     }
+    groups
+}
+
+/// SI and binary prefixes, with the byte and bit units each one produces
+const DATA_PREFIXES: &[(&str, Unit, Unit)] = &[
+    ("K", Unit::KB, Unit::Kb),
+    ("M", Unit::MB, Unit::Mb),
+    ("G", Unit::GB, Unit::Gb),
+    ("T", Unit::TB, Unit::Tb),
+    ("P", Unit::PB, Unit::Pb),
+    ("E", Unit::EB, Unit::Eb),
+    ("Ki", Unit::KiB, Unit::Kib),
+    ("Mi", Unit::MiB, Unit::Mib),
+    ("Gi", Unit::GiB, Unit::Gib),
+    ("Ti", Unit::TiB, Unit::Tib),
+    ("Pi", Unit::PiB, Unit::Pib),
+    ("Ei", Unit::EiB, Unit::Eib),
+];
+
+/// SI prefixes of the pascal, with the unit each one produces
+///
+/// Pressure uses the standard lowercase "k" and no binary prefixes, so it can't
+/// share `DATA_PREFIXES`.
+const PASCAL_PREFIXES: &[(&str, Unit)] = &[("k", Unit::Kilopascal), ("M", Unit::Megapascal)];
+
+/// Parse a case-sensitive `<prefix><base>` unit like "GiB", "Mb", "Gbps" or "kPa"
+///
+/// The base is "B" for bytes, "b" for bits or "bps" for bits per second with any
+/// SI or binary prefix in `DATA_PREFIXES`, or "Pa" for pascals with a prefix in
+/// `PASCAL_PREFIXES`. Case matters, since "mPa" would be millipascals.
+fn parse_prefixed_unit(text: &str) -> Option<Unit> {
+    let lookup = |prefix: &str| DATA_PREFIXES.iter().find(|(name, ..)| *name == prefix);
+
+    if let Some(prefix) = text.strip_suffix("Pa") {
+        let (_, unit) = PASCAL_PREFIXES.iter().find(|(name, _)| *name == prefix)?;
+        Some(unit.clone())
+    } else if let Some(prefix) = text.strip_suffix("bps") {
+        let (_, _, bit_unit) = lookup(prefix)?;
+        Some(rate_unit!(bit_unit.clone(), Unit::Second))
+    } else if let Some(prefix) = text.strip_suffix('B') {
+        let (_, byte_unit, _) = lookup(prefix)?;
+        Some(byte_unit.clone())
+    } else if let Some(prefix) = text.strip_suffix('b') {
+        let (_, _, bit_unit) = lookup(prefix)?;
+        Some(bit_unit.clone())
+    } else {
+        None
+    }
+}
+
+/// Parse a rate unit like "GiB/s", "queries/day", "$/month", "MB / hour" or "$/GiB"
+///
+/// Any data, bit, request or currency unit can be combined with any spelling of a
+/// time unit (singular, plural or abbreviated), so rates don't need their own arms.
+/// Which combinations are rates is decided by `Unit::is_valid_rate`.
+fn parse_rate_unit(text: &str) -> Option<Unit> {
+    let (numerator, denominator) = text.split_once('/')?;
+    let numerator = parse_unit(numerator.trim())?;
+    let denominator = parse_unit(denominator.trim())?;
+
+    if Unit::is_valid_rate(&numerator, &denominator) {
+        Some(rate_unit!(numerator, denominator))
+    } else {
+        None
+    }
+}
+
+/// Spellings that unknown words are compared against, paired with the unit to suggest
+const UNIT_SUGGESTIONS: &[(&str, &str)] = &[
+    // Time units
+    ("nanosecond", "nanosecond"),
+    ("nanoseconds", "nanoseconds"),
+    ("microsecond", "microsecond"),
+    ("microseconds", "microseconds"),
+    ("millisecond", "millisecond"),
+    ("milliseconds", "milliseconds"),
+    ("second", "second"),
+    ("seconds", "seconds"),
+    ("minute", "minute"),
+    ("minutes", "minutes"),
+    ("hour", "hour"),
+    ("hours", "hours"),
+    ("days", "days"),
+    ("week", "week"),
+    ("weeks", "weeks"),
+    ("month", "month"),
+    ("months", "months"),
+    ("quarter", "quarter"),
+    ("quarters", "quarters"),
+    ("year", "year"),
+    ("years", "years"),
+    // Data units, spelled out
+    ("byte", "byte"),
+    ("bytes", "bytes"),
+    ("bits", "bits"),
+    ("kilobyte", "KB"),
+    ("megabyte", "MB"),
+    ("gigabyte", "GB"),
+    ("terabyte", "TB"),
+    ("petabyte", "PB"),
+    ("exabyte", "EB"),
+    ("kibibyte", "KiB"),
+    ("mebibyte", "MiB"),
+    ("gibibyte", "GiB"),
+    ("tebibyte", "TiB"),
+    ("pebibyte", "PiB"),
+    ("exbibyte", "EiB"),
+    ("kilobit", "Kb"),
+    ("megabit", "Mb"),
+    ("gigabit", "Gb"),
+    ("terabit", "Tb"),
+    ("petabit", "Pb"),
+    ("exabit", "Eb"),
+    // Requests
+    ("request", "request"),
+    ("requests", "requests"),
+    ("query", "query"),
+    ("queries", "queries"),
+    // Percentages and currencies
+    ("percent", "percent"),
+    ("percentage", "percentage"),
+    ("dollar", "dollar"),
+    ("dollars", "dollars"),
+    ("euro", "euro"),
+    ("euros", "euros"),
+    ("pound", "pound"),
+    ("pounds", "pounds"),
+    ("rupee", "rupee"),
+    ("rupees", "rupees"),
+    ("yuan", "yuan"),
+];
+
+/// Suggest the closest known unit for a word that looks like a misspelled unit
+///
+/// Returns None for words that already parse as units, words shorter than four
+/// characters (too ambiguous), and words that aren't close to any known unit.
+pub fn suggest_unit(input: &str) -> Option<&'static str> {
+    if input.chars().count() < 4 || parse_unit(input).is_some() {
+        return None;
+    }
+
+    // Plural forms are compared without their trailing "s" so "gigbytes" suggests "GB"
+    let word = input.to_lowercase();
+    let max_distance = word.chars().count() / 3;
+
+    UNIT_SUGGESTIONS
+        .iter()
+        .flat_map(|(spelling, suggestion)| {
+            let plural = format!("{}s", spelling);
+            [
+                (edit_distance(&word, spelling), *suggestion),
+                (edit_distance(&word, &plural), *suggestion),
+            ]
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, suggestion)| suggestion)
+}
+
+/// Find the first word following a number that looks like a misspelled unit
+/// Returns the word as typed and the suggested unit (e.g., "1 gigbyte" -> ("gigbyte", "GB"))
+pub fn find_unit_suggestion(text: &str) -> Option<(String, &'static str)> {
+    let mut previous_was_number = false;
+
+    for word in text.split(|c: char| c.is_whitespace() || "+-*/()=^".contains(c)) {
+        if word.is_empty() {
+            continue;
+        }
+
+        // Handle words glued to their number like "5gigbyte"
+        let unit_part =
+            word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ',');
+        let has_number_prefix = unit_part.len() < word.len();
+
+        if (previous_was_number || has_number_prefix) && !unit_part.is_empty() {
+            if let Some(suggestion) = suggest_unit(unit_part) {
+                return Some((unit_part.to_string(), suggestion));
+            }
+        }
+
+        previous_was_number = unit_part.is_empty();
+    }
+
+    None
+}
+
+/// Levenshtein distance between two strings (in characters)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b_chars.len() + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
 }
@@ -189,7 +189,7 @@ fn test_generic_rate_with_bit_units() {
 
     // Test mixed bit/byte rate calculations
     let result = evaluate_test_expression("1 Gb/minute * 60 minutes to GiB");
-    assert_eq!(result, Some("6.985 GiB".to_string()));
+    assert_eq!(result, Some("≈ 6.985 GiB".to_string()));
 }
 
 #[test]
@@ -208,7 +208,7 @@ fn test_generic_rate_complex_expressions() {
 
     // Test rate with conversion
     let result = evaluate_test_expression("(1 GiB/minute * 60 minutes) to TB");
-    assert_eq!(result, Some("0.064 TB".to_string()));
+    assert_eq!(result, Some("≈ 0.064 TB".to_string()));
 
     // Test compound rate calculations
     let result = evaluate_test_expression("((100 MB/s * 60 s) / 10 minutes) * 5 minutes");
@@ -485,7 +485,7 @@ fn test_in_keyword_conversions() {
     // Test mixed base units (base 10 to base 2)
     assert_eq!(
         evaluate_test_expression("1000 MB * 5 in GiB"),
-        Some("4.657 GiB".to_string())
+        Some("≈ 4.657 GiB".to_string())
     );
 
     // Test rate calculations with time conversion
@@ -1286,13 +1286,13 @@ fn test_large_data_real_world_scenarios() {
     // Test very large data transfers
     assert_eq!(
         evaluate_test_expression("Transfer: 5 EiB to PB"),
-        Some("5,764.608 PB".to_string())
+        Some("≈ 5,764.608 PB".to_string())
     );
 
     // Test scientific computing scenarios
     assert_eq!(
         evaluate_test_expression("Dataset: 1.5 EB to TiB"),
-        Some("1,364,242.053 TiB".to_string())
+        Some("≈ 1,364,242.053 TiB".to_string())
     );
 
     // Test network throughput
@@ -2076,7 +2076,7 @@ fn test_extended_time_real_world_scenarios() {
     // Monthly data allowance
     assert_eq!(
         evaluate_test_expression("100 GB/month to GB/week"),
-        Some("22.998 GB/week".to_string()) // 100 GB / (30.44/7) weeks ≈ 22.998
+        Some("≈ 22.998 GB/week".to_string()) // 100 GB / (30.44/7) weeks ≈ 22.998
     );
 }
 
@@ -2404,6 +2404,29 @@ fn test_currency_rate_multiplication() {
     );
 }
 
+#[test]
+fn test_rate_times_calendar_units() {
+    // A week is 7 days, a month 30.436875 days and a year 365.25 days
+    assert_eq!(
+        evaluate_test_expression("$50/hr * 1 week"),
+        Some("8,400 $".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$2/day * 1 year"),
+        Some("730.5 $".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("$50/hr * 1 month"),
+        Some("36,524.25 $".to_string())
+    );
+
+    // A quarter is three months
+    assert_eq!(
+        evaluate_test_expression("$1000/month * 1 quarter"),
+        Some("3,000 $".to_string())
+    );
+}
+
 #[test]
 fn test_currency_rate_with_fractions() {
     // Test with fractional rates
@@ -2748,13 +2771,13 @@ fn test_currency_rate_conversions_with_expressions() {
 
     assert_eq!(
         evaluate_test_expression("£100/week to £/month"),
-        Some("434.812 £/month".to_string()) // 100 * (30.44/7), adjusted for actual calculation
+        Some("≈ 434.812 £/month".to_string()) // 100 * (30.44/7), adjusted for actual calculation
     );
 
-    // Test in complex expressions (currently evaluates the conversion part only)
+    // Test in complex expressions (the parenthesized conversion is evaluated first)
     assert_eq!(
         evaluate_test_expression("($10/hr to $/day) * 5 days"),
-        Some("240 $/day".to_string()) // $10/hr = $240/day (the conversion part works)
+        Some("1,200 $".to_string()) // $10/hr = $240/day, times 5 days
     );
 }
 
@@ -2810,3 +2833,861 @@ fn test_currency_rate_conversions_real_world() {
     let unit_val = result.unwrap();
     assert!((unit_val.value - 119.88).abs() < 0.1); // $9.99 * 12 = $119.88
 }
+
+#[test]
+fn test_suggest_unit() {
+    // Misspelled data units suggest the closest unit
+    let suggestion = suggest_unit("gigbyte");
+    assert!(
+        matches!(suggestion, Some("GB") | Some("GiB")),
+        "unexpected suggestion {:?}",
+        suggestion
+    );
+    assert_eq!(suggest_unit("gibibyte"), Some("GiB"));
+    assert_eq!(suggest_unit("megabytes"), Some("MB"));
+
+    // Misspelled time units
+    assert_eq!(suggest_unit("secnod"), Some("second"));
+    assert_eq!(suggest_unit("minuts"), Some("minute"));
+    assert_eq!(suggest_unit("SECNODS"), Some("second"));
+
+    // Valid units, short words and unrelated words get no suggestion
+    assert_eq!(suggest_unit("seconds"), None);
+    assert_eq!(suggest_unit("GiB"), None);
+    assert_eq!(suggest_unit("kg"), None);
+    assert_eq!(suggest_unit("servers"), None);
+    assert_eq!(suggest_unit("apples"), None);
+}
+
+#[test]
+fn test_find_unit_suggestion() {
+    assert_eq!(
+        find_unit_suggestion("1 secnod + 5 seconds"),
+        Some(("secnod".to_string(), "second"))
+    );
+    assert_eq!(
+        find_unit_suggestion("(2gigbyte + 1 GB)").map(|(word, _)| word),
+        Some("gigbyte".to_string())
+    );
+
+    // Words not following a number are left alone
+    assert_eq!(find_unit_suggestion("secnod thoughts: 5 + 3"), None);
+    assert_eq!(find_unit_suggestion("5 GiB + 3 GiB"), None);
+}
+
+#[test]
+fn test_rate_unit_spelling_matrix() {
+    let numerators = [
+        ("GiB", Unit::GiB),
+        ("MB", Unit::MB),
+        ("bytes", Unit::Byte),
+        ("Mb", Unit::Mb),
+        ("bits", Unit::Bit),
+        ("req", Unit::Request),
+        ("requests", Unit::Request),
+        ("query", Unit::Query),
+        ("queries", Unit::Query),
+        ("$", Unit::USD),
+        ("EUR", Unit::EUR),
+    ];
+    let denominators = [
+        ("s", Unit::Second),
+        ("sec", Unit::Second),
+        ("second", Unit::Second),
+        ("seconds", Unit::Second),
+        ("min", Unit::Minute),
+        ("minute", Unit::Minute),
+        ("minutes", Unit::Minute),
+        ("h", Unit::Hour),
+        ("hr", Unit::Hour),
+        ("hour", Unit::Hour),
+        ("hours", Unit::Hour),
+        ("day", Unit::Day),
+        ("days", Unit::Day),
+        ("week", Unit::Week),
+        ("weeks", Unit::Week),
+        ("month", Unit::Month),
+        ("months", Unit::Month),
+        ("year", Unit::Year),
+        ("years", Unit::Year),
+    ];
+
+    for (num_text, num_unit) in &numerators {
+        for (den_text, den_unit) in &denominators {
+            let expected = rate_unit!(num_unit.clone(), den_unit.clone());
+            for text in [
+                format!("{}/{}", num_text, den_text),
+                format!("{} / {}", num_text, den_text),
+            ] {
+                assert_eq!(
+                    parse_unit(&text),
+                    Some(expected.clone()),
+                    "parsing {}",
+                    text
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rate_unit_abbreviations_and_invalid_rates() {
+    assert_eq!(parse_unit("Gbps"), Some(rate_unit!(Unit::Gb, Unit::Second)));
+    assert_eq!(
+        parse_unit("rpm"),
+        Some(rate_unit!(Unit::Request, Unit::Minute))
+    );
+    assert_eq!(parse_unit("qph"), Some(rate_unit!(Unit::Query, Unit::Hour)));
+    // Lowercase binary prefixes mean bytes in "/s" rates
+    assert_eq!(
+        parse_unit("gib/s"),
+        Some(rate_unit!(Unit::GiB, Unit::Second))
+    );
+
+    // Only time denominators and rate-capable numerators form rates
+    assert_eq!(parse_unit("hours/day"), None);
+    assert_eq!(parse_unit("GiB/GiB"), None);
+    assert_eq!(parse_unit("GiB/s/s"), None);
+    assert_eq!(parse_unit("%/s"), None);
+}
+
+#[test]
+fn test_rate_conversions_across_bases_and_time_units() {
+    // Base-2 to base-10 data with a different time unit, converted in one step
+    // 10 * 1,048,576 B/min = 10,485.76 KB/min = 629,145.6 KB/hour
+    assert_eq!(
+        evaluate_test_expression("10 MiB/min in KB/hour"),
+        Some("≈ 629,145.6 KB/h".to_string())
+    );
+
+    // 1,073,741,824 B/s * 60 = 64,424.509 MB/min
+    assert_eq!(
+        evaluate_test_expression("1 GiB/s to MB/min"),
+        Some("≈ 64,424.509 MB/min".to_string())
+    );
+
+    // 1,099,511,627,776 B / 86,400 s = 12.726 MB/s
+    assert_eq!(
+        evaluate_test_expression("1 TiB/day to MB/s"),
+        Some("≈ 12.726 MB/s".to_string())
+    );
+
+    // Base-10 to base-2: 1e9 B / 3,600 s / 1,024 = 271.267 KiB/s
+    assert_eq!(
+        evaluate_test_expression("1 GB/hour to KiB/s"),
+        Some("≈ 271.267 KiB/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3,600 MB/hour to MiB/s"),
+        Some("≈ 0.954 MiB/s".to_string())
+    );
+
+    // Bits to bytes across bases: 1e9 b/s / 8 / 1,048,576 = 119.209 MiB/s
+    assert_eq!(
+        evaluate_test_expression("1 Gbps to MiB/s"),
+        Some("≈ 119.209 MiB/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("8 Gib/s to GB/min"),
+        Some("≈ 64.425 GB/min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB/s to Gbps"),
+        Some("≈ 8.59 Gb/s".to_string())
+    );
+
+    // Prices per data size convert inversely: $5 per GiB is $4.657 per GB
+    assert_eq!(
+        evaluate_test_expression("5 $/GiB to $/GB"),
+        Some("≈ 4.657 $/GB".to_string())
+    );
+
+    // Exact values, before formatting rounds them
+    let value = UnitValue::new(10.0, Some(rate_unit!(Unit::MiB, Unit::Minute)));
+    let converted = value.to_unit(&rate_unit!(Unit::KB, Unit::Hour)).unwrap();
+    floats_equal(converted.value, 629_145.6);
+    let value = UnitValue::new(1.0, Some(rate_unit!(Unit::Gb, Unit::Second)));
+    let converted = value.to_unit(&rate_unit!(Unit::KiB, Unit::Minute)).unwrap();
+    floats_equal(converted.value, 1e9 / 8.0 / 1024.0 * 60.0);
+
+    // Per-time and per-data rates, and rates of different things, don't convert
+    assert_eq!(evaluate_test_expression("5 $/GiB to $/month"), None);
+    assert_eq!(evaluate_test_expression("5 GiB/s to $/GiB"), None);
+    assert_eq!(evaluate_test_expression("1 MB/s to $/s"), None);
+    assert_eq!(evaluate_test_expression("10 req/s to MB/s"), None);
+}
+
+#[test]
+fn test_rate_numerator_and_denominator_combinations() {
+    // Rates are per unit of time, plus unit prices of data
+    let valid = [
+        (
+            "GiB/s",
+            UnitType::DataRate {
+                time_multiplier: 1.0,
+            },
+        ),
+        ("Mb/hour", UnitType::BitRate),
+        ("requests/min", UnitType::RequestRate),
+        ("queries/day", UnitType::RequestRate),
+        (
+            "$/month",
+            UnitType::DataRate {
+                time_multiplier: 2629746.0,
+            },
+        ),
+        (
+            "$/GiB",
+            UnitType::DataRate {
+                time_multiplier: 1.0,
+            },
+        ),
+        (
+            "€/TB",
+            UnitType::DataRate {
+                time_multiplier: 1.0,
+            },
+        ),
+        ("GiB/req", UnitType::DataPerRequest),
+        ("KB/query", UnitType::DataPerRequest),
+    ];
+    for (text, unit_type) in valid {
+        let unit = parse_unit(text).unwrap_or_else(|| panic!("{} should be a rate", text));
+        assert_eq!(unit.unit_type(), unit_type, "{}", text);
+    }
+
+    // Counts are only denominators of data sizes, and percentages or currencies
+    // never are
+    let invalid = [
+        "$/req",
+        "$/query",
+        "Mb/request",
+        "req/GiB",
+        "req/req",
+        "s/GiB",
+        "hours/day",
+        "GiB/GiB",
+        "GiB/$",
+        "$/$",
+        "$/%",
+        "%/s",
+        "GiB/s/s",
+    ];
+    for text in invalid {
+        assert_eq!(parse_unit(text), None, "{}", text);
+    }
+
+    assert!(Unit::is_valid_rate(&Unit::USD, &Unit::GiB));
+    assert!(!Unit::is_valid_rate(&Unit::USD, &Unit::Request));
+    assert!(Unit::is_valid_rate(&Unit::GiB, &Unit::Query));
+    assert!(!Unit::is_valid_rate(&Unit::Gib, &Unit::Query));
+
+    // Expressions with other count denominators don't evaluate rather than guessing
+    assert_eq!(
+        evaluate_test_expression("$0.002/request * 1000 requests"),
+        None
+    );
+    assert_eq!(evaluate_test_expression("$100 / 50 requests"), None);
+    assert_eq!(
+        evaluate_test_expression("$5/GiB * 2 GiB"),
+        Some("10 $".to_string())
+    );
+}
+
+#[test]
+fn test_micro_prefix_normalization() {
+    // The micro sign, Greek mu and ASCII "u" are interchangeable
+    for spelling in ["us", "usec", "µs", "μs", "µS", "µsec"] {
+        assert_eq!(
+            parse_unit(spelling),
+            Some(Unit::Microsecond),
+            "{}",
+            spelling
+        );
+    }
+    assert_eq!(
+        parse_unit("MB/µs"),
+        Some(rate_unit!(Unit::MB, Unit::Microsecond))
+    );
+
+    // Only a leading micro sign is a prefix
+    assert_eq!(parse_unit("µ"), None);
+    assert_eq!(parse_unit("sµ"), None);
+
+    assert_eq!(
+        evaluate_test_expression("1500 µs to ms"),
+        Some("1.5 ms".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 μs + 1 us"),
+        Some("2 us".to_string())
+    );
+}
+
+#[test]
+fn test_prefixed_data_units() {
+    let prefixed = [
+        ("KB", Unit::KB, "Kb", Unit::Kb),
+        ("MB", Unit::MB, "Mb", Unit::Mb),
+        ("GB", Unit::GB, "Gb", Unit::Gb),
+        ("TB", Unit::TB, "Tb", Unit::Tb),
+        ("PB", Unit::PB, "Pb", Unit::Pb),
+        ("EB", Unit::EB, "Eb", Unit::Eb),
+        ("KiB", Unit::KiB, "Kib", Unit::Kib),
+        ("MiB", Unit::MiB, "Mib", Unit::Mib),
+        ("GiB", Unit::GiB, "Gib", Unit::Gib),
+        ("TiB", Unit::TiB, "Tib", Unit::Tib),
+        ("PiB", Unit::PiB, "Pib", Unit::Pib),
+        ("EiB", Unit::EiB, "Eib", Unit::Eib),
+    ];
+
+    for (bytes_text, bytes_unit, bits_text, bits_unit) in prefixed {
+        assert_eq!(parse_unit(bytes_text), Some(bytes_unit));
+        assert_eq!(parse_unit(bits_text), Some(bits_unit.clone()));
+        assert_eq!(
+            parse_unit(&format!("{}ps", bits_text)),
+            Some(rate_unit!(bits_unit, Unit::Second))
+        );
+    }
+
+    assert_eq!(parse_unit("B"), Some(Unit::Byte));
+    assert_eq!(parse_unit("bps"), Some(rate_unit!(Unit::Bit, Unit::Second)));
+
+    // Unknown prefixes are rejected rather than guessed
+    assert_eq!(parse_unit("ZB"), None);
+    assert_eq!(parse_unit("Xib"), None);
+}
+
+#[test]
+fn test_format_plain() {
+    let cases = [
+        (
+            UnitValue::new(1024.0, Some(Unit::MiB)),
+            "1,024 MiB",
+            "1024 MiB",
+        ),
+        (UnitValue::new(1_234_567.0, None), "1,234,567", "1234567"),
+        (
+            UnitValue::new(-9_876_543.25, Some(Unit::USD)),
+            "-9,876,543.25 $",
+            "-9876543.25 $",
+        ),
+        (
+            UnitValue::new(999.5, Some(Unit::GB)),
+            "999.5 GB",
+            "999.5 GB",
+        ),
+        (
+            UnitValue::new(2_500_000.0, Some(rate_unit!(Unit::MB, Unit::Second))),
+            "2,500,000 MB/s",
+            "2500000 MB/s",
+        ),
+    ];
+
+    for (value, display, plain) in cases {
+        assert_eq!(value.format(), display);
+        assert_eq!(value.format_plain(), plain);
+        // Plain output parses back to the same value
+        let parsed = crate::expression::parse_result_string(&value.format_plain()).unwrap();
+        assert_eq!(parsed.value, value.value);
+        assert_eq!(parsed.unit, value.unit);
+    }
+}
+
+#[test]
+fn test_format_negative_values() {
+    // Values that round to zero at 3 decimal places lose their sign
+    assert_eq!(UnitValue::new(-0.0001, None).format(), "0");
+    assert_eq!(UnitValue::new(-0.0004, Some(Unit::GiB)).format(), "0 GiB");
+    assert_eq!(UnitValue::new(-0.0, None).format(), "0");
+    assert_eq!(UnitValue::new(-0.0005, None).format(), "-0.001");
+
+    // Negative numbers are grouped after the sign
+    assert_eq!(UnitValue::new(-1234567.0, None).format(), "-1,234,567");
+    assert_eq!(UnitValue::new(-123.0, None).format(), "-123");
+    assert_eq!(UnitValue::new(-1234567.5, None).format(), "-1,234,567.5");
+    assert_eq!(
+        UnitValue::new(-1000.25, Some(Unit::MB)).format(),
+        "-1,000.25 MB"
+    );
+    assert_eq!(
+        evaluate_test_expression("5 - 1234572"),
+        Some("-1,234,567".to_string())
+    );
+}
+
+#[test]
+fn test_type_descriptor() {
+    let descriptor = |expression| {
+        evaluate_with_unit_info(expression)
+            .unwrap()
+            .type_descriptor()
+    };
+
+    assert_eq!(descriptor("5 GiB/s"), "DataRate: GiB/s");
+    assert_eq!(descriptor("100 Mbps"), "BitRate: Mb/s");
+    assert_eq!(descriptor("1.5 TB"), "Data: TB");
+    assert_eq!(descriptor("8 Gib"), "Bit: Gib");
+    assert_eq!(descriptor("3 hours"), "Time: h");
+    assert_eq!(descriptor("100 QPS"), "RequestRate: query/s");
+    assert_eq!(descriptor("5 req"), "Request: req");
+    assert_eq!(descriptor("15%"), "Percentage: %");
+    assert_eq!(descriptor("$5"), "Currency: $");
+    assert_eq!(descriptor("42"), "Dimensionless");
+}
+
+#[test]
+fn test_non_finite_values() {
+    // Overflowing to infinity or NaN fails instead of showing "inf" or "NaN"
+    assert_eq!(evaluate_test_expression("1e308 * 10"), None);
+    assert_eq!(evaluate_test_expression("1e308 GB * 10"), None);
+    assert_eq!(evaluate_test_expression("10 ^ 400"), None);
+    assert_eq!(evaluate_test_expression("-1e308 - 1e308"), None);
+    assert_eq!(evaluate_test_expression("1e308 * 10 - 1e308 * 10"), None);
+    assert_eq!(
+        evaluate_test_expression("1e308 / 10"),
+        Some(UnitValue::new(1e307, None).format())
+    );
+
+    // Values that are already non-finite format without panicking
+    assert_eq!(UnitValue::new(f64::INFINITY, None).format(), "(undefined)");
+    assert_eq!(
+        UnitValue::new(f64::NEG_INFINITY, Some(Unit::GiB)).format(),
+        "(undefined)"
+    );
+    assert_eq!(
+        UnitValue::new(f64::NAN, Some(Unit::MB)).format(),
+        "(undefined)"
+    );
+    assert_eq!(UnitValue::new(f64::NAN, None).format_plain(), "(undefined)");
+}
+
+#[test]
+fn test_near_integer_snapping() {
+    use crate::settings::{DEFAULT_INTEGER_TOLERANCE_ULPS, set_integer_tolerance_ulps};
+
+    // Floating point error doesn't stop a result from being an integer
+    let third = UnitValue::new(3.0 * (1.0 / 3.0), None);
+    assert!(third.is_integer());
+    assert_eq!(third.format(), "1");
+    assert_eq!(evaluate_test_expression("3 * (1/3)"), Some("1".to_string()));
+
+    let tenths = UnitValue::new((0.1 + 0.2) * 10.0, Some(Unit::GiB));
+    assert_ne!(tenths.value, 3.0);
+    assert!(tenths.is_integer());
+    assert_eq!(tenths.format(), "3 GiB");
+    assert_eq!(
+        evaluate_test_expression("(0.1 + 0.2) * 10"),
+        Some("3".to_string())
+    );
+
+    // Genuine fractions are not hidden
+    assert!(!UnitValue::new(0.1 + 0.2, None).is_integer());
+    assert_eq!(
+        evaluate_test_expression("0.1 + 0.2"),
+        Some("0.3".to_string())
+    );
+    assert!(!UnitValue::new(1.000001, None).is_integer());
+    assert!(!UnitValue::new(1_000_000.5, None).is_integer());
+
+    // The tolerance is configurable
+    set_integer_tolerance_ulps(0.0);
+    assert!(!tenths.is_integer());
+    set_integer_tolerance_ulps(DEFAULT_INTEGER_TOLERANCE_ULPS);
+    assert!(tenths.is_integer());
+}
+
+#[test]
+fn test_supported_units() {
+    let groups = supported_units();
+    let names_for = |unit_type: UnitType| -> Vec<String> {
+        groups
+            .iter()
+            .find(|(group_type, _)| *group_type == unit_type)
+            .map(|(_, names)| names.iter().map(|name| name.to_string()).collect())
+            .unwrap_or_default()
+    };
+
+    let data = names_for(UnitType::Data);
+    assert!(data.contains(&"GiB".to_string()));
+    assert!(data.contains(&"bytes".to_string()));
+    let bits = names_for(UnitType::Bit);
+    assert!(bits.contains(&"Mb".to_string()));
+    assert!(bits.contains(&"bit".to_string()));
+    assert!(names_for(UnitType::Time).contains(&"hours".to_string()));
+    assert!(names_for(UnitType::Currency).contains(&"€".to_string()));
+    assert!(names_for(UnitType::Request).contains(&"queries".to_string()));
+    assert!(names_for(UnitType::Percentage).contains(&"%".to_string()));
+    assert!(names_for(UnitType::BitRate).contains(&"Gbps".to_string()));
+    assert!(names_for(UnitType::RequestRate).contains(&"rps".to_string()));
+
+    // Every listed name parses back to a unit of the type it is listed under
+    for (unit_type, names) in &groups {
+        assert!(!names.is_empty());
+        for name in names {
+            let unit = parse_unit(name).unwrap_or_else(|| panic!("{} doesn't parse", name));
+            assert_eq!(&unit.unit_type(), unit_type, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_request_count_to_rate_chains() {
+    // Requests / time gives a rate that converts to any other rate
+    assert_eq!(
+        evaluate_test_expression("3600 req / 1 hour to req/min"),
+        Some("60 req/min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 req / 1 hour to QPS"),
+        Some("1 query/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3600 queries / 1 hour to req/min"),
+        Some("60 req/min".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("86400 req / 1 day to req/hour"),
+        Some("3,600 req/h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1000000 requests / 1 day to QPS"),
+        Some("11.574 query/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("60 req / 30 min to req/hour"),
+        Some("120 req/h".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1000 req / 10 s to req/min"),
+        Some("6,000 req/min".to_string())
+    );
+
+    // Chained conversions keep the rate
+    assert_eq!(
+        evaluate_test_expression("3600 req / 1 hour to req/min to req/s"),
+        Some("1 req/s".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("86400 req / 1 day to req/hour to req/min"),
+        Some("60 req/min".to_string())
+    );
+
+    // And the rate can be turned back into a count
+    assert_eq!(
+        evaluate_test_expression("(3600 req / 1 hour) * 2 hours"),
+        Some("7,200 req".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(3600 req / 1 hour to req/min) + 30 req/min"),
+        Some("90 req/min".to_string())
+    );
+
+    // Counts aren't rates without a time to divide by
+    assert_eq!(evaluate_test_expression("3600 req to req/min"), None);
+}
+
+#[test]
+fn test_integer_grouping_beyond_i64() {
+    use super::value::format_number_with_commas;
+
+    assert_eq!(format_number_with_commas(0), "0");
+    assert_eq!(format_number_with_commas(-999), "-999");
+    assert_eq!(format_number_with_commas(-1_000), "-1,000");
+    assert_eq!(
+        format_number_with_commas(i64::MAX as i128),
+        "9,223,372,036,854,775,807"
+    );
+    assert_eq!(
+        format_number_with_commas(i64::MAX as i128 + 1),
+        "9,223,372,036,854,775,808"
+    );
+    assert_eq!(
+        format_number_with_commas(-123_456_789_012_345_678_901_234_567_890),
+        "-123,456,789,012,345,678,901,234,567,890"
+    );
+    assert_eq!(
+        format_number_with_commas(i128::MIN),
+        "-170,141,183,460,469,231,731,687,303,715,884,105,728"
+    );
+    assert_eq!(
+        format_number_with_commas(i128::MAX),
+        "170,141,183,460,469,231,731,687,303,715,884,105,727"
+    );
+}
+
+#[test]
+fn test_cmp_compatible() {
+    use std::cmp::Ordering;
+
+    let value = |v: f64, unit: Unit| UnitValue::new(v, Some(unit));
+
+    assert_eq!(
+        value(1.0, Unit::GiB).cmp_compatible(&value(1000.0, Unit::MiB)),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        value(1000.0, Unit::MiB).cmp_compatible(&value(1.0, Unit::GiB)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        value(1.0, Unit::GiB).cmp_compatible(&value(1024.0, Unit::MiB)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(
+        value(1.0, Unit::Hour).cmp_compatible(&value(61.0, Unit::Minute)),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        value(1.0, Unit::Hour).cmp_compatible(&value(60.0, Unit::Minute)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(
+        UnitValue::new(2.0, None).cmp_compatible(&UnitValue::new(3.0, None)),
+        Some(Ordering::Less)
+    );
+
+    // Incompatible types can't be ordered
+    assert_eq!(
+        value(1.0, Unit::GiB).cmp_compatible(&value(1.0, Unit::Hour)),
+        None
+    );
+    assert_eq!(
+        value(5.0, Unit::Minute).cmp_compatible(&UnitValue::new(5.0, None)),
+        None
+    );
+    assert_eq!(
+        UnitValue::new(5.0, None).cmp_compatible(&value(5.0, Unit::Minute)),
+        None
+    );
+}
+
+#[test]
+fn test_data_per_request() {
+    assert_eq!(
+        evaluate_test_expression("1000000 req * 2 KB/req to GB"),
+        Some("2 GB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 KB/req * 1000000 requests"),
+        Some("2,000,000 KB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("500 queries * 4 KiB/query"),
+        Some("2,000 KiB".to_string())
+    );
+
+    // The inverse: total data / count = data per request
+    assert_eq!(
+        evaluate_test_expression("2 GB / 1000000 req to KB/req"),
+        Some("2 KB/req".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("3 MiB / 3 req"),
+        Some("1 MiB/req".to_string())
+    );
+    // And data / data per request = count
+    assert_eq!(
+        evaluate_test_expression("2 GB / 2 KB/req"),
+        Some("1,000,000 req".to_string())
+    );
+
+    assert_eq!(
+        evaluate_test_expression("1 MiB/req to KiB/req"),
+        Some("1,024 KiB/req".to_string())
+    );
+
+    // Data per request isn't a rate over time
+    assert_eq!(evaluate_test_expression("5 GiB/req * 1 hour"), None);
+    assert_eq!(evaluate_test_expression("5 GiB/req to GiB/s"), None);
+}
+
+#[test]
+fn test_pressure_units() {
+    assert_eq!(parse_unit("Pa"), Some(Unit::Pascal));
+    assert_eq!(parse_unit("kPa"), Some(Unit::Kilopascal));
+    assert_eq!(parse_unit("MPa"), Some(Unit::Megapascal));
+    assert_eq!(parse_unit("psi"), Some(Unit::Psi));
+    assert_eq!(parse_unit("ksi"), Some(Unit::Ksi));
+    assert_eq!(parse_unit("atm"), Some(Unit::Atmosphere));
+    assert_eq!(parse_unit("bar"), Some(Unit::Bar));
+    assert_eq!(parse_unit("kilopascals"), Some(Unit::Kilopascal));
+    // "mPa" would be millipascals, not megapascals, and pascals aren't petabytes
+    assert_eq!(parse_unit("mPa"), None);
+    assert_eq!(parse_unit("KPa"), None);
+    assert_eq!(parse_unit("PB"), Some(Unit::PB));
+    assert_eq!(Unit::Pascal.unit_type(), UnitType::Pressure);
+
+    assert_eq!(
+        evaluate_test_expression("1 bar to psi"),
+        Some("14.504 psi".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 atm to kPa"),
+        Some("101.325 kPa".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 ksi to psi"),
+        Some("1,000 psi".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 ksi to MPa"),
+        Some("6.895 MPa".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("250 kPa + 1 bar to bar"),
+        Some("3.5 bar".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("30 psi / 2 psi"),
+        Some("15".to_string())
+    );
+
+    // Pressure doesn't mix with other kinds of units
+    assert_eq!(evaluate_test_expression("1 bar to GiB"), None);
+    assert_eq!(evaluate_test_expression("1 bar + 1 hour"), None);
+}
+
+#[test]
+fn test_approximate_conversions() {
+    // Decimal and binary prefixes don't divide into each other
+    assert_eq!(
+        evaluate_test_expression("1 GB to GiB"),
+        Some("≈ 0.931 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 Gbps to MiB/s"),
+        Some("≈ 119.209 MiB/s".to_string())
+    );
+
+    // Months and years are averages
+    assert_eq!(
+        evaluate_test_expression("1 month to days"),
+        Some("≈ 30.437 day".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("90 days to quarters"),
+        Some("≈ 0.986 quarter".to_string())
+    );
+
+    // Exact conversions within a prefix system or calendar
+    assert_eq!(
+        evaluate_test_expression("1 GiB to MiB"),
+        Some("1,024 MiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GB to MB"),
+        Some("1,000 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 GiB to B"),
+        Some("1,073,741,824 B".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("8 Mb to MB"),
+        Some("1 MB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 year to months"),
+        Some("12 month".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 hours to minutes"),
+        Some("120 min".to_string())
+    );
+
+    let value = UnitValue::new(1.0, Some(Unit::GB));
+    assert!(!value.approximate);
+    assert!(value.to_unit(&Unit::GiB).unwrap().approximate);
+    assert!(!value.to_unit(&Unit::MB).unwrap().approximate);
+    // Once approximate, converting further doesn't make a value exact
+    assert!(
+        value
+            .to_unit(&Unit::GiB)
+            .unwrap()
+            .to_unit(&Unit::MiB)
+            .unwrap()
+            .approximate
+    );
+    assert_eq!(
+        value.to_unit(&Unit::GiB).unwrap().format_plain(),
+        "0.931 GiB"
+    );
+
+    // Other lines read an approximate result as its value, and keep the mark
+    let previous = vec![Some("≈ 0.931 GiB".to_string())];
+    assert_eq!(
+        crate::expression::evaluate_expression_with_context("line1 * 2", &previous, 1),
+        Some("≈ 1.862 GiB".to_string())
+    );
+}
+
+#[test]
+fn test_arithmetic_on_approximate_values() {
+    assert_eq!(
+        evaluate_test_expression("1 GB to GiB * 2"),
+        Some("≈ 1.863 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("(1 GB to GiB) + 1 GiB"),
+        Some("≈ 1.931 GiB".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("2 * (1 month to days)"),
+        Some("≈ 60.874 day".to_string())
+    );
+    assert_eq!(
+        evaluate_test_expression("1 month to days - 10 days"),
+        Some("≈ 20.437 day".to_string())
+    );
+
+    // Exact conversions stay exact
+    assert_eq!(
+        evaluate_test_expression("(1 GiB to MiB) * 2"),
+        Some("2,048 MiB".to_string())
+    );
+
+    // Comparisons are exact answers either way
+    assert_eq!(
+        evaluate_test_expression("(1 GB to GiB) > 0.9 GiB"),
+        Some("1".to_string())
+    );
+}
+
+#[test]
+fn test_disabled_unit_families() {
+    use crate::settings::{UnitFamily, set_unit_family_enabled};
+
+    set_unit_family_enabled(UnitFamily::Time, false);
+    assert_eq!(parse_unit("min"), None);
+    assert_eq!(parse_unit("hours"), None);
+    // Rates need both of their families
+    assert_eq!(parse_unit("GiB/s"), None);
+    assert_eq!(parse_unit("Gbps"), None);
+    // Other families still work
+    assert_eq!(parse_unit("GiB"), Some(Unit::GiB));
+    assert_eq!(parse_unit("$/GiB"), Some(rate_unit!(Unit::USD, Unit::GiB)));
+    // Time words are prose again
+    assert_eq!(evaluate_test_expression("5 min + 3"), Some("8".to_string()));
+    assert_eq!(
+        evaluate_test_expression("2 GiB + 512 MiB"),
+        Some("2,560 MiB".to_string())
+    );
+    assert_eq!(evaluate_test_expression("5d * 2"), Some("10".to_string()));
+
+    set_unit_family_enabled(UnitFamily::Time, true);
+    assert_eq!(parse_unit("min"), Some(Unit::Minute));
+    assert_eq!(
+        evaluate_test_expression("5 min + 3 min"),
+        Some("8 min".to_string())
+    );
+
+    set_unit_family_enabled(UnitFamily::Currency, false);
+    assert_eq!(parse_unit("$"), None);
+    assert_eq!(parse_unit("$/month"), None);
+    assert_eq!(evaluate_test_expression("$5 * 3"), Some("15".to_string()));
+    set_unit_family_enabled(UnitFamily::Currency, true);
+
+    assert_eq!(UnitFamily::from_name("Time"), Some(UnitFamily::Time));
+    assert_eq!(UnitFamily::from_name("length"), None);
+}
@@ -1,5 +1,6 @@
 //! Unit type definitions and conversions
 
+use crate::settings::{UnitFamily, unit_family_enabled};
 use std::borrow::Cow;
 
 /// Error type for unit conversion operations
@@ -70,6 +71,15 @@ pub enum Unit {
     // Percentage unit (base: decimal value 0.0-1.0)
     Percent,
 
+    // Pressure units (base: pascals)
+    Pascal,
+    Kilopascal,
+    Megapascal,
+    Bar,
+    Psi, // Pounds per square inch
+    Ksi, // Thousand pounds per square inch
+    Atmosphere,
+
     // Currency units (no conversion between different currencies)
     USD, // US Dollar
     EUR, // Euro
@@ -86,6 +96,9 @@ pub enum Unit {
     RateUnit(Box<Unit>, Box<Unit>),
 }
 
+/// Pascals in a pound-force per square inch
+const PASCALS_PER_PSI: f64 = 6_894.757_293_168_361;
+
 /// Macro to simplify creating RateUnit instances
 #[macro_export]
 macro_rules! rate_unit {
@@ -101,12 +114,36 @@ pub enum UnitType {
     Data,
     Request,
     BitRate,
-    DataRate { time_multiplier: f64 },
+    DataRate {
+        time_multiplier: f64,
+    },
     RequestRate,
+    /// Data per request or query, like "KB/req"
+    DataPerRequest,
     Percentage,
+    Pressure,
     Currency,
 }
 
+impl UnitType {
+    /// Name of this unit type, as reported by `typeof(...)`
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnitType::Time => "Time",
+            UnitType::Bit => "Bit",
+            UnitType::Data => "Data",
+            UnitType::Request => "Request",
+            UnitType::BitRate => "BitRate",
+            UnitType::DataRate { .. } => "DataRate",
+            UnitType::RequestRate => "RequestRate",
+            UnitType::DataPerRequest => "DataPerRequest",
+            UnitType::Percentage => "Percentage",
+            UnitType::Pressure => "Pressure",
+            UnitType::Currency => "Currency",
+        }
+    }
+}
+
 impl Unit {
     /// Convert a value in this unit to the base unit for its type
     pub fn to_base_value(&self, value: f64) -> f64 {
@@ -165,6 +202,15 @@ impl Unit {
             // Percentage unit (convert to decimal 0.0-1.0)
             Unit::Percent => value / 100.0,
 
+            // Pressure units (convert to pascals)
+            Unit::Pascal => value,
+            Unit::Kilopascal => value * 1_000.0,
+            Unit::Megapascal => value * 1_000_000.0,
+            Unit::Bar => value * 100_000.0,
+            Unit::Psi => value * PASCALS_PER_PSI,
+            Unit::Ksi => value * PASCALS_PER_PSI * 1_000.0,
+            Unit::Atmosphere => value * 101_325.0,
+
             // Currency units (no conversion, base value is the same)
             Unit::USD
             | Unit::EUR
@@ -245,6 +291,15 @@ impl Unit {
             // Percentage unit (from decimal 0.0-1.0)
             Unit::Percent => base_value * 100.0,
 
+            // Pressure units (from pascals)
+            Unit::Pascal => base_value,
+            Unit::Kilopascal => base_value / 1_000.0,
+            Unit::Megapascal => base_value / 1_000_000.0,
+            Unit::Bar => base_value / 100_000.0,
+            Unit::Psi => base_value / PASCALS_PER_PSI,
+            Unit::Ksi => base_value / (PASCALS_PER_PSI * 1_000.0),
+            Unit::Atmosphere => base_value / 101_325.0,
+
             // Currency units (no conversion, value is the same)
             Unit::USD
             | Unit::EUR
@@ -269,6 +324,22 @@ impl Unit {
         }
     }
 
+    /// Check if a numerator and denominator form a supported rate
+    ///
+    /// Rates are per unit of time ("GiB/s", "req/min", "$/month"), except for unit
+    /// prices of data ("$/GiB") and data sizes per request ("KB/req"). Otherwise
+    /// counts aren't denominators, so "$/req" and "Mb/req" aren't rates.
+    pub fn is_valid_rate(numerator: &Unit, denominator: &Unit) -> bool {
+        matches!(
+            (numerator.unit_type(), denominator.unit_type()),
+            (
+                UnitType::Data | UnitType::Bit | UnitType::Request | UnitType::Currency,
+                UnitType::Time,
+            ) | (UnitType::Currency, UnitType::Data)
+                | (UnitType::Data, UnitType::Request)
+        )
+    }
+
     /// Get the unit type for this unit
     pub fn unit_type(&self) -> UnitType {
         match self {
@@ -311,6 +382,13 @@ impl Unit {
             | Unit::EiB => UnitType::Data,
             Unit::Request | Unit::Query => UnitType::Request,
             Unit::Percent => UnitType::Percentage,
+            Unit::Pascal
+            | Unit::Kilopascal
+            | Unit::Megapascal
+            | Unit::Bar
+            | Unit::Psi
+            | Unit::Ksi
+            | Unit::Atmosphere => UnitType::Pressure,
             Unit::USD
             | Unit::EUR
             | Unit::GBP
@@ -338,6 +416,10 @@ impl Unit {
                         time_multiplier: 1.0, // No time component for currency/data rates
                     },
 
+                    // Request sizes (e.g., KB/req)
+                    (UnitType::Data, UnitType::Request) => UnitType::DataPerRequest,
+
+                    // Rates are only built from combinations `is_valid_rate` accepts
                     _ => panic!(
                         "Rate type not supported: {:?}/{:?}",
                         b1.unit_type(),
@@ -348,6 +430,26 @@ impl Unit {
         }
     }
 
+    /// Check that this unit's families are turned on, see [`UnitFamily`]
+    pub fn is_enabled(&self) -> bool {
+        if let Unit::RateUnit(numerator, denominator) = self {
+            return numerator.is_enabled() && denominator.is_enabled();
+        }
+
+        let families: &[UnitFamily] = match self.unit_type() {
+            UnitType::Time => &[UnitFamily::Time],
+            UnitType::Bit | UnitType::Data => &[UnitFamily::Data],
+            UnitType::Request => &[UnitFamily::Requests],
+            UnitType::BitRate | UnitType::DataRate { .. } => &[UnitFamily::Data, UnitFamily::Time],
+            UnitType::RequestRate => &[UnitFamily::Requests, UnitFamily::Time],
+            UnitType::DataPerRequest => &[UnitFamily::Data, UnitFamily::Requests],
+            UnitType::Percentage => &[UnitFamily::Percent],
+            UnitType::Pressure => &[UnitFamily::Pressure],
+            UnitType::Currency => &[UnitFamily::Currency],
+        };
+        families.iter().all(|family| unit_family_enabled(*family))
+    }
+
     /// Get the display name for this unit
     pub fn display_name(&self) -> Cow<'static, str> {
         match self {
@@ -391,6 +493,13 @@ impl Unit {
             Unit::Request => Cow::Borrowed("req"),
             Unit::Query => Cow::Borrowed("query"),
             Unit::Percent => Cow::Borrowed("%"),
+            Unit::Pascal => Cow::Borrowed("Pa"),
+            Unit::Kilopascal => Cow::Borrowed("kPa"),
+            Unit::Megapascal => Cow::Borrowed("MPa"),
+            Unit::Bar => Cow::Borrowed("bar"),
+            Unit::Psi => Cow::Borrowed("psi"),
+            Unit::Ksi => Cow::Borrowed("ksi"),
+            Unit::Atmosphere => Cow::Borrowed("atm"),
             Unit::USD => Cow::Borrowed("$"),
             Unit::EUR => Cow::Borrowed("€"),
             Unit::GBP => Cow::Borrowed("£"),
@@ -527,4 +636,63 @@ impl Unit {
             Unit::KiB | Unit::MiB | Unit::GiB | Unit::TiB | Unit::PiB | Unit::EiB
         )
     }
+
+    /// Check if converting a value from this unit to `target` is only approximate
+    ///
+    /// Decimal and binary prefixes (GB and GiB) don't divide into each other, and
+    /// months, quarters and years are averages over leap years, so converting
+    /// between them and other time units is inexact. Rates are approximate if
+    /// either their numerator or denominator is.
+    pub fn converts_approximately_to(&self, target: &Unit) -> bool {
+        match (self, target) {
+            (Unit::RateUnit(numerator, denominator), Unit::RateUnit(target_num, target_denom)) => {
+                numerator.converts_approximately_to(target_num)
+                    || denominator.converts_approximately_to(target_denom)
+            }
+            _ => {
+                let mixed_prefixes = matches!(
+                    (self.prefix_base(), target.prefix_base()),
+                    (Some(base), Some(target_base)) if base != target_base
+                );
+                mixed_prefixes || self.is_calendar_time() != target.is_calendar_time()
+            }
+        }
+    }
+
+    /// The base of a data or bit unit's prefix: 10 for KB and Mb, 2 for KiB and Mib,
+    /// and None for plain bytes and bits or any other unit
+    fn prefix_base(&self) -> Option<u32> {
+        match self {
+            Unit::KB
+            | Unit::MB
+            | Unit::GB
+            | Unit::TB
+            | Unit::PB
+            | Unit::EB
+            | Unit::Kb
+            | Unit::Mb
+            | Unit::Gb
+            | Unit::Tb
+            | Unit::Pb
+            | Unit::Eb => Some(10),
+            Unit::KiB
+            | Unit::MiB
+            | Unit::GiB
+            | Unit::TiB
+            | Unit::PiB
+            | Unit::EiB
+            | Unit::Kib
+            | Unit::Mib
+            | Unit::Gib
+            | Unit::Tib
+            | Unit::Pib
+            | Unit::Eib => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Check if this is a calendar time unit, whose length is an average
+    fn is_calendar_time(&self) -> bool {
+        matches!(self, Unit::Month | Unit::Quarter | Unit::Year)
+    }
 }
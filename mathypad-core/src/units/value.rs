@@ -1,56 +1,121 @@
 //! Unit value representation and operations
 
 use super::types::{Unit, UnitType};
+use crate::settings::{
+    Notation, decimal_comma, decimal_places, integer_tolerance_ulps, notation,
+    swap_decimal_separators,
+};
 use crate::{FLOAT_EPSILON, MAX_INTEGER_FOR_FORMATTING};
+use std::cmp::Ordering;
 
 /// Represents a numeric value with an optional unit
 #[derive(Debug, Clone)]
 pub struct UnitValue {
     pub value: f64,
     pub unit: Option<Unit>,
+    /// Set by conversions that aren't exact, like GB to GiB or days to months,
+    /// so the value is shown with a leading "≈"
+    pub approximate: bool,
 }
 
 impl UnitValue {
     /// Create a new UnitValue
     pub fn new(value: f64, unit: Option<Unit>) -> Self {
-        UnitValue { value, unit }
+        UnitValue {
+            value,
+            unit,
+            approximate: false,
+        }
+    }
+
+    /// Create a boolean result (1 for true, 0 for false) from a comparison
+    pub fn from_bool(value: bool) -> Self {
+        UnitValue::new(if value { 1.0 } else { 0.0 }, None)
+    }
+
+    /// Check if this value is truthy (any non-zero value)
+    pub fn is_truthy(&self) -> bool {
+        self.value.abs() >= FLOAT_EPSILON
+    }
+
+    /// Compare with another value of a compatible unit type
+    ///
+    /// The other value is converted into this value's unit first, so "1 GiB" and
+    /// "1024 MiB" are equal. Values within a few ulps of each other count as equal
+    /// to absorb conversion rounding. Returns None for incompatible types, or when
+    /// only one side has a unit.
+    pub fn cmp_compatible(&self, other: &UnitValue) -> Option<Ordering> {
+        let (left, right) = match (&self.unit, &other.unit) {
+            (Some(unit), Some(_)) => (self.value, other.to_unit(unit)?.value),
+            (None, None) => (self.value, other.value),
+            _ => return None,
+        };
+
+        let tolerance = FLOAT_EPSILON * left.abs().max(right.abs()).max(1.0) * 4.0;
+        if (left - right).abs() <= tolerance {
+            Some(Ordering::Equal)
+        } else {
+            left.partial_cmp(&right)
+        }
     }
 
     /// Convert this value to a different unit of the same type
+    ///
+    /// The result is marked approximate if the conversion isn't exact, or if this
+    /// value already was.
     pub fn to_unit(&self, target_unit: &Unit) -> Option<UnitValue> {
+        let mut converted = self.convert_to(target_unit)?;
+        converted.approximate = self.approximate
+            || self
+                .unit
+                .as_ref()
+                .is_some_and(|unit| unit.converts_approximately_to(target_unit));
+        Some(converted)
+    }
+
+    /// Convert this value to a different unit of the same type, see `to_unit`
+    fn convert_to(&self, target_unit: &Unit) -> Option<UnitValue> {
         match &self.unit {
             Some(current_unit) => {
-                // Special handling for rate unit conversions first
+                // Rates convert their numerator and denominator in one step, so
+                // 10 MiB/min in KB/hour is 10 * 1,048.576 KB per min * 60 min per hour
                 if let (
                     Unit::RateUnit(curr_num, curr_denom),
                     Unit::RateUnit(targ_num, targ_denom),
                 ) = (current_unit, target_unit)
                 {
-                    // Check if numerators are compatible (same currency/unit type)
-                    if curr_num.unit_type() == targ_num.unit_type() && curr_num == targ_num {
-                        // Convert the denominator to match the target
-                        let curr_denom_base = curr_denom.to_base_value(1.0);
-                        let targ_denom_base = targ_denom.to_base_value(1.0);
-
-                        // Rate conversion: if we have $/month and want $/year,
-                        // we need to multiply by how many current periods fit in target period
-                        // For $/month to $/year: $5/month * 12 months/year = $60/year
-                        let conversion_factor = targ_denom_base / curr_denom_base;
-                        let converted_value = self.value * conversion_factor;
-
-                        return Some(UnitValue::new(converted_value, Some(target_unit.clone())));
-                    } else if curr_num.unit_type() == UnitType::Currency
-                        && targ_num.unit_type() == UnitType::Currency
-                    {
-                        // Different currencies - not convertible
+                    // Per-time and per-data rates (e.g. $/month and $/GiB) don't mix
+                    if curr_denom.unit_type() != targ_denom.unit_type() {
                         return None;
                     }
+
+                    // The numerator converts like a plain value, including bits to
+                    // bytes, except that different currencies are never converted
+                    let numerator_factor = if curr_num.unit_type() == UnitType::Currency
+                        || targ_num.unit_type() == UnitType::Currency
+                    {
+                        if curr_num != targ_num {
+                            return None;
+                        }
+                        1.0
+                    } else {
+                        UnitValue::new(1.0, Some(curr_num.as_ref().clone()))
+                            .to_unit(targ_num)?
+                            .value
+                    };
+
+                    // The denominator converts inversely: $5/month is $60/year
+                    let denominator_factor =
+                        targ_denom.to_base_value(1.0) / curr_denom.to_base_value(1.0);
+
+                    return Some(UnitValue::new(
+                        self.value * numerator_factor * denominator_factor,
+                        Some(target_unit.clone()),
+                    ));
                 }
 
-                // Check if units are the same type or compatible data rates
-                if current_unit.unit_type() == target_unit.unit_type()
-                    || self.can_convert_between_data_rates(current_unit, target_unit)
-                {
+                // Check if units are the same type
+                if current_unit.unit_type() == target_unit.unit_type() {
                     let base_value = current_unit.to_base_value(self.value);
                     let converted_value = target_unit.clone().from_base_value(base_value);
                     Some(UnitValue::new(converted_value, Some(target_unit.clone())))
@@ -75,24 +140,12 @@ impl UnitValue {
         }
     }
 
-    /// Check if conversion between data rates with different time units is possible
-    fn can_convert_between_data_rates(&self, current: &Unit, target: &Unit) -> bool {
-        use super::types::UnitType;
-        matches!(
-            (current.unit_type(), target.unit_type()),
-            (UnitType::DataRate { .. }, UnitType::DataRate { .. })
-        )
-    }
-
     /// Check if conversion between bits and bytes is possible
     fn can_convert_between_bits_bytes(&self, current: &Unit, target: &Unit) -> bool {
         use super::types::UnitType;
         matches!(
             (current.unit_type(), target.unit_type()),
-            (UnitType::Bit, UnitType::Data)
-                | (UnitType::Data, UnitType::Bit)
-                | (UnitType::BitRate, UnitType::DataRate { .. })
-                | (UnitType::DataRate { .. }, UnitType::BitRate)
+            (UnitType::Bit, UnitType::Data) | (UnitType::Data, UnitType::Bit)
         )
     }
 
@@ -115,32 +168,154 @@ impl UnitValue {
                 let converted_value = target.clone().from_base_value(bits);
                 Some(UnitValue::new(converted_value, Some(target.clone())))
             }
-            // Bit rate to Byte rate conversion
-            (UnitType::BitRate, UnitType::DataRate { .. }) => {
-                let bits_per_sec = current.to_base_value(self.value); // Convert to base bits/sec
-                let bytes_per_sec = bits_per_sec / 8.0; // 8 bits/sec = 1 byte/sec
-                let converted_value = target.clone().from_base_value(bytes_per_sec);
-                Some(UnitValue::new(converted_value, Some(target.clone())))
-            }
-            // Byte rate to Bit rate conversion
-            (UnitType::DataRate { .. }, UnitType::BitRate) => {
-                let bytes_per_sec = current.to_base_value(self.value); // Convert to base bytes/sec
-                let bits_per_sec = bytes_per_sec * 8.0; // 1 byte/sec = 8 bits/sec
-                let converted_value = target.clone().from_base_value(bits_per_sec);
-                Some(UnitValue::new(converted_value, Some(target.clone())))
-            }
             _ => None,
         }
     }
 
-    /// Format the value for display
+    /// Express a data size in the largest unit that keeps the number at least 1,
+    /// like 1536 MiB as 1.5 GiB or 999 bytes as 999 B
+    ///
+    /// `binary` picks the KiB/MiB/... scale, otherwise KB/MB/... is used. Bits stay
+    /// bits, and plain numbers are read as a count of bytes.
+    pub fn humanize(&self, binary: bool) -> Option<UnitValue> {
+        let is_bits = match &self.unit {
+            None => false,
+            Some(unit) => match unit.unit_type() {
+                UnitType::Data => false,
+                UnitType::Bit => true,
+                _ => return None,
+            },
+        };
+
+        let scale = match (is_bits, binary) {
+            (false, true) => [
+                Unit::Byte,
+                Unit::KiB,
+                Unit::MiB,
+                Unit::GiB,
+                Unit::TiB,
+                Unit::PiB,
+                Unit::EiB,
+            ],
+            (false, false) => [
+                Unit::Byte,
+                Unit::KB,
+                Unit::MB,
+                Unit::GB,
+                Unit::TB,
+                Unit::PB,
+                Unit::EB,
+            ],
+            (true, true) => [
+                Unit::Bit,
+                Unit::Kib,
+                Unit::Mib,
+                Unit::Gib,
+                Unit::Tib,
+                Unit::Pib,
+                Unit::Eib,
+            ],
+            (true, false) => [
+                Unit::Bit,
+                Unit::Kb,
+                Unit::Mb,
+                Unit::Gb,
+                Unit::Tb,
+                Unit::Pb,
+                Unit::Eb,
+            ],
+        };
+
+        let base = match &self.unit {
+            Some(unit) => unit.to_base_value(self.value),
+            None => self.value,
+        };
+        let unit = scale
+            .into_iter()
+            .rev()
+            .find(|unit| unit.to_base_value(1.0) <= base.abs())
+            .unwrap_or(if is_bits { Unit::Bit } else { Unit::Byte });
+        Some(UnitValue::new(
+            unit.clone().from_base_value(base),
+            Some(unit),
+        ))
+    }
+
+    /// Format the value for display, with a leading "≈" if it is approximate
     pub fn format(&self) -> String {
-        let formatted_value =
-            if self.value.fract() == 0.0 && self.value.abs() < MAX_INTEGER_FOR_FORMATTING {
-                format_number_with_commas(self.value as i64)
-            } else {
-                format_decimal_with_commas(self.value)
-            };
+        let formatted = self.with_unit_name(self.format_number());
+        if self.approximate {
+            format!("≈ {}", formatted)
+        } else {
+            formatted
+        }
+    }
+
+    /// Format the value without thousands separators (e.g., "1024 MiB"), for machine use
+    pub fn format_plain(&self) -> String {
+        let grouping = if decimal_comma() { '.' } else { ',' };
+        self.with_unit_name(self.format_number().replace(grouping, ""))
+    }
+
+    /// Describe the value's dimension and unit, e.g. "DataRate: GiB/s" or "Dimensionless"
+    pub fn type_descriptor(&self) -> String {
+        match &self.unit {
+            Some(unit) => format!("{}: {}", unit.unit_type().name(), unit.display_name()),
+            None => "Dimensionless".to_string(),
+        }
+    }
+
+    /// Format the value with every digit of the underlying f64 and no grouping
+    /// (e.g., "1.152921504606847 EB"), for checking conversions exactly
+    pub fn format_full_precision(&self) -> String {
+        let number = self.value.to_string();
+        if decimal_comma() {
+            self.with_unit_name(swap_decimal_separators(&number))
+        } else {
+            self.with_unit_name(number)
+        }
+    }
+
+    /// Check whether the value is an integer, allowing for floating point error
+    ///
+    /// Values within `integer_tolerance_ulps()` relative epsilons of an integer count,
+    /// so "3 * (1/3)" is 1, but genuine fractions like 1.000001 don't.
+    pub fn is_integer(&self) -> bool {
+        let rounded = self.value.round();
+        let tolerance = FLOAT_EPSILON * integer_tolerance_ulps() * rounded.abs().max(1.0);
+        (self.value - rounded).abs() <= tolerance
+    }
+
+    /// Format just the number, with comma separators (dots in European style)
+    ///
+    /// Only plain notation groups digits: scientific and engineering mantissas are
+    /// always under 10 or 1,000, so they are written without separators.
+    fn format_number(&self) -> String {
+        let formatted = match notation() {
+            Notation::Plain => {
+                if self.is_integer() && self.value.abs() < MAX_INTEGER_FOR_FORMATTING {
+                    format_number_with_commas(self.value.round() as i128)
+                } else {
+                    format_decimal_with_commas(self.value)
+                }
+            }
+            Notation::Scientific => format_with_exponent(self.value, 1),
+            Notation::Engineering => format_with_exponent(self.value, 3),
+        };
+
+        if decimal_comma() {
+            swap_decimal_separators(&formatted)
+        } else {
+            formatted
+        }
+    }
+
+    /// Append the unit's display name to a formatted number
+    fn with_unit_name(&self, formatted_value: String) -> String {
+        // Infinity and NaN aren't numbers that can be grouped or read back
+        if !self.value.is_finite() {
+            return "(undefined)".to_string();
+        }
 
         match &self.unit {
             Some(unit) => format!("{} {}", formatted_value, unit.display_name()),
@@ -149,8 +324,32 @@ impl UnitValue {
     }
 }
 
-/// Format a number with comma separators
-fn format_number_with_commas(num: i64) -> String {
+/// Format a number as a mantissa and an exponent that is a multiple of `step`,
+/// e.g. "1.5e9" (step 1) or "12.5e3" (step 3), with up to 3 decimals
+fn format_with_exponent(num: f64, step: i32) -> String {
+    if num.abs() < FLOAT_EPSILON || !num.is_finite() {
+        return format_decimal_with_commas(num);
+    }
+
+    let mut exponent = num.abs().log10().floor() as i32;
+    exponent -= exponent.rem_euclid(step);
+    let mut mantissa = num / 10f64.powi(exponent);
+
+    // Rounding can carry the mantissa up to the next exponent (9.9996e3 is 1e4)
+    if (mantissa.abs() * 1000.0).round() / 1000.0 >= 10f64.powi(step) {
+        exponent += step;
+        mantissa = num / 10f64.powi(exponent);
+    }
+
+    let mantissa = format!("{:.3}", mantissa);
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    format!("{}e{}", mantissa, exponent)
+}
+
+/// Format an integer with comma separators
+///
+/// Takes i128 so integers beyond the i64 range (up to about 1.7e38) group correctly.
+pub(crate) fn format_number_with_commas(num: i128) -> String {
     let num_str = num.to_string();
     let mut result = String::new();
     let chars: Vec<char> = num_str.chars().collect();
@@ -181,20 +380,10 @@ fn format_decimal_with_commas(num: f64) -> String {
     let is_negative = num < 0.0;
     let abs_num = num.abs();
 
-    let formatted = format!("{:.3}", abs_num);
+    let formatted = format!("{:.*}", decimal_places(), abs_num);
 
-    // Split into whole and decimal parts
-    let parts: Vec<&str> = formatted.split('.').collect();
-    if parts.len() != 2 {
-        return if is_negative {
-            format!("-{}", formatted)
-        } else {
-            formatted
-        };
-    }
-
-    let whole_part = parts[0];
-    let decimal_part = parts[1];
+    // Split into whole and decimal parts (there are none with 0 decimal places)
+    let (whole_part, decimal_part) = formatted.split_once('.').unwrap_or((&formatted, ""));
 
     // Add commas to whole part
     let whole_with_commas = if whole_part == "0" {
@@ -221,7 +410,8 @@ fn format_decimal_with_commas(num: f64) -> String {
         format!("{}.{}", whole_with_commas, decimal_trimmed)
     };
 
-    if is_negative {
+    // Values that round to zero (e.g., -0.0001) are shown as "0", not "-0"
+    if is_negative && formatted_result != "0" {
         format!("-{}", formatted_result)
     } else {
         formatted_result
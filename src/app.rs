@@ -1,9 +1,13 @@
 //! Application state and core logic
 
 use crate::Mode;
-use mathypad_core::core::MathypadCore;
-use mathypad_core::expression::update_line_references_in_text;
-use std::path::PathBuf;
+use crate::cursor_store::CursorPosition;
+use mathypad_core::core::{MathypadCore, Project, Theme};
+use mathypad_core::expression::{
+    ResultsSummary, evaluate_with_steps, parse_result_string, summarize_results,
+    update_line_references_in_text,
+};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 /// Animation state for a result line
@@ -66,6 +70,40 @@ impl ResultAnimation {
     }
 }
 
+/// State for cycling through unit name completions with repeated Tab presses
+#[derive(Clone, Debug)]
+pub struct UnitCompletion {
+    pub start_col: usize,        // Column where the completed unit name starts
+    pub candidates: Vec<String>, // Matching unit names
+    pub index: usize,            // Candidate currently inserted
+}
+
+/// A pad open in the editor, with its own text, results, cursor and file
+///
+/// The active buffer's state lives in `App`'s own fields (`core`, `file_path`,
+/// `has_unsaved_changes` and `scroll_offset`), so editing code doesn't need to know
+/// about buffers; the others wait in `App::buffers` until they're switched to.
+#[derive(Default)]
+pub struct Buffer {
+    pub core: MathypadCore,
+    pub file_path: Option<PathBuf>,
+    pub has_unsaved_changes: bool,
+    pub scroll_offset: usize,
+    /// The mathypad.toml next to the file; its settings apply while the buffer is active
+    pub project: Option<Project>,
+}
+
+impl Buffer {
+    /// Cursor line/column and scroll offset, to remember when the file is closed
+    pub fn cursor_position(&self) -> CursorPosition {
+        CursorPosition {
+            line: self.core.cursor_line,
+            col: self.core.cursor_col,
+            scroll_offset: self.scroll_offset,
+        }
+    }
+}
+
 /// Main application state for the mathematical notepad
 pub struct App {
     /// Core calculation and text state (shared with web UI)
@@ -80,6 +118,7 @@ pub struct App {
     pub save_as_input: String,                           // Current input for save as filename
     pub save_as_and_quit: bool, // Whether to quit after saving in save as dialog
     pub separator_position: u16, // Position of the separator between text and results (percentage)
+    pub show_results_panel: bool, // Whether the results panel is shown (toggled with :results)
     pub is_dragging_separator: bool, // Whether the user is currently dragging the separator
     pub is_hovering_separator: bool, // Whether the mouse is hovering over the separator
     pub copy_flash_animations: Vec<Option<ResultAnimation>>, // Flash animations for copied lines
@@ -91,6 +130,16 @@ pub struct App {
     pub pending_normal_command: Option<char>, // For multi-character vim commands like 'dd'
     pub command_line: String,            // Current command line input (starts with ':')
     pub command_cursor: usize,           // Cursor position in command line
+    pub unit_completion: Option<UnitCompletion>, // Active Tab completion of a unit name
+    pub steps_popup: Option<Vec<String>>, // Evaluation steps shown by :steps
+    pub show_result_type: bool,          // Show the current result's unit type (toggled with :type)
+    pub theme: Theme,                    // Syntax highlighting colors (switched with :theme)
+    pub visual_anchor: usize,            // Line where the visual mode selection started
+    pub buffers: Vec<Buffer>, // Open buffers; the active one's slot is empty while it's being edited
+    pub active_buffer: usize, // Index of the buffer being edited
+    pub project: Option<Project>, // Project file of the buffer being edited
+    pub replaced_settings: Option<Project>, // Settings the active project replaced, put back on switching away
+    pub message: Option<String>, // Error or warning shown at the bottom until the next key
 }
 
 impl Default for App {
@@ -107,6 +156,7 @@ impl Default for App {
             save_as_input: String::new(),      // Start with empty filename input
             save_as_and_quit: false,           // Start without quit flag
             separator_position: 80,            // Default to 80% for text, 20% for results
+            show_results_panel: true,          // Start with the results panel shown
             is_dragging_separator: false,      // Start without dragging
             is_hovering_separator: false,      // Start without hovering
             copy_flash_animations: vec![None], // Start with no copy animations
@@ -118,11 +168,28 @@ impl Default for App {
             pending_normal_command: None,      // No pending vim command
             command_line: String::new(),       // Start with empty command line
             command_cursor: 0,                 // Start cursor at beginning of command line
+            unit_completion: None,             // No completion in progress
+            steps_popup: None,                 // No steps shown
+            show_result_type: false,           // Unit type shown on demand
+            theme: Theme::default(),           // Standard colors until a theme is loaded
+            visual_anchor: 0,                  // Set when visual mode starts
+            buffers: vec![Buffer::default()],  // Just the pad being edited
+            active_buffer: 0,                  // Editing the first buffer
+            project: None,                     // No project file
+            replaced_settings: None,           // No project settings in effect
+            message: None,                     // Nothing to report
         }
     }
 }
 
 impl App {
+    /// Create an app editing the given buffer, such as a file just loaded
+    pub fn from_buffer(buffer: Buffer) -> App {
+        let mut app = App::default();
+        app.swap_active_buffer(buffer);
+        app
+    }
+
     #[cfg(test)]
     pub fn test_scenario_line_splitting(&mut self) -> (String, String) {
         // Set up the scenario: "5" on line 1, "line1 + 1" on line 2
@@ -156,6 +223,19 @@ impl App {
         self.has_unsaved_changes = true;
     }
 
+    /// Add `delta` to the number under or after the cursor (vim's Ctrl+A / Ctrl+X)
+    pub fn increment_number(&mut self, delta: i64) {
+        if self.core.increment_number_at_cursor(delta) {
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Insert text at the current cursor position, such as a snippet
+    pub fn insert_text(&mut self, text: &str) {
+        self.core.insert_str(text);
+        self.has_unsaved_changes = true;
+    }
+
     /// Delete the character before the cursor
     pub fn delete_char(&mut self) {
         if self.core.cursor_line < self.core.text_lines.len() {
@@ -209,11 +289,13 @@ impl App {
                     // Previous line is empty, current line has content
                     // Delete the previous line (conceptually what the user wants)
                     self.core.text_lines[self.core.cursor_line - 1] = current_line;
+                    self.core.shift_display_units(self.core.cursor_line - 1, -1);
                     self.update_line_references_for_deletion(self.core.cursor_line - 1);
                     self.core.cursor_line -= 1;
                     self.core.cursor_col = 0;
                 } else {
                     // Normal case: merge current line into previous line
+                    self.core.shift_display_units(self.core.cursor_line, -1);
                     self.update_line_references_for_deletion(self.core.cursor_line);
                     self.core.cursor_line -= 1;
                     self.core.cursor_col =
@@ -353,7 +435,12 @@ impl App {
             // Handle line reference updates for insertion
             let insertion_point = self.core.cursor_line + 1; // 0-based index of newly inserted line
 
+            self.core.shift_display_units(insertion_point, 1);
             if left_empty && !right_empty {
+                if let Some(unit) = self.core.display_units.remove(&self.core.cursor_line) {
+                    self.core.display_units.insert(insertion_point, unit);
+                }
+
                 // Content moved from cursor_line to insertion_point
                 // Use combined update that handles both content move and position shifts
                 self.update_line_references_for_line_split_with_content_move(
@@ -419,10 +506,53 @@ impl App {
         }
     }
 
+    /// Move cursor to the start of the line (Home / Ctrl+A)
+    pub fn move_to_line_start(&mut self) {
+        self.core.cursor_col = 0;
+    }
+
+    /// Move cursor past the last character of the line (End / Ctrl+E)
+    pub fn move_to_line_end(&mut self) {
+        if self.core.cursor_line < self.core.text_lines.len() {
+            self.core.cursor_col = self.core.text_lines[self.core.cursor_line].chars().count();
+        }
+    }
+
+    /// Delete from the start of the line to the cursor (Ctrl+U)
+    pub fn delete_to_line_start(&mut self) {
+        if self.core.cursor_line < self.core.text_lines.len() && self.core.cursor_col > 0 {
+            let line = &mut self.core.text_lines[self.core.cursor_line];
+            let end_byte = line
+                .char_indices()
+                .nth(self.core.cursor_col)
+                .map_or(line.len(), |(i, _)| i);
+            line.drain(..end_byte);
+
+            self.core.cursor_col = 0;
+            self.update_result(self.core.cursor_line);
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Delete from the cursor to the end of the line (Ctrl+K)
+    pub fn delete_to_line_end(&mut self) {
+        if self.core.cursor_line < self.core.text_lines.len() {
+            let line = &mut self.core.text_lines[self.core.cursor_line];
+            let Some((start_byte, _)) = line.char_indices().nth(self.core.cursor_col) else {
+                return;
+            };
+            line.truncate(start_byte);
+
+            self.update_result(self.core.cursor_line);
+            self.has_unsaved_changes = true;
+        }
+    }
+
     /// Delete the entire current line (vim 'dd' command)
     pub fn delete_line(&mut self) {
         if self.core.text_lines.len() > 1 {
             // Update line references before deletion
+            self.core.shift_display_units(self.core.cursor_line, -1);
             self.update_line_references_for_deletion(self.core.cursor_line);
 
             // Remove the line
@@ -835,6 +965,23 @@ impl App {
         }
     }
 
+    /// Convert the current line's result by appending (or replacing) a "to <unit>" conversion
+    ///
+    /// The line is only rewritten if it has a result and the converted line still
+    /// evaluates; otherwise it is left unchanged and false is returned.
+    pub fn convert_current_line_to(&mut self, unit: &str) -> bool {
+        let line_index = self.core.cursor_line;
+        if !self.core.convert_line_to(line_index, unit) {
+            return false;
+        }
+        self.start_result_animation(line_index);
+
+        let line_length = self.core.text_lines[line_index].chars().count();
+        self.core.cursor_col = self.core.cursor_col.min(line_length);
+        self.has_unsaved_changes = true;
+        true
+    }
+
     /// Update line references in all lines when a line is deleted
     /// All references > deleted_line need to be decremented by 1
     /// References to the deleted line become invalid
@@ -906,8 +1053,8 @@ impl App {
 
     /// Recalculate all lines in the notebook
     pub fn recalculate_all(&mut self) {
-        // Clear variables to ensure fresh calculation
-        self.core.variables.clear();
+        // Clear variables to ensure fresh calculation (pinned results are kept)
+        self.core.reset_variables();
 
         // Recalculate each line in order
         for i in 0..self.core.text_lines.len() {
@@ -979,6 +1126,108 @@ impl App {
         Ok(())
     }
 
+    /// Show how the current line's expression evaluates, step by step
+    pub fn show_steps_for_current_line(&mut self) {
+        let line_index = self.core.cursor_line;
+        let (mut steps, result) = evaluate_with_steps(
+            &self.core.text_lines[line_index],
+            &self.core.variables,
+            &self.core.results,
+            line_index,
+        );
+        steps.push(match result {
+            Some(result) => format!("Result: {}", result),
+            None => "No result".to_string(),
+        });
+        self.steps_popup = Some(steps);
+    }
+
+    /// Sort the pad's lines, or only the first to last given line, by their result
+    /// (`:sort`, or `:sort!` for largest first)
+    pub fn sort_lines(&mut self, lines: Option<(usize, usize)>, descending: bool) {
+        let range = match lines {
+            Some((first, last)) => first..last + 1,
+            None => 0..self.core.text_lines.len(),
+        };
+        if self.core.sort_lines_by_result(range, descending) {
+            self.result_animations = vec![None; self.core.text_lines.len()];
+            self.has_unsaved_changes = true;
+        } else {
+            self.message =
+                Some("Not sorted: a line would move above a line or variable it uses".to_string());
+        }
+    }
+
+    /// Duplicate the current line below it and move to the copy (like vim's `yyp`)
+    pub fn duplicate_line(&mut self) {
+        self.core.duplicate_line();
+
+        let copy = self.core.cursor_line;
+        if copy <= self.result_animations.len() {
+            self.result_animations.insert(copy, None);
+        }
+        if copy <= self.copy_flash_animations.len() {
+            self.copy_flash_animations.insert(copy, None);
+            self.copy_flash_is_result.insert(copy, false);
+        }
+        self.has_unsaved_changes = true;
+    }
+
+    /// Start selecting lines from the current one (vim's `V`)
+    pub fn start_visual_mode(&mut self) {
+        self.visual_anchor = self.core.cursor_line;
+        self.mode = Mode::Visual;
+    }
+
+    /// First and last selected line, if lines are being selected in visual mode
+    pub fn selected_lines(&self) -> Option<(usize, usize)> {
+        if self.mode != Mode::Visual {
+            return None;
+        }
+        Some(self.last_selection())
+    }
+
+    /// First and last line of the latest visual mode selection, for `:'<,'>` commands
+    pub fn last_selection(&self) -> (usize, usize) {
+        let anchor = self
+            .visual_anchor
+            .min(self.core.text_lines.len().saturating_sub(1));
+        let cursor = self.core.cursor_line;
+        (anchor.min(cursor), anchor.max(cursor))
+    }
+
+    /// Sum and average of the selected lines' results
+    pub fn selection_summary(&self) -> Option<ResultsSummary> {
+        let (first, last) = self.selected_lines()?;
+        summarize_results(self.core.results.iter().skip(first).take(last - first + 1))
+    }
+
+    /// Unit type of the current line's result, e.g. "DataRate: GiB/s", if it has one
+    pub fn current_result_type(&self) -> Option<String> {
+        let result = self.core.results.get(self.core.cursor_line)?.as_deref()?;
+        parse_result_string(result).map(|value| value.type_descriptor())
+    }
+
+    /// Current cursor line/column and scroll offset, to remember when the file is closed
+    pub fn cursor_position(&self) -> CursorPosition {
+        CursorPosition {
+            line: self.core.cursor_line,
+            col: self.core.cursor_col,
+            scroll_offset: self.scroll_offset,
+        }
+    }
+
+    /// Move the cursor to a remembered position, clamped to the current text
+    /// (the file may have shrunk since the position was saved)
+    pub fn restore_cursor_position(&mut self, position: CursorPosition) {
+        let last_line = self.core.text_lines.len().saturating_sub(1);
+        self.core.cursor_line = position.line.min(last_line);
+        self.core.cursor_col = position
+            .col
+            .min(self.core.text_lines[self.core.cursor_line].chars().count());
+        self.scroll_offset = position.scroll_offset.min(self.core.cursor_line);
+    }
+
     /// Set the file path (used when loading a file)
     pub fn set_file_path(&mut self, path: Option<PathBuf>) {
         self.file_path = path;
@@ -1008,6 +1257,139 @@ impl App {
         }
     }
 
+    /// Open a buffer after the existing ones and switch to it (`:e`)
+    ///
+    /// A file that is already open is switched to instead of being opened twice.
+    pub fn open_buffer(&mut self, buffer: Buffer) {
+        if let Some(path) = &buffer.file_path {
+            if self.switch_to_open_file(path) {
+                return;
+            }
+        }
+        self.buffers.push(buffer);
+        self.switch_buffer(self.buffers.len() - 1);
+    }
+
+    /// Switch to the buffer with a file already open, returning false if none has it
+    pub fn switch_to_open_file(&mut self, path: &Path) -> bool {
+        let path = canonical_path(path);
+        let open = (0..self.buffers.len()).find(|&i| {
+            self.buffer_path(i)
+                .is_some_and(|p| canonical_path(p) == path)
+        });
+        match open {
+            Some(index) => {
+                self.switch_buffer(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make another buffer active, keeping the current one's state in its slot
+    pub fn switch_buffer(&mut self, index: usize) {
+        if index == self.active_buffer || index >= self.buffers.len() {
+            return;
+        }
+        let incoming = std::mem::take(&mut self.buffers[index]);
+        self.buffers[self.active_buffer] = self.swap_active_buffer(incoming);
+        self.active_buffer = index;
+    }
+
+    /// Switch to the next buffer, wrapping around (`:bn`)
+    pub fn next_buffer(&mut self) {
+        self.switch_buffer((self.active_buffer + 1) % self.buffers.len());
+    }
+
+    /// Switch to the previous buffer, wrapping around (`:bp`)
+    pub fn previous_buffer(&mut self) {
+        let count = self.buffers.len();
+        self.switch_buffer((self.active_buffer + count - 1) % count);
+    }
+
+    /// Put a buffer's state into the active fields, returning what was there
+    fn swap_active_buffer(&mut self, buffer: Buffer) -> Buffer {
+        // Settings from the outgoing buffer's project don't apply to other pads
+        if let Some(replaced) = self.replaced_settings.take() {
+            replaced.apply_settings();
+        }
+
+        let outgoing = Buffer {
+            core: std::mem::replace(&mut self.core, buffer.core),
+            file_path: std::mem::replace(&mut self.file_path, buffer.file_path),
+            has_unsaved_changes: std::mem::replace(
+                &mut self.has_unsaved_changes,
+                buffer.has_unsaved_changes,
+            ),
+            scroll_offset: std::mem::replace(&mut self.scroll_offset, buffer.scroll_offset),
+            project: std::mem::replace(&mut self.project, buffer.project),
+        };
+
+        if let Some(project) = &self.project {
+            self.replaced_settings = Some(project.apply_settings());
+        }
+        // Settings like :notation may have changed while another buffer was showing
+        self.core.recalculate_all();
+
+        // Animations and half-finished commands belong to the text that was showing
+        let line_count = self.core.text_lines.len();
+        self.result_animations = vec![None; line_count];
+        self.copy_flash_animations = vec![None; line_count];
+        self.copy_flash_is_result = vec![false; line_count];
+        self.unit_completion = None;
+        self.pending_normal_command = None;
+        outgoing
+    }
+
+    /// File path of a buffer, whether or not it's the active one
+    fn buffer_path(&self, index: usize) -> Option<&PathBuf> {
+        if index == self.active_buffer {
+            self.file_path.as_ref()
+        } else {
+            self.buffers[index].file_path.as_ref()
+        }
+    }
+
+    /// Tab bar labels: each buffer's file name, with "*" if it has unsaved changes
+    pub fn buffer_labels(&self) -> Vec<String> {
+        (0..self.buffers.len())
+            .map(|index| {
+                let name = self
+                    .buffer_path(index)
+                    .and_then(|path| path.file_name())
+                    .map_or("[No Name]".into(), |name| name.to_string_lossy());
+                let unsaved = if index == self.active_buffer {
+                    self.has_unsaved_changes
+                } else {
+                    self.buffers[index].has_unsaved_changes
+                };
+                if unsaved {
+                    format!("{} *", name)
+                } else {
+                    name.into_owned()
+                }
+            })
+            .collect()
+    }
+
+    /// Make a buffer with unsaved changes active, preferring the current one
+    ///
+    /// Returns false if no buffer has unsaved changes, so it's safe to quit.
+    pub fn focus_unsaved_buffer(&mut self) -> bool {
+        if self.has_unsaved_changes {
+            return true;
+        }
+        match (0..self.buffers.len())
+            .find(|&i| i != self.active_buffer && self.buffers[i].has_unsaved_changes)
+        {
+            Some(index) => {
+                self.switch_buffer(index);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Update separator position based on mouse column position
     pub fn update_separator_position(&mut self, mouse_x: u16, terminal_width: u16) {
         // Calculate percentage based on mouse position
@@ -1016,8 +1398,20 @@ impl App {
         self.separator_position = percentage.clamp(20, 80);
     }
 
+    /// Set the results panel to a percentage of the width, showing it if it was hidden
+    ///
+    /// Like dragging, both panels keep at least 20% of the width.
+    pub fn set_results_width(&mut self, percentage: u16) {
+        self.separator_position = (100 - percentage.min(100)).clamp(20, 80);
+        self.show_results_panel = true;
+    }
+
     /// Check if mouse position is over the separator (within a few columns for easier dragging)
     pub fn is_mouse_over_separator(&self, mouse_x: u16, terminal_width: u16) -> bool {
+        // There is no separator to drag while the results panel is hidden
+        if !self.show_results_panel {
+            return false;
+        }
         let separator_x = (self.separator_position as f32 / 100.0 * terminal_width as f32) as u16;
         // Allow dragging within 2 columns of the separator
         mouse_x.abs_diff(separator_x) <= 2
@@ -1045,22 +1439,7 @@ impl App {
         line_index: usize,
         is_result: bool,
     ) -> Result<(), String> {
-        // Copy to clipboard using arboard (only available on non-WASM platforms)
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            let mut clipboard = arboard::Clipboard::new()
-                .map_err(|e| format!("Failed to access clipboard: {}", e))?;
-            clipboard
-                .set_text(text)
-                .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
-        }
-
-        #[cfg(target_arch = "wasm32")]
-        {
-            // On WASM, we can't use arboard but we still want to show the animation
-            // Web clipboard access would need to be implemented using web-sys if needed
-            let _ = text; // Suppress unused variable warning
-        }
+        set_clipboard_text(text)?;
 
         // Start flash animation for the copied line
         self.start_copy_flash_animation(line_index, is_result);
@@ -1068,6 +1447,36 @@ impl App {
         Ok(())
     }
 
+    /// Assemble every line that has a result as plain "expression = result" text
+    pub fn all_results_as_text(&self) -> String {
+        self.core
+            .text_lines
+            .iter()
+            .zip(&self.core.results)
+            .filter_map(|(line, result)| {
+                let result = result.as_ref()?;
+                // Lines ending in an "=" evaluate marker already have their separator
+                let expression = line.trim().trim_end_matches('=').trim_end();
+                Some(format!("{} = {}", expression, result))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Copy all expression/result pairs to the clipboard at once
+    pub fn copy_all_results_to_clipboard(&mut self) -> Result<(), String> {
+        set_clipboard_text(&self.all_results_as_text())?;
+
+        // Flash every copied result
+        for line_index in 0..self.core.results.len() {
+            if self.core.results[line_index].is_some() {
+                self.start_copy_flash_animation(line_index, true);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Start a copy flash animation for a specific line
     fn start_copy_flash_animation(&mut self, line_index: usize, is_result: bool) {
         // Ensure the copy flash animations vector is large enough
@@ -1117,9 +1526,49 @@ impl App {
     }
 }
 
+/// Put text on the system clipboard
+fn set_clipboard_text(text: &str) -> Result<(), String> {
+    // Copy to clipboard using arboard (only available on non-WASM platforms)
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut clipboard =
+            arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+        clipboard
+            .set_text(text)
+            .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        // On WASM, we can't use arboard but we still want to show the animation
+        // Web clipboard access would need to be implemented using web-sys if needed
+        let _ = text; // Suppress unused variable warning
+    }
+
+    Ok(())
+}
+
+/// A path in a form that can be compared, so "./a.pad" and "a.pad" are the same file
+///
+/// A file that doesn't exist yet is compared by its canonical directory and name.
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
 #[cfg(test)]
 mod app_tests {
     use super::*;
+    use mathypad_core::settings::{Notation, notation};
 
     #[test]
     fn test_line_splitting_with_line_references() {
@@ -1298,6 +1747,64 @@ mod app_tests {
         );
     }
 
+    #[test]
+    fn test_all_results_as_text() {
+        let mut app = App::default();
+        app.core.text_lines = vec![
+            "5 + 3".to_string(),
+            "Some notes".to_string(),
+            "".to_string(),
+            "  10 GiB to MiB =".to_string(),
+        ];
+        app.core.results = vec![
+            Some("8".to_string()),
+            None,
+            None,
+            Some("10,240 MiB".to_string()),
+        ];
+
+        assert_eq!(
+            app.all_results_as_text(),
+            "5 + 3 = 8\n10 GiB to MiB = 10,240 MiB"
+        );
+
+        // Nothing to copy when there are no results
+        let app = App::default();
+        assert_eq!(app.all_results_as_text(), "");
+    }
+
+    #[test]
+    fn test_convert_current_line_to() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["2 GiB + 512 MiB".to_string(), "notes".to_string()];
+        app.core.results = vec![None, None];
+        app.recalculate_all();
+
+        // Appends a conversion to the current line
+        assert!(app.convert_current_line_to("MiB"));
+        assert_eq!(app.core.text_lines[0], "2 GiB + 512 MiB to MiB");
+        assert_eq!(app.core.results[0], Some("2,560 MiB".to_string()));
+
+        // Replaces an existing conversion instead of stacking another one
+        assert!(app.convert_current_line_to(" GB "));
+        assert_eq!(app.core.text_lines[0], "2 GiB + 512 MiB to GB");
+        app.core.text_lines[0] = "2 GiB + 512 MiB in KiB =".to_string();
+        assert!(app.convert_current_line_to("GiB"));
+        assert_eq!(app.core.text_lines[0], "2 GiB + 512 MiB to GiB");
+        assert_eq!(app.core.results[0], Some("2.5 GiB".to_string()));
+
+        // Unknown and incompatible units leave the line alone
+        assert!(!app.convert_current_line_to("parsecs"));
+        assert!(!app.convert_current_line_to("hours"));
+        assert_eq!(app.core.text_lines[0], "2 GiB + 512 MiB to GiB");
+        assert_eq!(app.core.results[0], Some("2.5 GiB".to_string()));
+
+        // Lines without a result aren't rewritten
+        app.core.cursor_line = 1;
+        assert!(!app.convert_current_line_to("GiB"));
+        assert_eq!(app.core.text_lines[1], "notes");
+    }
+
     #[test]
     fn test_separator_position_updates() {
         let mut app = App::default();
@@ -1572,6 +2079,156 @@ mod app_tests {
         assert_eq!(app.core.cursor_col, 6);
     }
 
+    #[test]
+    fn test_buffers_keep_their_own_state() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["x = 5".to_string(), "x * 2".to_string()];
+        app.recalculate_all();
+        app.core.cursor_line = 1;
+        app.core.cursor_col = 3;
+        app.has_unsaved_changes = true;
+
+        app.open_buffer(Buffer {
+            core: MathypadCore::from_lines(vec!["1 GiB to MiB".to_string()]),
+            file_path: Some(PathBuf::from("sizes.pad")),
+            project: Some(Project {
+                notation: Some(Notation::Engineering),
+                ..Project::default()
+            }),
+            ..Buffer::default()
+        });
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.active_buffer, 1);
+        assert_eq!(app.core.text_lines, vec!["1 GiB to MiB"]);
+        assert_eq!(app.core.results[0], Some("1.024e3 MiB".to_string()));
+        assert_eq!(notation(), Notation::Engineering);
+        assert_eq!(app.core.cursor_line, 0);
+        assert!(!app.has_unsaved_changes);
+
+        // Variables from the other buffer aren't visible here
+        app.core.text_lines[0] = "x".to_string();
+        app.recalculate_all();
+        assert_eq!(app.core.results[0], None);
+
+        app.previous_buffer();
+        assert_eq!(app.active_buffer, 0);
+        assert_eq!(app.core.text_lines, vec!["x = 5", "x * 2"]);
+        assert_eq!(app.core.results[1], Some("10".to_string()));
+        assert_eq!(notation(), Notation::Plain);
+        assert_eq!((app.core.cursor_line, app.core.cursor_col), (1, 3));
+        assert!(app.has_unsaved_changes);
+        assert_eq!(app.file_path, None);
+
+        // Switching wraps around, and the edit in the second buffer was kept
+        app.previous_buffer();
+        assert_eq!(app.active_buffer, 1);
+        assert_eq!(app.core.text_lines, vec!["x"]);
+        assert_eq!(notation(), Notation::Engineering);
+        app.next_buffer();
+        assert_eq!(app.active_buffer, 0);
+        assert_eq!(notation(), Notation::Plain);
+
+        assert_eq!(app.buffer_labels(), vec!["[No Name] *", "sizes.pad"]);
+    }
+
+    #[test]
+    fn test_open_buffer_switches_to_an_open_file() {
+        let mut app = App::from_buffer(Buffer {
+            file_path: Some(PathBuf::from("a.pad")),
+            ..Buffer::default()
+        });
+        app.open_buffer(Buffer {
+            file_path: Some(PathBuf::from("b.pad")),
+            ..Buffer::default()
+        });
+        app.open_buffer(Buffer {
+            file_path: Some(PathBuf::from("a.pad")),
+            ..Buffer::default()
+        });
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.active_buffer, 0);
+        assert_eq!(app.file_path, Some(PathBuf::from("a.pad")));
+    }
+
+    #[test]
+    fn test_focus_unsaved_buffer() {
+        let mut app = App::default();
+        assert!(!app.focus_unsaved_buffer());
+
+        app.open_buffer(Buffer {
+            has_unsaved_changes: true,
+            ..Buffer::default()
+        });
+        app.open_buffer(Buffer::default());
+        assert_eq!(app.active_buffer, 2);
+
+        // Quitting from a saved buffer brings up the one with changes
+        assert!(app.focus_unsaved_buffer());
+        assert_eq!(app.active_buffer, 1);
+        app.has_unsaved_changes = false;
+        assert!(!app.focus_unsaved_buffer());
+    }
+
+    #[test]
+    fn test_line_start_and_end_movement() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["€5 × 3 = ?".to_string(), String::new()];
+        app.core.results = vec![None, None];
+
+        for col in [0, 4, 10] {
+            app.core.cursor_col = col;
+            app.move_to_line_end();
+            assert_eq!(app.core.cursor_col, 10);
+            app.move_to_line_start();
+            assert_eq!(app.core.cursor_col, 0);
+        }
+
+        app.core.cursor_line = 1;
+        app.move_to_line_end();
+        assert_eq!(app.core.cursor_col, 0);
+    }
+
+    #[test]
+    fn test_delete_to_line_start_and_end() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["5 € + 3 €".to_string()];
+        app.core.results = vec![None];
+
+        // Ctrl+U in the middle of a multibyte line
+        app.core.cursor_col = 4;
+        app.delete_to_line_start();
+        assert_eq!(app.core.text_lines[0], "+ 3 €");
+        assert_eq!(app.core.cursor_col, 0);
+        assert!(app.has_unsaved_changes);
+
+        // Ctrl+U at the start does nothing
+        app.delete_to_line_start();
+        assert_eq!(app.core.text_lines[0], "+ 3 €");
+
+        // Ctrl+K before a multibyte character
+        app.core.text_lines[0] = "5 € + 3 €".to_string();
+        app.core.cursor_col = 2;
+        app.delete_to_line_end();
+        assert_eq!(app.core.text_lines[0], "5 ");
+        assert_eq!(app.core.cursor_col, 2);
+
+        // Ctrl+K at the end does nothing
+        app.delete_to_line_end();
+        assert_eq!(app.core.text_lines[0], "5 ");
+
+        // Ctrl+U at the end clears the line, Ctrl+K at the start clears it too
+        app.core.text_lines[0] = "2 + 2".to_string();
+        app.core.cursor_col = 5;
+        app.delete_to_line_start();
+        assert_eq!(app.core.text_lines[0], "");
+
+        app.core.text_lines[0] = "2 + 2".to_string();
+        app.core.cursor_col = 0;
+        app.delete_to_line_end();
+        assert_eq!(app.core.text_lines[0], "");
+        assert_eq!(app.core.results[0], None);
+    }
+
     #[test]
     fn test_delete_word_forward_big() {
         let mut app = App::default();
@@ -1704,4 +2361,43 @@ mod app_tests {
         assert_eq!(app.core.text_lines[0], "🚀");
         assert_eq!(app.core.cursor_col, 1);
     }
+
+    #[test]
+    fn test_cursor_position_survives_reopen() {
+        use crate::cursor_store::{read_position, write_position};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = temp_dir.path().join("positions");
+        let file = temp_dir.path().join("notes.pad");
+
+        // "Close" a file with the cursor in the middle of line 3
+        let mut app = App::default();
+        app.core.text_lines = vec!["1 GiB".into(), "2 GiB".into(), "line1 + line2".into()];
+        app.core.cursor_line = 2;
+        app.core.cursor_col = 5;
+        app.scroll_offset = 1;
+        write_position(&store, &file, app.cursor_position()).unwrap();
+
+        // "Reopen" it unchanged
+        let mut reopened = App::default();
+        reopened.core.text_lines = app.core.text_lines.clone();
+        reopened.restore_cursor_position(read_position(&store, &file).unwrap());
+        assert_eq!(reopened.core.cursor_line, 2);
+        assert_eq!(reopened.core.cursor_col, 5);
+        assert_eq!(reopened.scroll_offset, 1);
+
+        // If the file shrank, the position is clamped to what's left
+        let mut shrunk = App::default();
+        shrunk.core.text_lines = vec!["1 GiB".into(), "2".into()];
+        shrunk.restore_cursor_position(read_position(&store, &file).unwrap());
+        assert_eq!(shrunk.core.cursor_line, 1);
+        assert_eq!(shrunk.core.cursor_col, 1);
+        assert_eq!(shrunk.scroll_offset, 1);
+
+        let mut empty = App::default();
+        empty.restore_cursor_position(read_position(&store, &file).unwrap());
+        assert_eq!(empty.core.cursor_line, 0);
+        assert_eq!(empty.core.cursor_col, 0);
+        assert_eq!(empty.scroll_offset, 0);
+    }
 }
@@ -1,8 +1,13 @@
 //! Binary entry point for mathypad
 
 use clap::{Arg, Command, ValueHint, crate_version};
+use mathypad::cli::{
+    run_document_mode, run_one_shot_template, run_report_mode, run_stats_mode, should_use_color,
+};
 use mathypad::{run_one_shot_mode, version};
+use mathypad_core::settings::set_strict_expressions;
 use std::error::Error;
+use std::io::IsTerminal;
 
 // TUI-related imports (not available on WASM)
 #[cfg(not(target_arch = "wasm32"))]
@@ -13,7 +18,23 @@ use std::path::PathBuf;
 fn main() -> Result<(), Box<dyn Error>> {
     // Check for one-shot mode first (before clap parsing to preserve existing behavior)
     if let Some(expression) = extract_one_shot_expression() {
-        return run_one_shot_mode(&expression);
+        if has_flag_before_separator("--strict") {
+            set_strict_expressions(true);
+        }
+        if let Some(template) = flag_value_before_separator("--format") {
+            return run_one_shot_template(&expression, &template);
+        }
+        let no_color_env = std::env::var("NO_COLOR").ok();
+        let color = should_use_color(
+            has_flag_before_separator("--no-color"),
+            std::io::stdout().is_terminal(),
+            no_color_env.as_deref(),
+        );
+        return run_one_shot_mode(
+            &expression,
+            has_flag_before_separator("--precision-full"),
+            color,
+        );
     }
 
     let matches = build_cli().get_matches();
@@ -24,12 +45,41 @@ fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    // Print the current and last seen versions for update checkers
+    if matches.get_flag("version-json") {
+        println!(
+            "{}",
+            version::version_json(version::get_stored_version().as_deref())
+        );
+        return Ok(());
+    }
+
+    // Strict mode ignores expressions embedded in prose
+    if matches.get_flag("strict") {
+        set_strict_expressions(true);
+    }
+
+    // Handle stats mode (reads values from stdin)
+    if matches.get_flag("stats") {
+        return run_stats_mode();
+    }
+
     // Handle changelog flags
     if matches.get_flag("changelog") || matches.get_flag("whats-new") {
         print_changelog();
         return Ok(());
     }
 
+    // Handle document mode (reads a whole pad from stdin)
+    if matches.get_flag("interactive-stdin") {
+        return run_document_mode();
+    }
+
+    // Handle report mode (prints a pad file with its results inline)
+    if let Some(path) = matches.get_one::<String>("report") {
+        return run_report_mode(path);
+    }
+
     // Initialize version tracking (create ~/.mathypad and write current version)
     if let Err(e) = version::init_version_tracking() {
         eprintln!("Warning: Could not initialize version tracking: {}", e);
@@ -68,6 +118,20 @@ fn extract_one_shot_expression() -> Option<String> {
     }
 }
 
+/// Check for a flag given before the "--" one-shot separator
+fn has_flag_before_separator(flag: &str) -> bool {
+    std::env::args()
+        .take_while(|arg| arg != "--")
+        .any(|arg| arg == flag)
+}
+
+/// Get the value of a flag like `--format <value>` given before the "--" separator
+fn flag_value_before_separator(flag: &str) -> Option<String> {
+    let mut args = std::env::args().take_while(|arg| arg != "--");
+    args.position(|arg| arg == flag)?;
+    args.next()
+}
+
 /// Build the CLI command structure
 fn build_cli() -> Command {
     Command::new("mathypad")
@@ -92,12 +156,61 @@ fn build_cli() -> Command {
                 .action(clap::ArgAction::SetTrue)
                 .help("Show the changelog"),
         )
+        .arg(
+            Arg::new("version-json")
+                .long("version-json")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print the current and last seen versions as JSON"),
+        )
         .arg(
             Arg::new("whats-new")
                 .long("whats-new")
                 .action(clap::ArgAction::SetTrue)
                 .help("Show what's new (alias for --changelog)"),
         )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print count, sum, mean, min, max and median of values read from stdin"),
+        )
+        .arg(
+            Arg::new("interactive-stdin")
+                .long("interactive-stdin")
+                .action(clap::ArgAction::SetTrue)
+                .help("Evaluate a whole pad read from stdin and print each line with its result"),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .value_hint(ValueHint::FilePath)
+                .help("Print a pad file as plain text with \" => result\" after each expression line"),
+        )
+        .arg(
+            Arg::new("precision-full")
+                .long("precision-full")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print one-shot results with full precision, without rounding or grouping"),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .action(clap::ArgAction::SetTrue)
+                .help("Print one-shot results without colors (also off when piped or NO_COLOR is set)"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("TEMPLATE")
+                .help("Print one-shot results with a template using {value}, {unit}, {formatted}, {input} and {type}"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(clap::ArgAction::SetTrue)
+                .help("Only evaluate lines that are entirely an expression, ignoring prose"),
+        )
         .arg(
             Arg::new("file")
                 .help("File to open")
@@ -110,6 +223,12 @@ fn build_cli() -> Command {
              \x20 mathypad                      # Start empty interactive mode\n\
              \x20 mathypad calculations.pad     # Open file in interactive mode\n\
              \x20 mathypad -- \"100 GB to GiB\"   # One-shot calculation\n\
+             \x20 mathypad --precision-full -- \"1 EiB to EB\"  # Show every digit\n\
+             \x20 mathypad --format '{value} {unit}' -- \"1 GiB to MiB\"  # Prints \"1024 MiB\"\n\
+             \x20 mathypad --stats < sizes.txt  # Summarize values from stdin\n\
+             \x20 mathypad --interactive-stdin < budget.pad  # Print a pad with its results\n\
+             \x20 mathypad --report budget.pad  # Share a pad with its results inline\n\
+             \x20 mathypad --version-json  # Print {\"version\":...,\"latest_seen\":...} for update checkers\n\
              \x20 eval \"$(mathypad --completions bash)\"  # Enable bash completions",
         )
 }
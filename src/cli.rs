@@ -1,18 +1,38 @@
 //! Command-line interface functions
 
+use crate::UnitValue;
 use crate::evaluate_expression_with_context;
-use crate::expression::parse_line_reference;
-use crate::units::parse_unit;
+use crate::expression::{
+    EvalResult, evaluate_expression_value_with_context, evaluate_with_bindings,
+    parse_line_reference,
+};
+use crate::units::{find_unit_suggestion, parse_unit};
+use mathypad_core::core::{MathypadCore, deserialize_lines};
+use std::collections::HashMap;
 use std::error::Error;
+use std::io::{BufRead, Read};
 
 /// Run one-shot evaluation mode (non-interactive)
-pub fn run_one_shot_mode(expression: &str) -> Result<(), Box<dyn Error>> {
+///
+/// With `full_precision` the result is printed with every digit of the f64 value
+/// instead of rounded and grouped. `color` turns on syntax highlighting of the
+/// expression; see `should_use_color`.
+pub fn run_one_shot_mode(
+    expression: &str,
+    full_precision: bool,
+    color: bool,
+) -> Result<(), Box<dyn Error>> {
     // Print the expression with syntax highlighting
-    print_formatted_expression(expression);
+    print_formatted_expression(expression, color);
 
     // Evaluate the expression (no context for one-shot mode)
-    if let Some(result) = evaluate_expression_with_context(expression, &[], 0) {
+    if let Some(result) = one_shot_result(expression, full_precision) {
         println!(" = {}", result);
+    } else if let Some((word, suggestion)) = find_unit_suggestion(expression) {
+        println!(
+            " = (invalid expression: unknown unit '{}', did you mean {}?)",
+            word, suggestion
+        );
     } else {
         println!(" = (invalid expression)");
     }
@@ -20,9 +40,279 @@ pub fn run_one_shot_mode(expression: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-/// Print a mathematical expression with ANSI color formatting
-pub fn print_formatted_expression(text: &str) {
-    // Use ANSI escape codes to print numbers in light blue and units in green
+/// Decide whether one-shot output should be highlighted with ANSI colors
+///
+/// Colors are used only when writing to a terminal, and are turned off by
+/// `--no-color` or a non-empty `NO_COLOR` environment variable (see no-color.org).
+pub fn should_use_color(
+    no_color_flag: bool,
+    is_terminal: bool,
+    no_color_env: Option<&str>,
+) -> bool {
+    is_terminal && !no_color_flag && no_color_env.is_none_or(str::is_empty)
+}
+
+/// Evaluate a one-shot expression, optionally keeping full f64 precision
+pub fn one_shot_result(expression: &str, full_precision: bool) -> Option<String> {
+    if full_precision {
+        evaluate_expression_value_with_context(expression, &[], 0)
+            .map(|value| value.format_full_precision())
+    } else {
+        evaluate_expression_with_context(expression, &[], 0)
+    }
+}
+
+/// Run one-shot mode printing only the result, formatted with a `--format` template
+pub fn run_one_shot_template(expression: &str, template: &str) -> Result<(), Box<dyn Error>> {
+    let result = evaluate_with_bindings(expression, &HashMap::new())
+        .ok_or_else(|| format!("could not evaluate '{}'", expression))?;
+    println!("{}", format_template(template, expression, &result)?);
+    Ok(())
+}
+
+/// Fill in a `--format` template for an evaluated expression
+///
+/// Placeholders are `{value}` (the number without grouping, e.g. "1024"), `{unit}`,
+/// `{formatted}` (the result as normally shown, e.g. "1,024 MiB"), `{input}` (the
+/// expression) and `{type}` (e.g. "Data"). Literal braces are written `{{` and `}}`.
+pub fn format_template(template: &str, input: &str, result: &EvalResult) -> Result<String, String> {
+    let value = &result.value;
+    let mut output = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('{') if name.is_empty() => {
+                            output.push('{');
+                            break;
+                        }
+                        Some('}') => {
+                            output.push_str(&placeholder_value(&name, input, value)?);
+                            break;
+                        }
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unclosed '{{' in format '{}'", template)),
+                    }
+                }
+            }
+            '}' => match chars.next() {
+                Some('}') => output.push('}'),
+                _ => {
+                    return Err(format!(
+                        "unmatched '}}' in format '{}' (use '}}}}' for a literal brace)",
+                        template
+                    ));
+                }
+            },
+            c => output.push(c),
+        }
+    }
+
+    Ok(output)
+}
+
+/// The text for one `--format` placeholder
+fn placeholder_value(name: &str, input: &str, value: &UnitValue) -> Result<String, String> {
+    match name {
+        "value" => Ok(UnitValue::new(value.value, None).format_plain()),
+        "unit" => Ok(value
+            .unit
+            .as_ref()
+            .map(|unit| unit.display_name().into_owned())
+            .unwrap_or_default()),
+        "formatted" => Ok(value.format()),
+        "input" => Ok(input.to_string()),
+        "type" => Ok(value
+            .unit
+            .as_ref()
+            .map_or("Dimensionless", |unit| unit.unit_type().name())
+            .to_string()),
+        _ => Err(format!(
+            "unknown placeholder '{{{}}}' (expected {{value}}, {{unit}}, {{formatted}}, {{input}} or {{type}})",
+            name
+        )),
+    }
+}
+
+/// Run stats mode: read one value per line from stdin and print summary statistics
+pub fn run_stats_mode() -> Result<(), Box<dyn Error>> {
+    let report = stats_report(std::io::stdin().lock())?;
+    print!("{}", report);
+    Ok(())
+}
+
+/// Compute count, sum, mean, min, max and median for one value per line of input
+///
+/// Each non-empty line is evaluated like a one-shot expression, so lines can be
+/// plain numbers ("42"), unit values ("1.5 GiB") or whole expressions ("2 * 512 MiB").
+/// All values are converted to the unit of the first one; a line that doesn't
+/// evaluate or whose unit isn't compatible with the first is an error.
+pub fn stats_report(input: impl BufRead) -> Result<String, Box<dyn Error>> {
+    let mut values: Vec<UnitValue> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        // The unrounded value, so many small values don't add up to 0
+        let value = evaluate_expression_value_with_context(text, &[], 0)
+            .ok_or_else(|| format!("line {}: could not evaluate '{}'", index + 1, text))?;
+
+        let value = match values.first() {
+            None => value,
+            Some(first) => match &first.unit {
+                Some(unit) => value.to_unit(unit),
+                None if value.unit.is_none() => Some(value),
+                None => None,
+            }
+            .ok_or_else(|| {
+                format!(
+                    "line {}: '{}' is not compatible with {}",
+                    index + 1,
+                    text,
+                    unit_type_name(first)
+                )
+            })?,
+        };
+        values.push(value);
+    }
+
+    let Some(first) = values.first() else {
+        return Err("no values to summarize".into());
+    };
+    let unit = first.unit.clone();
+
+    let mut sorted: Vec<f64> = values.iter().map(|v| v.value).collect();
+    sorted.sort_by(f64::total_cmp);
+
+    let count = sorted.len();
+    let sum: f64 = sorted.iter().sum();
+    let median = if count % 2 == 0 {
+        (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+    } else {
+        sorted[count / 2]
+    };
+    let format = |value: f64| UnitValue::new(value, unit.clone()).format();
+
+    Ok(format!(
+        "count: {}\nsum: {}\nmean: {}\nmin: {}\nmax: {}\nmedian: {}\n",
+        count,
+        format(sum),
+        format(sum / count as f64),
+        format(sorted[0]),
+        format(sorted[count - 1]),
+        format(median),
+    ))
+}
+
+/// Run document mode: read a whole pad from stdin and print it with its results
+pub fn run_document_mode() -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    print!("{}", document_report(&input));
+    Ok(())
+}
+
+/// Evaluate a whole document like a pad and print each line next to its result
+///
+/// Lines are evaluated top to bottom with the same context as the editor, so
+/// variables, "lineN", `prev`, `ans` and sum_above() all resolve. Results line up
+/// in a column after the longest line that has one, as "expression  → result";
+/// lines without a result are printed as they are.
+pub fn document_report(input: &str) -> String {
+    if input.is_empty() {
+        return String::new();
+    }
+
+    let core = MathypadCore::from_lines(deserialize_lines(input));
+    let lines: Vec<&str> = core.text_lines.iter().map(|line| line.trim_end()).collect();
+    let width = lines
+        .iter()
+        .zip(&core.results)
+        .filter(|(_, result)| result.is_some())
+        .map(|(line, _)| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    for (line, result) in lines.iter().zip(&core.results) {
+        match result {
+            Some(result) => {
+                let padding = width - line.chars().count();
+                output.push_str(&format!("{}{}  → {}\n", line, " ".repeat(padding), result));
+            }
+            None => {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+/// Run report mode: print a pad file with each result after its line
+pub fn run_report_mode(path: &str) -> Result<(), Box<dyn Error>> {
+    let input = std::fs::read_to_string(path)?;
+    print!("{}", plain_report(&input));
+    Ok(())
+}
+
+/// Mirror a pad as plain text for sharing, with results inline
+///
+/// Evaluates like `document_report`, but every line is kept exactly as written
+/// and lines with a result get " => result" appended instead of being aligned
+/// into a column. Prose, comments and blank lines pass through verbatim.
+pub fn plain_report(input: &str) -> String {
+    if input.is_empty() {
+        return String::new();
+    }
+
+    let core = MathypadCore::from_lines(deserialize_lines(input));
+    let mut output = String::new();
+    for (line, result) in core.text_lines.iter().zip(&core.results) {
+        match result {
+            Some(result) => output.push_str(&format!("{} => {}\n", line.trim_end(), result)),
+            None => {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+/// Name of a value's unit type for error messages
+fn unit_type_name(value: &UnitValue) -> &'static str {
+    value
+        .unit
+        .as_ref()
+        .map_or("plain numbers", |unit| unit.unit_type().name())
+}
+
+/// Print a mathematical expression, with ANSI color formatting if `color` is set
+pub fn print_formatted_expression(text: &str, color: bool) {
+    print!("{}", highlight_expression(text, color));
+}
+
+/// Highlight an expression with ANSI escape codes
+///
+/// Numbers are light blue, units green, keywords yellow, line references magenta
+/// and operators cyan. Without `color` the text is returned unchanged.
+pub fn highlight_expression(text: &str, color: bool) -> String {
+    let mut output = String::new();
+    let mut paint = |code: Option<u8>, text: &str| match code {
+        Some(code) if color => output.push_str(&format!("\x1b[{}m{}\x1b[0m", code, text)),
+        _ => output.push_str(text),
+    };
     let mut current_pos = 0;
     let chars: Vec<char> = text.chars().collect();
 
@@ -44,15 +334,15 @@ pub fn print_formatted_expression(text: &str) {
             // Check if it's a valid unit, keyword, or line reference
             if parse_line_reference(&word_text).is_some() {
                 // Print line reference in magenta (ANSI color code 95)
-                print!("\x1b[95m{}\x1b[0m", word_text);
+                paint(Some(95), &word_text);
             } else if word_text.to_lowercase() == "to" || word_text.to_lowercase() == "in" {
                 // Print keywords in yellow (ANSI color code 93)
-                print!("\x1b[93m{}\x1b[0m", word_text);
+                paint(Some(93), &word_text);
             } else if parse_unit(&word_text).is_some() {
                 // Print units in green (ANSI color code 92)
-                print!("\x1b[92m{}\x1b[0m", word_text);
+                paint(Some(92), &word_text);
             } else {
-                print!("{}", word_text);
+                paint(None, &word_text);
             }
         } else if chars[current_pos].is_ascii_digit() || chars[current_pos] == '.' {
             // Handle numbers
@@ -78,18 +368,20 @@ pub fn print_formatted_expression(text: &str) {
             if has_digit {
                 let number_text: String = chars[start_pos..current_pos].iter().collect();
                 // Print number in light blue (ANSI color code 94)
-                print!("\x1b[94m{}\x1b[0m", number_text);
+                paint(Some(94), &number_text);
             } else {
-                print!("{}", chars[start_pos]);
+                paint(None, &chars[start_pos].to_string());
                 current_pos = start_pos + 1;
             }
         } else if "+-*/()".contains(chars[current_pos]) {
             // Print operators in cyan (ANSI color code 96)
-            print!("\x1b[96m{}\x1b[0m", chars[current_pos]);
+            paint(Some(96), &chars[current_pos].to_string());
             current_pos += 1;
         } else {
-            print!("{}", chars[current_pos]);
+            paint(None, &chars[current_pos].to_string());
             current_pos += 1;
         }
     }
+
+    output
 }
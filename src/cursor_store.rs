@@ -0,0 +1,159 @@
+//! Remembers where the cursor was in each opened file
+//!
+//! Positions are kept in ~/.mathypad/positions, one file per line as
+//! "line col scroll_offset<TAB>path", with the most recently closed file last.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const POSITIONS_FILE: &str = "positions";
+
+/// Only the most recently closed files are remembered
+const MAX_ENTRIES: usize = 100;
+
+/// Cursor line/column and scroll offset of a file when it was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CursorPosition {
+    pub line: usize,
+    pub col: usize,
+    pub scroll_offset: usize,
+}
+
+/// Get the remembered cursor position for a file, if any
+pub fn load_cursor_position(file: &Path) -> Option<CursorPosition> {
+    read_position(&positions_file().ok()?, file)
+}
+
+/// Remember the cursor position for a file
+pub fn save_cursor_position(file: &Path, position: CursorPosition) -> Result<(), Box<dyn Error>> {
+    write_position(&positions_file()?, file, position)
+}
+
+/// Read the position for a file from a positions store
+pub fn read_position(store: &Path, file: &Path) -> Option<CursorPosition> {
+    let key = store_key(file);
+    let contents = fs::read_to_string(store).ok()?;
+
+    contents
+        .lines()
+        .rev()
+        .filter_map(parse_entry)
+        .find(|(path, _)| *path == key)
+        .map(|(_, position)| position)
+}
+
+/// Write the position for a file to a positions store, replacing any older entry
+pub fn write_position(
+    store: &Path,
+    file: &Path,
+    position: CursorPosition,
+) -> Result<(), Box<dyn Error>> {
+    let key = store_key(file);
+    let contents = fs::read_to_string(store).unwrap_or_default();
+
+    let mut entries: Vec<String> = contents
+        .lines()
+        .filter(|line| parse_entry(line).is_some_and(|(path, _)| path != key))
+        .map(str::to_string)
+        .collect();
+    entries.push(format!(
+        "{} {} {}\t{}",
+        position.line, position.col, position.scroll_offset, key
+    ));
+
+    let skip = entries.len().saturating_sub(MAX_ENTRIES);
+    let mut output = entries[skip..].join("\n");
+    output.push('\n');
+
+    if let Some(parent) = store.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(store, output)?;
+    Ok(())
+}
+
+/// Parse a "line col scroll_offset<TAB>path" entry
+fn parse_entry(entry: &str) -> Option<(&str, CursorPosition)> {
+    let (numbers, path) = entry.split_once('\t')?;
+    let mut numbers = numbers.split_whitespace().map(|n| n.parse::<usize>().ok());
+
+    let position = CursorPosition {
+        line: numbers.next()??,
+        col: numbers.next()??,
+        scroll_offset: numbers.next()??,
+    };
+    Some((path, position))
+}
+
+/// Files are keyed by their absolute path so relative and absolute opens match
+fn store_key(file: &Path) -> String {
+    fs::canonicalize(file)
+        .or_else(|_| std::path::absolute(file))
+        .unwrap_or_else(|_| file.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Get the ~/.mathypad/positions path
+fn positions_file() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(crate::version::get_mathypad_dir()?.join(POSITIONS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_position() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = temp_dir.path().join("positions");
+        let file_a = temp_dir.path().join("a.pad");
+        let file_b = temp_dir.path().join("b.pad");
+
+        assert_eq!(read_position(&store, &file_a), None);
+
+        let position = CursorPosition {
+            line: 12,
+            col: 4,
+            scroll_offset: 3,
+        };
+        write_position(&store, &file_a, position).unwrap();
+        write_position(&store, &file_b, CursorPosition::default()).unwrap();
+        assert_eq!(read_position(&store, &file_a), Some(position));
+        assert_eq!(
+            read_position(&store, &file_b),
+            Some(CursorPosition::default())
+        );
+
+        // Saving again replaces the old entry instead of adding another
+        let moved = CursorPosition {
+            line: 1,
+            col: 0,
+            scroll_offset: 0,
+        };
+        write_position(&store, &file_a, moved).unwrap();
+        assert_eq!(read_position(&store, &file_a), Some(moved));
+        assert_eq!(fs::read_to_string(&store).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_store_keeps_most_recent_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = temp_dir.path().join("positions");
+
+        for i in 0..MAX_ENTRIES + 5 {
+            let file = temp_dir.path().join(format!("{}.pad", i));
+            write_position(&store, &file, CursorPosition::default()).unwrap();
+        }
+
+        let oldest = temp_dir.path().join("0.pad");
+        let newest = temp_dir.path().join(format!("{}.pad", MAX_ENTRIES + 4));
+        assert_eq!(read_position(&store, &oldest), None);
+        assert!(read_position(&store, &newest).is_some());
+        assert_eq!(
+            fs::read_to_string(&store).unwrap().lines().count(),
+            MAX_ENTRIES
+        );
+    }
+}
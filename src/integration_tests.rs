@@ -9,6 +9,17 @@ use crate::test_helpers::{evaluate_test_expression, evaluate_with_unit_info};
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ui::handle_command_mode;
+    use crate::{App, Mode};
+    use crossterm::event::KeyCode;
+
+    /// Run a command line as if it was typed in command mode
+    fn run_command(app: &mut App, command: &str) {
+        app.mode = Mode::Command;
+        app.command_line = command.to_string();
+        app.command_cursor = app.command_line.len();
+        handle_command_mode(app, KeyCode::Enter);
+    }
 
     #[test]
     fn test_end_to_end_basic_calculations() {
@@ -916,10 +927,6 @@ mod tests {
 
     #[test]
     fn test_force_quit_commands() {
-        use crate::ui::handle_command_mode;
-        use crate::{App, Mode};
-        use crossterm::event::KeyCode;
-
         // Test :q! command - should quit immediately even with unsaved changes
         let mut app = App {
             mode: Mode::Command,
@@ -978,10 +985,180 @@ mod tests {
     }
 
     #[test]
-    fn test_additional_vim_commands() {
-        use crate::{App, Mode};
-        use crossterm::event::KeyCode;
+    fn test_addunit_command() {
+        use mathypad_core::settings::{AdditionUnitPolicy, set_addition_unit_policy};
+
+        let mut app = App::default();
+        app.core.text_lines = vec!["2 TB + 500 GB".to_string()];
+        app.core.results = vec![None];
+        app.recalculate_all();
+        assert_eq!(app.core.results[0], Some("2,500 GB".to_string()));
+
+        // :addunit left keeps the first operand's unit and recalculates
+        app.mode = Mode::Command;
+        app.command_line = ":addunit left".to_string();
+        app.command_cursor = app.command_line.len();
+        assert!(!handle_command_mode(&mut app, KeyCode::Enter));
+        assert_eq!(app.core.results[0], Some("2.5 TB".to_string()));
+
+        // Unknown policies are ignored
+        app.mode = Mode::Command;
+        app.command_line = ":addunit biggest".to_string();
+        app.command_cursor = app.command_line.len();
+        handle_command_mode(&mut app, KeyCode::Enter);
+        assert_eq!(app.core.results[0], Some("2.5 TB".to_string()));
+
+        app.mode = Mode::Command;
+        app.command_line = ":addunit smaller".to_string();
+        app.command_cursor = app.command_line.len();
+        handle_command_mode(&mut app, KeyCode::Enter);
+        assert_eq!(app.core.results[0], Some("2,500 GB".to_string()));
+
+        set_addition_unit_policy(AdditionUnitPolicy::default());
+    }
+
+    #[test]
+    fn test_unit_completion_candidates() {
+        use crate::ui::unit_completion_candidates;
+
+        let candidates = unit_completion_candidates("Gi");
+        for expected in ["GiB", "Gib", "GiB/s", "Gib/s", "Gibps"] {
+            assert!(
+                candidates.contains(&expected.to_string()),
+                "missing {} in {:?}",
+                expected,
+                candidates
+            );
+        }
+        // Shortest names come first
+        assert_eq!(&candidates[..2], ["GiB", "Gib"]);
+
+        // Falls back to case-insensitive matching
+        assert!(unit_completion_candidates("HOU").contains(&"hour".to_string()));
+        assert_eq!(unit_completion_candidates("xyz"), Vec::<String>::new());
+        assert_eq!(unit_completion_candidates(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_unit_tab_completion() {
+        use crate::App;
+        use crate::ui::handle_insert_mode;
+
+        let mut app = App::default();
+        for c in "10 minu".chars() {
+            handle_insert_mode(&mut app, KeyCode::Char(c));
+        }
+        // The common prefix of "minute" and "minutes" is completed first
+        handle_insert_mode(&mut app, KeyCode::Tab);
+        assert_eq!(app.core.text_lines[0], "10 minute");
+        assert_eq!(app.core.cursor_col, 9);
+        // Then the unique remaining match
+        handle_insert_mode(&mut app, KeyCode::Tab);
+        assert_eq!(app.core.text_lines[0], "10 minutes");
+
+        let mut app = App::default();
+        for c in "5 Gi".chars() {
+            handle_insert_mode(&mut app, KeyCode::Char(c));
+        }
+        // Ambiguous matches cycle on repeated Tabs
+        handle_insert_mode(&mut app, KeyCode::Tab);
+        assert_eq!(app.core.text_lines[0], "5 GiB");
+        assert_eq!(app.core.results[0], Some("5 GiB".to_string()));
+        handle_insert_mode(&mut app, KeyCode::Tab);
+        assert_eq!(app.core.text_lines[0], "5 Gib");
+
+        // Typing ends the cycle
+        handle_insert_mode(&mut app, KeyCode::Char(' '));
+        assert!(app.unit_completion.is_none());
+        assert_eq!(app.core.text_lines[0], "5 Gib ");
+    }
+
+    #[test]
+    fn test_control_keys_are_ignored_under_dialogs() {
+        use crate::ui::{handle_control_key, handle_insert_mode};
+
+        let mut app = App::default();
+        for c in "10 + 5".chars() {
+            handle_insert_mode(&mut app, KeyCode::Char(c));
+        }
+        handle_control_key(&mut app, 'a');
+        assert_eq!(app.core.cursor_col, 0);
+        handle_control_key(&mut app, 'k');
+        assert_eq!(app.core.text_lines[0], "");
+
+        for c in "10 + 5".chars() {
+            handle_insert_mode(&mut app, KeyCode::Char(c));
+        }
+        app.show_save_as_dialog = true;
+        handle_control_key(&mut app, 'u');
+        handle_control_key(&mut app, 'a');
+        assert_eq!(app.core.text_lines[0], "10 + 5");
+        assert_eq!(app.core.cursor_col, 6);
+        app.show_save_as_dialog = false;
+        app.steps_popup = Some(vec!["10 + 5 = 15".to_string()]);
+        handle_control_key(&mut app, 'a');
+        assert_eq!(app.core.cursor_col, 6);
+        app.steps_popup = None;
+
+        // Ctrl+A/X in normal mode leave the number alone too
+        app.mode = Mode::Normal;
+        app.core.cursor_col = 0;
+        app.show_welcome_dialog = true;
+        handle_control_key(&mut app, 'a');
+        assert_eq!(app.core.text_lines[0], "10 + 5");
+        app.show_welcome_dialog = false;
+        app.show_unsaved_dialog = true;
+        handle_control_key(&mut app, 'x');
+        assert_eq!(app.core.text_lines[0], "10 + 5");
+
+        app.show_unsaved_dialog = false;
+        handle_control_key(&mut app, 'a');
+        assert_eq!(app.core.text_lines[0], "11 + 5");
+        assert_eq!(app.core.results[0], Some("16".to_string()));
+    }
+
+    #[test]
+    fn test_results_command() {
+        let mut app = App::default();
+        assert!(app.show_results_panel);
+
+        run_command(&mut app, ":results off");
+        assert!(!app.show_results_panel);
+        assert!(!app.is_mouse_over_separator(80, 100));
+
+        run_command(&mut app, ":results on");
+        assert!(app.show_results_panel);
+
+        // Without an argument the panel is toggled
+        run_command(&mut app, ":results");
+        assert!(!app.show_results_panel);
+        run_command(&mut app, ":results");
+        assert!(app.show_results_panel);
+    }
+
+    #[test]
+    fn test_split_command() {
+        let mut app = App::default();
+        run_command(&mut app, ":split 30");
+        assert_eq!(app.separator_position, 70);
+
+        // Both panels stay usable, like when dragging the separator
+        run_command(&mut app, ":split 5");
+        assert_eq!(app.separator_position, 80);
+        run_command(&mut app, ":split 95");
+        assert_eq!(app.separator_position, 20);
+
+        // Invalid widths leave the split alone, and a hidden panel is shown again
+        run_command(&mut app, ":split wide");
+        assert_eq!(app.separator_position, 20);
+        app.show_results_panel = false;
+        run_command(&mut app, ":split 40");
+        assert_eq!(app.separator_position, 60);
+        assert!(app.show_results_panel);
+    }
 
+    #[test]
+    fn test_additional_vim_commands() {
         // Test basic movement commands
         let mut app = App::default();
         app.core.text_lines = vec![
@@ -1083,4 +1260,671 @@ mod tests {
         // The live update should have automatically updated line 2 to 40 (15 + 25)
         assert_eq!(app.core.results[2], Some("40".to_string()));
     }
+
+    #[test]
+    fn test_one_shot_full_precision() {
+        use crate::cli::one_shot_result;
+
+        // 1 EiB is 2^60 bytes = 1.152921504606846976 EB, the closest f64 to which prints as:
+        assert_eq!(
+            one_shot_result("1 EiB to EB", true),
+            Some("1.152921504606847 EB".to_string())
+        );
+        assert_eq!(
+            one_shot_result("1 EiB to EB", false),
+            Some("≈ 1.153 EB".to_string())
+        );
+
+        // No grouping either
+        assert_eq!(
+            one_shot_result("1 EiB to B", true),
+            Some("1152921504606847000 B".to_string())
+        );
+        assert_eq!(
+            one_shot_result("1 / 3", true),
+            Some("0.3333333333333333".to_string())
+        );
+        assert_eq!(one_shot_result("5 MB to QPS", true), None);
+    }
+
+    #[test]
+    fn test_one_shot_color_decision() {
+        use crate::cli::{highlight_expression, should_use_color};
+
+        assert!(should_use_color(false, true, None));
+        assert!(!should_use_color(false, false, None));
+        assert!(!should_use_color(true, true, None));
+        assert!(!should_use_color(false, true, Some("1")));
+        // An empty NO_COLOR doesn't count as set
+        assert!(should_use_color(false, true, Some("")));
+        assert!(!should_use_color(false, false, Some("")));
+
+        assert_eq!(
+            highlight_expression("5 GB to MB", false),
+            "5 GB to MB".to_string()
+        );
+        assert_eq!(
+            highlight_expression("5 GB + x", true),
+            "\x1b[94m5\x1b[0m \x1b[92mGB\x1b[0m \x1b[96m+\x1b[0m x"
+        );
+    }
+
+    #[test]
+    fn test_one_shot_format_template() {
+        use crate::cli::format_template;
+        use mathypad_core::evaluate_with_bindings;
+        use std::collections::HashMap;
+
+        let format = |template: &str, input: &str| {
+            let result = evaluate_with_bindings(input, &HashMap::new()).unwrap();
+            format_template(template, input, &result)
+        };
+
+        assert_eq!(
+            format("{value} {unit}", "1 GiB to MiB"),
+            Ok("1024 MiB".to_string())
+        );
+        assert_eq!(
+            format("{formatted}", "1 TiB to MiB"),
+            Ok("1,048,576 MiB".to_string())
+        );
+        assert_eq!(
+            format("{input} = {formatted} ({type})", "10 GB / 2 s"),
+            Ok("10 GB / 2 s = 5 GB/s (DataRate)".to_string())
+        );
+        assert_eq!(
+            format("{value}|{unit}|{type}", "5 * 3"),
+            Ok("15||Dimensionless".to_string())
+        );
+        assert_eq!(
+            format("no placeholders", "1 + 1"),
+            Ok("no placeholders".to_string())
+        );
+
+        // Doubled braces are literal
+        assert_eq!(
+            format("{{\"size\": {value}}}", "1.5 GiB"),
+            Ok("{\"size\": 1.5}".to_string())
+        );
+        assert_eq!(format("{{value}}", "1 + 1"), Ok("{value}".to_string()));
+
+        // Mistakes are reported instead of printed
+        assert!(
+            format("{size}", "1 GiB")
+                .unwrap_err()
+                .contains("unknown placeholder '{size}'")
+        );
+        assert!(
+            format("{value", "1 GiB")
+                .unwrap_err()
+                .contains("unclosed '{'")
+        );
+        assert!(
+            format("value}", "1 GiB")
+                .unwrap_err()
+                .contains("unmatched '}'")
+        );
+    }
+
+    #[test]
+    fn test_stats_mode_report() {
+        use crate::cli::stats_report;
+
+        let input = "1 GiB\n512 MiB\n\n2 * 1 GiB\n256 MiB\n";
+        let report = stats_report(input.as_bytes()).unwrap();
+        assert_eq!(
+            report,
+            "count: 4\n\
+             sum: 3.75 GiB\n\
+             mean: 0.938 GiB\n\
+             min: 0.25 GiB\n\
+             max: 2 GiB\n\
+             median: 0.75 GiB\n"
+        );
+
+        // Plain numbers work too, and an odd count takes the middle value
+        let report = stats_report("3\n1\n2\n".as_bytes()).unwrap();
+        assert!(report.contains("median: 2\n"));
+        assert!(report.contains("mean: 2\n"));
+
+        // Values are summarized before rounding, so tiny values don't count as 0
+        let report = stats_report("0.0004\n0.0002\n0.0004\n0.0006\n".as_bytes()).unwrap();
+        assert_eq!(
+            report,
+            "count: 4\n\
+             sum: 0.002\n\
+             mean: 0\n\
+             min: 0\n\
+             max: 0.001\n\
+             median: 0\n"
+        );
+
+        // Incompatible units abort with an error naming the line
+        let error = stats_report("1 GiB\n5 seconds\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: '5 seconds' is not compatible with Data"
+        );
+        assert!(stats_report("1 GiB\n42\n".as_bytes()).is_err());
+        assert!(stats_report("1 GiB\nhello\n".as_bytes()).is_err());
+        assert!(stats_report("\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_document_mode_report() {
+        use crate::cli::document_report;
+
+        let input = "# Storage budget\n\
+                     servers = 40\n\
+                     ram = 2 GiB\n\
+                     servers * ram\n\
+                     \n\
+                     line4 to TiB\n\
+                     prev * 2\n";
+        assert_eq!(
+            document_report(input),
+            "# Storage budget\n\
+             servers = 40   → 40\n\
+             ram = 2 GiB    → 2 GiB\n\
+             servers * ram  → 80 GiB\n\
+             \n\
+             line4 to TiB   → 0.078 TiB\n\
+             prev * 2       → 0.156 TiB\n"
+        );
+
+        // References to lines that don't resolve leave the line without a result
+        assert_eq!(document_report("5\nline3 + 1\n"), "5  → 5\nline3 + 1\n");
+        assert_eq!(document_report(""), "");
+    }
+
+    #[test]
+    fn test_plain_report() {
+        use crate::cli::plain_report;
+
+        let input = "# Storage budget\n\
+                     We run 40 servers, each with 2 GiB of RAM.\n\
+                     servers = 40\n\
+                     ram = 2 GiB   \n\
+                     \n\
+                     Total: servers * ram\n\
+                     line6 to TiB\n\
+                     \x20 indented notes stay as they are\n";
+        assert_eq!(
+            plain_report(input),
+            "# Storage budget\n\
+             We run 40 servers, each with 2 GiB of RAM.\n\
+             servers = 40 => 40\n\
+             ram = 2 GiB => 2 GiB\n\
+             \n\
+             Total: servers * ram => 80 GiB\n\
+             line6 to TiB => 0.078 TiB\n\
+             \x20 indented notes stay as they are\n"
+        );
+        assert_eq!(plain_report(""), "");
+    }
+
+    #[test]
+    fn test_buffer_commands() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let budget = temp_dir.path().join("budget.pad");
+        std::fs::write(&budget, "rent = $1200\nrent * 12").unwrap();
+
+        let mut app = App::default();
+        app.core.text_lines = vec!["2 + 2".to_string()];
+        app.recalculate_all();
+        app.has_unsaved_changes = true;
+
+        run_command(&mut app, &format!(":e {}", budget.display()));
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.file_path, Some(budget.clone()));
+        assert_eq!(app.core.results[1], Some("14,400 $".to_string()));
+
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.text_lines, vec!["2 + 2"]);
+        run_command(&mut app, ":bp");
+        assert_eq!(app.file_path, Some(budget.clone()));
+
+        // A file that doesn't exist yet opens empty, to be created on save
+        let new_pad = temp_dir.path().join("new.pad");
+        run_command(&mut app, &format!(":e {}", new_pad.display()));
+        assert_eq!(app.buffers.len(), 3);
+        assert_eq!(app.core.text_lines, vec![""]);
+        assert!(app.has_unsaved_changes);
+
+        // :q with unsaved changes in any buffer asks first
+        app.has_unsaved_changes = false;
+        app.mode = Mode::Command;
+        app.command_line = ":q".to_string();
+        assert!(!handle_command_mode(&mut app, KeyCode::Enter));
+        assert!(app.show_unsaved_dialog);
+        assert_eq!(app.active_buffer, 0);
+    }
+
+    #[test]
+    fn test_project_settings_stay_with_their_pad() {
+        use mathypad_core::settings::{Notation, notation, strict_expressions};
+
+        // Two pads in their own directories, each with its own project file
+        let strict_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            strict_dir.path().join("mathypad.toml"),
+            "[settings]\nstrict = true\n[variables]\nservers = 4",
+        )
+        .unwrap();
+        let strict_pad = strict_dir.path().join("plan.pad");
+        std::fs::write(&strict_pad, "Cost: 5 * 3\nservers * 2").unwrap();
+
+        let eng_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            eng_dir.path().join("mathypad.toml"),
+            "[settings]\nnotation = eng",
+        )
+        .unwrap();
+        let eng_pad = eng_dir.path().join("sizes.pad");
+        std::fs::write(&eng_pad, "Cost: 5 * 3\n1500 * 2\nservers * 2").unwrap();
+
+        let mut app = App::default();
+        app.core.text_lines = vec!["Cost: 5 * 3".to_string()];
+        app.recalculate_all();
+
+        run_command(&mut app, &format!(":e {}", strict_pad.display()));
+        assert_eq!(app.core.results, vec![None, Some("8".to_string())]);
+        assert!(strict_expressions());
+
+        // The other pad doesn't get the first one's settings or variables
+        run_command(&mut app, &format!(":e {}", eng_pad.display()));
+        assert_eq!(
+            app.core.results,
+            vec![Some("15e0".to_string()), Some("3e3".to_string()), None]
+        );
+        assert!(!strict_expressions());
+        assert_eq!(notation(), Notation::Engineering);
+
+        // A pad without a project evaluates with the editor's own settings again
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.results, vec![Some("15".to_string())]);
+        assert!(!strict_expressions());
+        assert_eq!(notation(), Notation::Plain);
+
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.results, vec![None, Some("8".to_string())]);
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.results[1], Some("3e3".to_string()));
+        run_command(&mut app, ":bn");
+        assert_eq!(notation(), Notation::Plain);
+    }
+
+    #[test]
+    fn test_edit_switches_to_an_open_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let pad = dir.path().join("plan.pad");
+        std::fs::write(&pad, "x = 5\nx * 2").unwrap();
+
+        let mut app = App::default();
+        run_command(&mut app, &format!(":e {}", pad.display()));
+        app.core.text_lines[0] = "x = 7".to_string();
+        app.recalculate_all();
+        app.has_unsaved_changes = true;
+        app.core.cursor_line = 1;
+        app.core.cursor_col = 4;
+
+        // Opening the file again switches back to the live buffer instead of reading it
+        std::fs::write(&pad, "changed on disk").unwrap();
+        run_command(&mut app, ":bn");
+        run_command(&mut app, &format!(":e {}", pad.display()));
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.active_buffer, 1);
+        assert_eq!(app.core.text_lines, vec!["x = 7", "x * 2"]);
+        assert_eq!(app.core.results[1], Some("14".to_string()));
+        assert_eq!((app.core.cursor_line, app.core.cursor_col), (1, 4));
+        assert!(app.has_unsaved_changes);
+
+        // Other spellings of the same path find the open buffer too
+        let dir_path = dir.path().display();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        for spelling in [
+            format!("{}/./plan.pad", dir_path),
+            format!("{}/sub/../plan.pad", dir_path),
+        ] {
+            run_command(&mut app, ":bn");
+            run_command(&mut app, &format!(":e {}", spelling));
+            assert_eq!(app.buffers.len(), 2);
+            assert_eq!(app.active_buffer, 1);
+        }
+        // Including files that haven't been saved yet
+        run_command(&mut app, &format!(":e {}/new.pad", dir_path));
+        run_command(&mut app, &format!(":e {}/./new.pad", dir_path));
+        assert_eq!(app.buffers.len(), 3);
+
+        // A file that can't be opened is reported in the editor
+        run_command(&mut app, &format!(":e {}/sub", dir_path));
+        assert_eq!(app.buffers.len(), 3);
+        assert!(app.message.unwrap().starts_with("Open failed:"));
+    }
+
+    #[test]
+    fn test_switching_buffers_picks_up_setting_changes() {
+        use mathypad_core::settings::{Notation, set_notation};
+
+        let dir = tempfile::tempdir().unwrap();
+        let pad = dir.path().join("sizes.pad");
+        std::fs::write(&pad, "2.5 GiB * 2").unwrap();
+
+        let mut app = App::default();
+        app.core.text_lines = vec!["1500 * 2".to_string()];
+        app.recalculate_all();
+        run_command(&mut app, &format!(":e {}", pad.display()));
+
+        // The other buffer is shown with the notation chosen while it was hidden
+        run_command(&mut app, ":notation eng");
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.results[0], Some("3e3".to_string()));
+        // With decimal commas, "2.5" is 25
+        run_command(&mut app, ":decimal comma");
+        run_command(&mut app, ":bn");
+        assert_eq!(app.core.results[0], Some("50e0 GiB".to_string()));
+
+        run_command(&mut app, ":decimal point");
+        set_notation(Notation::Plain);
+    }
+
+    #[test]
+    fn test_malformed_project_file_is_skipped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("mathypad.toml"),
+            "[settings]\nstrct = true\n[variables]\nservers = 4",
+        )
+        .unwrap();
+        let pad = temp_dir.path().join("plan.pad");
+        std::fs::write(&pad, "Cost: 5 * 3").unwrap();
+
+        let mut app = App::default();
+        run_command(&mut app, &format!(":e {}", pad.display()));
+        assert_eq!(app.file_path, Some(pad));
+        assert_eq!(app.core.results, vec![Some("15".to_string())]);
+        assert!(app.project.is_none());
+        // The problem is shown in the editor rather than printed over it
+        let message = app.message.unwrap();
+        assert!(message.starts_with("Could not load project file:"));
+        assert!(message.ends_with("line 2: unknown setting 'strct'"));
+    }
+
+    #[test]
+    fn test_pin_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["rate = 100 MB/s".to_string(), "rate * 1 hour".to_string()];
+        app.recalculate_all();
+        app.core.cursor_line = 1;
+
+        run_command(&mut app, ":pin before");
+        assert_eq!(app.core.pinned["before"].value, 360_000.0);
+
+        app.core.text_lines[0] = "rate = 200 MB/s".to_string();
+        app.core
+            .text_lines
+            .push("rate * 1 hour - before".to_string());
+        app.recalculate_all();
+        assert_eq!(app.core.results[1], Some("720,000 MB".to_string()));
+        assert_eq!(app.core.results[2], Some("360,000 MB".to_string()));
+
+        run_command(&mut app, ":unpin before");
+        assert!(app.core.pinned.is_empty());
+        assert_eq!(app.core.results[2], None);
+    }
+
+    #[test]
+    fn test_unit_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["notes".to_string(), "512 MiB * 4".to_string()];
+        app.recalculate_all();
+        app.core.cursor_line = 1;
+
+        run_command(&mut app, ":unit GiB");
+        assert_eq!(app.core.results[1], Some("2 GiB".to_string()));
+
+        // The unit follows the line when the one above is deleted
+        app.core.cursor_line = 0;
+        app.delete_line();
+        assert_eq!(app.core.text_lines, vec!["512 MiB * 4".to_string()]);
+        assert_eq!(app.core.results[0], Some("2 GiB".to_string()));
+
+        run_command(&mut app, ":unit");
+        assert_eq!(app.core.results[0], Some("2,048 MiB".to_string()));
+    }
+
+    #[test]
+    fn test_strict_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["Cost: 5 * 3".to_string(), "5 * 3".to_string()];
+        app.recalculate_all();
+        assert_eq!(app.core.results[0], Some("15".to_string()));
+
+        run_command(&mut app, ":strict on");
+        assert_eq!(app.core.results[0], None);
+        assert_eq!(app.core.results[1], Some("15".to_string()));
+
+        run_command(&mut app, ":strict off");
+        assert_eq!(app.core.results[0], Some("15".to_string()));
+    }
+
+    #[test]
+    fn test_decimal_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["3,14 + 0,86".to_string(), "line1 / 8".to_string()];
+        app.recalculate_all();
+        assert_eq!(app.core.results[0], Some("400".to_string()));
+        assert_eq!(app.core.results[1], Some("50".to_string()));
+
+        run_command(&mut app, ":decimal comma");
+        assert_eq!(app.core.results[0], Some("4".to_string()));
+        assert_eq!(app.core.results[1], Some("0,5".to_string()));
+
+        run_command(&mut app, ":decimal point");
+        assert_eq!(app.core.results[1], Some("50".to_string()));
+    }
+
+    #[test]
+    fn test_units_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec![
+            "h = 3".to_string(),
+            "h * 2".to_string(),
+            "1 GiB to MiB".to_string(),
+        ];
+        app.recalculate_all();
+        // "h" is read as hours
+        assert_eq!(app.core.results[1], Some("2 h".to_string()));
+
+        run_command(&mut app, ":units disable time");
+        assert_eq!(app.core.results[0], Some("3".to_string()));
+        assert_eq!(app.core.results[1], Some("6".to_string()));
+        assert_eq!(app.core.results[2], Some("1,024 MiB".to_string()));
+
+        run_command(&mut app, ":units enable time");
+        assert_eq!(app.core.results[1], Some("2 h".to_string()));
+
+        // Unknown families are left alone
+        run_command(&mut app, ":units disable length");
+        assert_eq!(app.core.results[2], Some("1,024 MiB".to_string()));
+    }
+
+    #[test]
+    fn test_sort_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec![
+            "2 hours".to_string(),
+            "30 min".to_string(),
+            "line1 + line2".to_string(),
+            "todo".to_string(),
+        ];
+        app.recalculate_all();
+
+        run_command(&mut app, ":sort");
+        assert_eq!(
+            app.core.text_lines,
+            vec!["30 min", "2 hours", "line2 + line1", "todo"]
+        );
+        assert_eq!(app.core.results[2], Some("150 min".to_string()));
+        assert!(app.has_unsaved_changes);
+
+        // Largest first would move the sum above the lines it adds up
+        app.has_unsaved_changes = false;
+        run_command(&mut app, ":sort!");
+        assert_eq!(
+            app.core.text_lines,
+            vec!["30 min", "2 hours", "line2 + line1", "todo"]
+        );
+        assert_eq!(app.core.results[2], Some("150 min".to_string()));
+        assert!(!app.has_unsaved_changes);
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn test_sort_selected_lines() {
+        use crate::ui::{handle_normal_mode, handle_visual_mode};
+
+        let mut app = App::default();
+        app.core.text_lines = vec![
+            "5 GiB".to_string(),
+            "3 GiB".to_string(),
+            "1 GiB".to_string(),
+            "4 GiB".to_string(),
+        ];
+        app.recalculate_all();
+        app.mode = Mode::Normal;
+        app.core.cursor_line = 1;
+
+        // V, j, then ':' starts a command for the selected lines
+        handle_normal_mode(&mut app, KeyCode::Char('V'));
+        handle_visual_mode(&mut app, KeyCode::Char('j'));
+        handle_visual_mode(&mut app, KeyCode::Char(':'));
+        assert_eq!(app.mode, Mode::Command);
+        assert_eq!(app.command_line, ":'<,'>");
+        for c in "sort".chars() {
+            handle_command_mode(&mut app, KeyCode::Char(c));
+        }
+        handle_command_mode(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.core.text_lines,
+            vec!["5 GiB", "1 GiB", "3 GiB", "4 GiB"]
+        );
+
+        app.core.cursor_line = 3;
+        run_command(&mut app, ":'<,'>sort!");
+        assert_eq!(
+            app.core.text_lines,
+            vec!["5 GiB", "4 GiB", "3 GiB", "1 GiB"]
+        );
+
+        // Other commands don't take a selection
+        run_command(&mut app, ":'<,'>dup");
+        assert_eq!(app.core.text_lines.len(), 4);
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn test_theme_command() {
+        use mathypad_core::core::Theme;
+
+        let mut app = App::default();
+        run_command(&mut app, ":theme monochrome");
+        assert_eq!(app.theme, Theme::monochrome());
+
+        // Unknown themes leave the current one alone
+        run_command(&mut app, ":theme neon");
+        assert_eq!(app.theme, Theme::monochrome());
+
+        run_command(&mut app, ":theme default");
+        assert_eq!(app.theme, Theme::default());
+    }
+
+    #[test]
+    fn test_dup_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec![
+            "$40/hour".to_string(),
+            "line1 * 8 hours".to_string(),
+            "line2 * 5".to_string(),
+        ];
+        app.recalculate_all();
+        app.core.cursor_line = 1;
+
+        run_command(&mut app, ":dup");
+        assert_eq!(
+            app.core.text_lines,
+            vec![
+                "$40/hour",
+                "line1 * 8 hours",
+                "line1 * 8 hours",
+                "line2 * 5"
+            ]
+        );
+        assert_eq!(app.core.cursor_line, 2);
+        assert_eq!(app.core.results[1], Some("320 $".to_string()));
+        assert_eq!(app.core.results[2], Some("320 $".to_string()));
+        assert_eq!(app.core.results[3], Some("1,600 $".to_string()));
+        assert!(app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_insert_text_at_cursor() {
+        use crate::App;
+
+        let mut app = App::default();
+        app.core.text_lines = vec!["price * 12".to_string()];
+        app.recalculate_all();
+        app.core.cursor_col = 0;
+
+        // Snippets are inserted where the cursor is and evaluated right away
+        app.insert_text("$40/month ");
+        assert_eq!(app.core.text_lines[0], "$40/month price * 12");
+        assert_eq!(app.core.cursor_col, 10);
+        assert!(app.has_unsaved_changes);
+
+        app.core.text_lines = vec!["5 € + ".to_string()];
+        app.core.cursor_col = 6;
+        app.insert_text("3 €");
+        assert_eq!(app.core.text_lines[0], "5 € + 3 €");
+        assert_eq!(app.core.cursor_col, 9);
+        assert_eq!(app.core.results[0], Some("8 €".to_string()));
+    }
+
+    #[test]
+    fn test_fmt_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["2GiB+512MiB".to_string(), "(1+2".to_string()];
+        app.recalculate_all();
+        app.core.cursor_col = 7;
+
+        run_command(&mut app, ":fmt");
+        assert_eq!(app.core.text_lines[0], "2GiB + 512MiB");
+        assert_eq!(app.core.results[0], Some("2,560 MiB".to_string()));
+        assert_eq!(app.core.cursor_col, 7);
+        assert!(app.has_unsaved_changes);
+
+        // Lines that don't parse are left alone
+        app.has_unsaved_changes = false;
+        app.core.cursor_line = 1;
+        run_command(&mut app, ":fmt");
+        assert_eq!(app.core.text_lines[1], "(1+2");
+        assert!(!app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_steps_command() {
+        let mut app = App::default();
+        app.core.text_lines = vec!["hello".to_string()];
+        app.recalculate_all();
+
+        app.mode = Mode::Command;
+        app.command_line = ":steps".to_string();
+        app.command_cursor = app.command_line.len();
+        handle_command_mode(&mut app, KeyCode::Enter);
+
+        // Lines without a result still get a popup saying so
+        let steps = app.steps_popup.clone().unwrap();
+        assert_eq!(steps.last().map(String::as_str), Some("No result"));
+        assert_eq!(app.core.text_lines, vec!["hello".to_string()]);
+    }
 }
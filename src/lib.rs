@@ -15,8 +15,16 @@ pub mod version;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod app;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod cursor_store;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod mode;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod project_config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod snippet_store;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod theme_config;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod ui;
 
 // GUI module (only available with 'gui' feature)
@@ -36,7 +44,7 @@ pub use mathypad_core::{Unit, UnitType, UnitValue};
 
 // TUI-related re-exports (not available on WASM)
 #[cfg(not(target_arch = "wasm32"))]
-pub use app::App;
+pub use app::{App, Buffer};
 #[cfg(not(target_arch = "wasm32"))]
 pub use mode::Mode;
 #[cfg(not(target_arch = "wasm32"))]
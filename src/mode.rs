@@ -9,4 +9,6 @@ pub enum Mode {
     Normal,
     /// Command mode - vim-like command line
     Command,
+    /// Visual mode - vim-like linewise selection
+    Visual,
 }
@@ -0,0 +1,55 @@
+//! Loads the mathypad.toml project file that sits next to a pad
+//!
+//! See `mathypad_core::core::project` for what the file can contain.
+
+use mathypad_core::core::Project;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const PROJECT_FILE: &str = "mathypad.toml";
+
+/// Get the project file path for a pad: mathypad.toml in the same directory
+pub fn project_file_for(pad: &Path) -> PathBuf {
+    pad.with_file_name(PROJECT_FILE)
+}
+
+/// Read a project file, or None if it doesn't exist
+pub fn read_project(path: &Path) -> Result<Option<Project>, Box<dyn Error>> {
+    let config = match fs::read_to_string(path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let project =
+        Project::from_config(&config).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some(project))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_project() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = project_file_for(&temp_dir.path().join("plan.pad"));
+        assert_eq!(path, temp_dir.path().join("mathypad.toml"));
+
+        assert!(read_project(&path).unwrap().is_none());
+
+        fs::write(&path, "[variables]\nservers = 12\n").unwrap();
+        let project = read_project(&path).unwrap().unwrap();
+        assert_eq!(project.values[0].0, "servers");
+        assert_eq!(project.values[0].1.value, 12.0);
+
+        fs::write(&path, "[settings]\ncalendar = 360\n").unwrap();
+        let error = read_project(&path).unwrap_err().to_string();
+        assert!(
+            error.ends_with("mathypad.toml: line 2: unknown setting 'calendar'"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Saved formula snippets, inserted with `:snip name` and saved with `:snipsave name`
+//!
+//! Snippets are kept in ~/.mathypad/snippets, one per line as "name<TAB>text",
+//! e.g. "monthly_cost\tmonthly_cost = $X/month".
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SNIPPETS_FILE: &str = "snippets";
+
+/// Get a saved snippet by name, if there is one
+pub fn load_snippet(name: &str) -> Option<String> {
+    read_snippet(&snippets_file().ok()?, name)
+}
+
+/// Save a snippet under a name, replacing any snippet with that name
+pub fn save_snippet(name: &str, text: &str) -> Result<(), Box<dyn Error>> {
+    write_snippet(&snippets_file()?, name, text)
+}
+
+/// Read a snippet by name from a snippets store
+pub fn read_snippet(store: &Path, name: &str) -> Option<String> {
+    let contents = fs::read_to_string(store).ok()?;
+
+    contents
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(snippet_name, _)| *snippet_name == name)
+        .map(|(_, text)| text.to_string())
+}
+
+/// Write a snippet to a snippets store, replacing any older snippet with that name
+///
+/// Names can't be empty or contain whitespace, and the text must be a single
+/// non-empty line.
+pub fn write_snippet(store: &Path, name: &str, text: &str) -> Result<(), Box<dyn Error>> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid snippet name '{}'", name).into());
+    }
+    if text.trim().is_empty() || text.contains(['\n', '\r']) {
+        return Err("a snippet must be a single non-empty line".into());
+    }
+
+    let contents = fs::read_to_string(store).unwrap_or_default();
+    let mut entries: Vec<&str> = contents
+        .lines()
+        .filter(|line| {
+            line.split_once('\t')
+                .is_some_and(|(other, _)| other != name)
+        })
+        .collect();
+    let entry = format!("{}\t{}", name, text);
+    entries.push(&entry);
+
+    let mut output = entries.join("\n");
+    output.push('\n');
+
+    if let Some(parent) = store.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(store, output)?;
+    Ok(())
+}
+
+/// Get the ~/.mathypad/snippets path
+fn snippets_file() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(crate::version::get_mathypad_dir()?.join(SNIPPETS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_snippet() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = temp_dir.path().join("snippets");
+
+        assert_eq!(read_snippet(&store, "monthly_cost"), None);
+
+        write_snippet(&store, "monthly_cost", "monthly_cost = $X/month").unwrap();
+        write_snippet(&store, "egress", "egress = 5 TB * $0.09/GB").unwrap();
+        assert_eq!(
+            read_snippet(&store, "monthly_cost"),
+            Some("monthly_cost = $X/month".to_string())
+        );
+        assert_eq!(
+            read_snippet(&store, "egress"),
+            Some("egress = 5 TB * $0.09/GB".to_string())
+        );
+
+        // Saving again replaces the old snippet instead of adding another
+        write_snippet(&store, "egress", "egress = 10 TB * $0.09/GB").unwrap();
+        assert_eq!(
+            read_snippet(&store, "egress"),
+            Some("egress = 10 TB * $0.09/GB".to_string())
+        );
+        assert_eq!(fs::read_to_string(&store).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_invalid_snippets_are_rejected() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = temp_dir.path().join("snippets");
+
+        assert!(write_snippet(&store, "", "1 + 1").is_err());
+        assert!(write_snippet(&store, "two words", "1 + 1").is_err());
+        assert!(write_snippet(&store, "blank", "   ").is_err());
+        assert!(write_snippet(&store, "multi", "1\n2").is_err());
+        assert!(!store.exists());
+    }
+}
@@ -0,0 +1,59 @@
+//! Loads the syntax highlighting theme from ~/.mathypad/theme
+//!
+//! The file names a built-in theme and/or overrides single colors, one setting per
+//! line, e.g. "theme = high-contrast" or "number = #4169e1".
+
+use mathypad_core::core::Theme;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const THEME_FILE: &str = "theme";
+
+/// Get the configured theme, or None if there is no theme file
+pub fn load_theme() -> Result<Option<Theme>, Box<dyn Error>> {
+    read_theme(&theme_file()?)
+}
+
+/// Read a theme config file, or None if it doesn't exist
+pub fn read_theme(path: &Path) -> Result<Option<Theme>, Box<dyn Error>> {
+    let config = match fs::read_to_string(path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let theme = Theme::from_config(&config).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some(theme))
+}
+
+/// Get the ~/.mathypad/theme path
+fn theme_file() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(crate::version::get_mathypad_dir()?.join(THEME_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_theme() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("theme");
+
+        assert!(read_theme(&path).unwrap().is_none());
+
+        fs::write(&path, "theme = high-contrast\nnormal = #808080\n").unwrap();
+        let theme = read_theme(&path).unwrap().unwrap();
+        assert_eq!(theme.name, "high-contrast");
+        assert_eq!(theme.normal, (128, 128, 128));
+
+        fs::write(&path, "theme = neon\n").unwrap();
+        let error = read_theme(&path).unwrap_err().to_string();
+        assert!(
+            error.ends_with("theme: line 1: unknown theme 'neon'"),
+            "{}",
+            error
+        );
+    }
+}
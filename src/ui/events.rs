@@ -1,6 +1,11 @@
 //! Event handling and main TUI loop
 
-use super::render::ui;
+use super::render::{split_main_area, split_tab_bar, ui, welcome_scroll_bounds};
+use crate::app::{Buffer, UnitCompletion};
+use crate::cursor_store::{load_cursor_position, save_cursor_position};
+use crate::project_config::{project_file_for, read_project};
+use crate::snippet_store::{load_snippet, save_snippet};
+use crate::theme_config::load_theme;
 use crate::{App, Mode, TICK_RATE_MS};
 use crossterm::{
     event::{
@@ -10,7 +15,15 @@ use crossterm::{
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use mathypad_core::UnitType;
+use mathypad_core::core::{MathypadCore, Theme};
+use mathypad_core::expression::format_expression;
+use mathypad_core::settings::{
+    AdditionUnitPolicy, Notation, UnitFamily, set_addition_unit_policy, set_assume_bytes,
+    set_decimal_comma, set_notation, set_strict_expressions, set_unit_family_enabled,
+};
+use mathypad_core::units::supported_units;
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Rect};
 use std::{
     error::Error,
     fs,
@@ -181,6 +194,13 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
     // Initialize recovery state with current app
     update_panic_recovery_text(&app.core.text_lines);
 
+    // Use the configured highlighting theme, if there is one
+    match load_theme() {
+        Ok(Some(theme)) => app.theme = theme,
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: Could not load theme: {}", e),
+    }
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -221,6 +241,8 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
         if crossterm::event::poll(timeout)? {
             match event::read()? {
                 Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    // A message stays up until the next key
+                    app.message = None;
                     match key.code {
                         KeyCode::Char('q')
                             if key
@@ -231,7 +253,7 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                             if app.show_unsaved_dialog {
                                 // In dialog: Ctrl+Q means quit without saving
                                 break;
-                            } else if app.has_unsaved_changes {
+                            } else if app.focus_unsaved_buffer() {
                                 // Show unsaved changes dialog
                                 app.show_unsaved_dialog = true;
                             } else {
@@ -248,7 +270,7 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                             if app.show_unsaved_dialog {
                                 // In dialog: Ctrl+C means quit without saving
                                 break;
-                            } else if app.has_unsaved_changes {
+                            } else if app.focus_unsaved_buffer() {
                                 // Show unsaved changes dialog
                                 app.show_unsaved_dialog = true;
                             } else {
@@ -265,6 +287,13 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                                 app.delete_word();
                             }
                         }
+                        KeyCode::Char(c @ ('a' | 'x' | 'e' | 'u' | 'k'))
+                            if key
+                                .modifiers
+                                .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                        {
+                            handle_control_key(&mut app, c);
+                        }
                         KeyCode::Char('s')
                             if key
                                 .modifiers
@@ -274,7 +303,7 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                                 // In save as dialog: Ctrl+S means confirm save
                                 match app.save_as_from_dialog() {
                                     Ok(should_quit) => {
-                                        if should_quit {
+                                        if should_quit && quit_after_save(&mut app) {
                                             break;
                                         }
                                     }
@@ -287,7 +316,7 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                                 if app.file_path.is_some() {
                                     if let Err(e) = app.save() {
                                         eprintln!("Save failed: {}", e);
-                                    } else {
+                                    } else if quit_after_save(&mut app) {
                                         // Save succeeded, exit
                                         break;
                                     }
@@ -309,7 +338,10 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                             }
                         }
                         KeyCode::Esc => {
-                            if app.show_save_as_dialog {
+                            if app.steps_popup.is_some() {
+                                // Dismiss the evaluation steps popup
+                                app.steps_popup = None;
+                            } else if app.show_save_as_dialog {
                                 // Dismiss the save as dialog
                                 app.show_save_as_dialog = false;
                                 app.save_as_and_quit = false;
@@ -329,14 +361,19 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                             }
                         }
                         _ => {
-                            if app.show_save_as_dialog {
+                            if app.steps_popup.is_some() {
+                                // Any key dismisses the evaluation steps popup
+                                app.steps_popup = None;
+                            } else if app.show_save_as_dialog {
                                 // Handle text input for save as dialog
                                 if handle_save_as_input(&mut app, key.code) {
                                     break;
                                 }
                             } else if app.show_welcome_dialog {
                                 // Handle welcome dialog input (scrolling)
-                                handle_welcome_dialog_input(&mut app, key.code);
+                                let size = terminal.size()?;
+                                let area = Rect::new(0, 0, size.width, size.height);
+                                handle_welcome_dialog_input(&mut app, key.code, area);
                             } else if !app.show_unsaved_dialog {
                                 // Only handle normal input if we're not showing any dialog
                                 match app.mode {
@@ -351,6 +388,9 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
                                             break;
                                         }
                                     }
+                                    Mode::Visual => {
+                                        handle_visual_mode(&mut app, key.code);
+                                    }
                                 }
                             }
                         }
@@ -382,21 +422,49 @@ fn run_event_loop(mut app: App) -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
+    // Remember where the cursor was in each file so reopening it picks up from there
+    let active = app
+        .file_path
+        .as_ref()
+        .map(|path| (path, app.cursor_position()));
+    let others = app
+        .buffers
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| index != app.active_buffer)
+        .filter_map(|(_, buffer)| Some((buffer.file_path.as_ref()?, buffer.cursor_position())));
+    for (path, position) in active.into_iter().chain(others) {
+        if let Err(e) = save_cursor_position(path, position) {
+            eprintln!("Warning: Could not save cursor position: {}", e);
+        }
+    }
+
     Ok(())
 }
 
 /// Run the interactive TUI mode with an optional file to load
 pub fn run_interactive_mode_with_file(file_path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
     let app = if let Some(path) = file_path {
-        load_app_from_file(path)?
+        let (buffer, warning) = load_buffer_from_file(path.clone())?;
+        let mut app = App::from_buffer(buffer);
+        if let Some(position) = load_cursor_position(&path) {
+            app.restore_cursor_position(position);
+        }
+        app.message = warning;
+        app
     } else {
         App::default()
     };
     run_event_loop(app)
 }
 
-/// Load an App from a file, creating the file if it doesn't exist
-fn load_app_from_file(path: PathBuf) -> Result<App, Box<dyn Error>> {
+/// Load a file into a buffer, creating the file if it doesn't exist
+///
+/// Rates and variables from a mathypad.toml next to the file are pinned in the
+/// buffer, and its settings apply while the buffer is active. A project file that
+/// can't be read is skipped so the pad still opens, and a warning to show for it is
+/// returned with the buffer.
+pub fn load_buffer_from_file(path: PathBuf) -> Result<(Buffer, Option<String>), Box<dyn Error>> {
     let contents = match fs::read_to_string(&path) {
         Ok(contents) => contents,
         Err(e) if e.kind() == io::ErrorKind::NotFound => {
@@ -407,46 +475,40 @@ fn load_app_from_file(path: PathBuf) -> Result<App, Box<dyn Error>> {
         Err(e) => return Err(Box::new(e)),
     };
 
-    let mut app = App::default();
-
-    // Clear the default empty line if we have file content
-    if !contents.trim().is_empty() {
-        app.core.text_lines.clear();
-        app.core.results.clear();
-        app.result_animations.clear();
-    }
-
-    // Split the contents into lines and load them into the app
-    for line in contents.lines() {
-        app.core.text_lines.push(line.to_string());
-        app.core.results.push(None);
-        app.result_animations.push(None);
-    }
-
-    // If the file is empty, ensure we have at least one empty line
-    if app.core.text_lines.is_empty() {
-        app.core.text_lines.push(String::new());
-        app.core.results.push(None);
-        app.result_animations.push(None);
-    }
-
-    // Recalculate all lines
-    app.recalculate_all();
-
-    // Set the file path and mark as saved (for existing files) or unsaved (for new files)
-    app.set_file_path(Some(path.clone()));
-
-    // If the file didn't exist, mark it as having unsaved changes so it gets created on save
-    if !path.exists() {
-        app.has_unsaved_changes = true;
+    // An empty file still gets one empty line
+    let lines = contents.lines().map(str::to_string).collect();
+    let mut core = MathypadCore::from_lines(lines);
+    let (project, warning) = match read_project(&project_file_for(&path)) {
+        Ok(project) => (project, None),
+        Err(e) => (None, Some(format!("Could not load project file: {}", e))),
+    };
+    if let Some(project) = &project {
+        project.pin_values(&mut core);
+        core.recalculate_all();
     }
 
-    Ok(app)
+    let buffer = Buffer {
+        core,
+        // If the file didn't exist, mark it as having unsaved changes so it gets created on save
+        has_unsaved_changes: !path.exists(),
+        file_path: Some(path),
+        scroll_offset: 0,
+        project,
+    };
+    Ok((buffer, warning))
 }
 
 /// Handle key events in insert mode
-fn handle_insert_mode(app: &mut App, key: KeyCode) {
+pub fn handle_insert_mode(app: &mut App, key: KeyCode) {
+    // Any key other than Tab ends a completion cycle
+    if key != KeyCode::Tab {
+        app.unit_completion = None;
+    }
+
     match key {
+        KeyCode::Tab => {
+            complete_unit_at_cursor(app);
+        }
         KeyCode::Char(c) => {
             app.insert_char(c);
         }
@@ -468,6 +530,12 @@ fn handle_insert_mode(app: &mut App, key: KeyCode) {
         KeyCode::Right => {
             app.move_cursor_right();
         }
+        KeyCode::Home => {
+            app.move_to_line_start();
+        }
+        KeyCode::End => {
+            app.move_to_line_end();
+        }
         KeyCode::Esc => {
             // Switch to normal mode
             app.mode = Mode::Normal;
@@ -476,6 +544,137 @@ fn handle_insert_mode(app: &mut App, key: KeyCode) {
     }
 }
 
+/// Handle the Ctrl+A/E/U/K line editing keys in insert mode and Ctrl+A/X in normal mode
+///
+/// The keys do nothing while a dialog or the evaluation steps popup is showing.
+pub fn handle_control_key(app: &mut App, c: char) {
+    if app.show_unsaved_dialog
+        || app.show_save_as_dialog
+        || app.show_welcome_dialog
+        || app.steps_popup.is_some()
+    {
+        return;
+    }
+
+    match (&app.mode, c) {
+        (Mode::Insert, 'a') => app.move_to_line_start(),
+        (Mode::Insert, 'e') => app.move_to_line_end(),
+        (Mode::Insert, 'u') => app.delete_to_line_start(),
+        (Mode::Insert, 'k') => app.delete_to_line_end(),
+        (Mode::Normal, 'a') => app.increment_number(1),
+        (Mode::Normal, 'x') => app.increment_number(-1),
+        _ => {}
+    }
+}
+
+/// Find unit names that complete a partially typed unit
+///
+/// Names come from `supported_units`, plus "/s" rates for data and bit units.
+/// Case-sensitive matches are preferred ("Gi" completes to "GiB", not "gib"-style
+/// aliases); if there are none, matching falls back to ignoring case.
+pub fn unit_completion_candidates(partial: &str) -> Vec<String> {
+    if partial.is_empty() {
+        return Vec::new();
+    }
+
+    let mut names = Vec::new();
+    for (unit_type, group) in supported_units() {
+        for name in group {
+            if matches!(unit_type, UnitType::Data | UnitType::Bit) {
+                names.push(format!("{}/s", name));
+            }
+            names.push(name.into_owned());
+        }
+    }
+
+    let mut candidates: Vec<String> = names
+        .iter()
+        .filter(|name| name.starts_with(partial) && name.as_str() != partial)
+        .cloned()
+        .collect();
+    if candidates.is_empty() {
+        let partial = partial.to_lowercase();
+        candidates = names
+            .into_iter()
+            .filter(|name| {
+                name.to_lowercase().starts_with(&partial) && name.to_lowercase() != partial
+            })
+            .collect();
+    }
+
+    candidates.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    candidates.dedup();
+    candidates
+}
+
+/// Complete the unit name before the cursor, cycling through matches on repeated Tabs
+fn complete_unit_at_cursor(app: &mut App) {
+    let line_index = app.core.cursor_line;
+    let chars: Vec<char> = app.core.text_lines[line_index].chars().collect();
+    let cursor_col = app.core.cursor_col.min(chars.len());
+
+    let (start_col, replacement) = if let Some(completion) = app.unit_completion.as_mut() {
+        // Repeated Tab: replace the previous completion with the next candidate
+        completion.index = (completion.index + 1) % completion.candidates.len();
+        (
+            completion.start_col,
+            completion.candidates[completion.index].clone(),
+        )
+    } else {
+        let start_col = chars[..cursor_col]
+            .iter()
+            .rposition(|c| !(c.is_ascii_alphabetic() || *c == '/'))
+            .map_or(0, |i| i + 1);
+        let partial: String = chars[start_col..cursor_col].iter().collect();
+        let candidates = unit_completion_candidates(&partial);
+
+        match candidates.as_slice() {
+            [] => return,
+            [only] => (start_col, only.clone()),
+            [first, ..] => {
+                // Complete the common prefix first, then cycle through the candidates
+                let common = common_prefix(&candidates);
+                if common.chars().count() > partial.chars().count() {
+                    (start_col, common)
+                } else {
+                    let first = first.clone();
+                    app.unit_completion = Some(UnitCompletion {
+                        start_col,
+                        candidates,
+                        index: 0,
+                    });
+                    (start_col, first)
+                }
+            }
+        }
+    };
+
+    let mut new_line: String = chars[..start_col].iter().collect();
+    new_line.push_str(&replacement);
+    new_line.extend(&chars[cursor_col..]);
+    app.core.text_lines[line_index] = new_line;
+    app.core.cursor_col = start_col + replacement.chars().count();
+    app.update_result(line_index);
+    app.has_unsaved_changes = true;
+}
+
+/// Longest prefix shared by all the strings
+fn common_prefix(strings: &[String]) -> String {
+    let Some(first) = strings.first() else {
+        return String::new();
+    };
+    let mut prefix: Vec<char> = first.chars().collect();
+    for string in &strings[1..] {
+        let shared = prefix
+            .iter()
+            .zip(string.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(shared);
+    }
+    prefix.into_iter().collect()
+}
+
 /// Handle key events in normal mode (vim-like)
 pub fn handle_normal_mode(app: &mut App, key: KeyCode) {
     // Check if we have a pending command
@@ -557,15 +756,13 @@ pub fn handle_normal_mode(app: &mut App, key: KeyCode) {
             // Start a 'g' command (for 'gg')
             app.pending_normal_command = Some('g');
         }
-        KeyCode::Char('0') => {
+        KeyCode::Char('0') | KeyCode::Home => {
             // Go to beginning of line
-            app.core.cursor_col = 0;
+            app.move_to_line_start();
         }
-        KeyCode::Char('$') => {
+        KeyCode::Char('$') | KeyCode::End => {
             // Go to end of line
-            if app.core.cursor_line < app.core.text_lines.len() {
-                app.core.cursor_col = app.core.text_lines[app.core.cursor_line].chars().count();
-            }
+            app.move_to_line_end();
         }
         KeyCode::Char('G') => {
             // Go to end of file
@@ -609,15 +806,26 @@ pub fn handle_normal_mode(app: &mut App, key: KeyCode) {
                 .text_lines
                 .insert(app.core.cursor_line, String::new());
             app.core.results.insert(app.core.cursor_line, None);
+            app.core.shift_display_units(app.core.cursor_line, 1);
             app.core.cursor_col = 0;
             app.mode = Mode::Insert;
         }
+        KeyCode::Char('V') => {
+            // Select whole lines, showing the sum of their results
+            app.start_visual_mode();
+        }
         KeyCode::Char(':') => {
             // Enter command mode
             app.mode = Mode::Command;
             app.command_line = ":".to_string();
             app.command_cursor = 1;
         }
+        KeyCode::Char('T') => {
+            // Prompt for a unit to convert the current line's result to
+            app.mode = Mode::Command;
+            app.command_line = ":to ".to_string();
+            app.command_cursor = app.command_line.len();
+        }
         // Allow arrow keys in normal mode too
         KeyCode::Up => {
             app.move_cursor_up();
@@ -635,6 +843,31 @@ pub fn handle_normal_mode(app: &mut App, key: KeyCode) {
     }
 }
 
+/// Handle key events in visual mode (vim-like linewise selection)
+pub fn handle_visual_mode(app: &mut App, key: KeyCode) {
+    match key {
+        KeyCode::Char('j') | KeyCode::Down => {
+            app.move_cursor_down();
+        }
+        KeyCode::Char('k') | KeyCode::Up => {
+            app.move_cursor_up();
+        }
+        KeyCode::Char('G') => {
+            app.core.cursor_line = app.core.text_lines.len().saturating_sub(1);
+        }
+        KeyCode::Char('V') => {
+            app.mode = Mode::Normal;
+        }
+        KeyCode::Char(':') => {
+            // Enter command mode for the selected lines, like vim
+            app.mode = Mode::Command;
+            app.command_line = ":'<,'>".to_string();
+            app.command_cursor = app.command_line.len();
+        }
+        _ => {}
+    }
+}
+
 /// Handle key events in command mode (vim-like)
 /// Returns true if the application should quit
 pub fn handle_command_mode(app: &mut App, key: KeyCode) -> bool {
@@ -707,22 +940,50 @@ fn execute_command(app: &mut App) -> bool {
     } else {
         return false;
     };
+    // ":'<,'>" (typed by ':' in visual mode) limits a command to the selected lines
+    let (selection, command) = match command.strip_prefix("'<,'>") {
+        Some(rest) => (Some(app.last_selection()), rest),
+        None => (None, command),
+    };
     let parts: Vec<&str> = command.split_whitespace().collect();
 
     if parts.is_empty() {
         return false;
     }
+    if selection.is_some() && !matches!(parts[0], "sort" | "sort!") {
+        app.message = Some(format!(":{} can't be used on a selection", parts[0]));
+        return false;
+    }
 
     match parts[0] {
         "q" | "quit" => {
             // Quit command
-            if app.has_unsaved_changes {
+            if app.focus_unsaved_buffer() {
                 app.show_unsaved_dialog = true;
                 return false; // Don't quit yet, show dialog
             } else {
                 return true; // Signal to quit
             }
         }
+        "e" | "edit" if parts.len() > 1 => {
+            // Open a file in a new buffer: :e budget.pad
+            // A file that's already open is switched to as it is, without reading it again
+            let path = PathBuf::from(parts[1..].join(" "));
+            if !app.switch_to_open_file(&path) {
+                match load_buffer_from_file(path.clone()) {
+                    Ok((buffer, warning)) => {
+                        app.open_buffer(buffer);
+                        if let Some(position) = load_cursor_position(&path) {
+                            app.restore_cursor_position(position);
+                        }
+                        app.message = warning;
+                    }
+                    Err(e) => app.message = Some(format!("Open failed: {}", e)),
+                }
+            }
+        }
+        "bn" | "bnext" => app.next_buffer(),
+        "bp" | "bprevious" => app.previous_buffer(),
         "q!" | "quit!" => {
             // Force quit command - exit without saving, no confirmation
             return true; // Signal to quit immediately
@@ -776,7 +1037,7 @@ fn execute_command(app: &mut App) -> bool {
                 };
 
                 match app.save_as(PathBuf::from(filename)) {
-                    Ok(_) => return true, // Signal to quit
+                    Ok(_) => return quit_after_save(app), // Signal to quit
                     Err(e) => {
                         eprintln!("Save failed: {}", e);
                     }
@@ -785,7 +1046,7 @@ fn execute_command(app: &mut App) -> bool {
                 // Save to current file and quit
                 if app.file_path.is_some() {
                     match app.save() {
-                        Ok(_) => return true, // Signal to quit
+                        Ok(_) => return quit_after_save(app), // Signal to quit
                         Err(e) => {
                             eprintln!("Save failed: {}", e);
                         }
@@ -798,6 +1059,177 @@ fn execute_command(app: &mut App) -> bool {
                 }
             }
         }
+        "addunit" => {
+            // Choose the result unit for addition: :addunit left|smaller|larger
+            if let Some(policy) = parts
+                .get(1)
+                .and_then(|name| AdditionUnitPolicy::from_name(name))
+            {
+                set_addition_unit_policy(policy);
+                app.recalculate_all();
+            }
+        }
+        "assume" => {
+            // Read large bare numbers as byte counts: :assume bytes|none
+            match parts.get(1) {
+                Some(&"bytes") => set_assume_bytes(true),
+                Some(&"none") => set_assume_bytes(false),
+                _ => return false,
+            }
+            app.recalculate_all();
+        }
+        "strict" => {
+            // Only evaluate lines that are entirely an expression: :strict on|off
+            match parts.get(1) {
+                Some(&"on") => set_strict_expressions(true),
+                Some(&"off") => set_strict_expressions(false),
+                _ => return false,
+            }
+            app.recalculate_all();
+        }
+        "decimal" => {
+            // European ("3,14") or default ("3.14") numbers: :decimal comma|point
+            match parts.get(1) {
+                Some(&"comma") => set_decimal_comma(true),
+                Some(&"point") => set_decimal_comma(false),
+                _ => return false,
+            }
+            app.recalculate_all();
+        }
+        "units" => {
+            // Turn a unit family on or off: :units enable|disable <family>
+            let enabled = match parts.get(1) {
+                Some(&"enable") => true,
+                Some(&"disable") => false,
+                _ => return false,
+            };
+            match parts.get(2).and_then(|name| UnitFamily::from_name(name)) {
+                Some(family) => set_unit_family_enabled(family, enabled),
+                None => return false,
+            }
+            app.recalculate_all();
+        }
+        "notation" => {
+            // Write results as grouped digits or with exponents: :notation plain|sci|eng
+            match parts.get(1).and_then(|name| Notation::from_name(name)) {
+                Some(notation) => set_notation(notation),
+                None => return false,
+            }
+            app.recalculate_all();
+        }
+        "to" => {
+            // Convert the current line's result: :to <unit>
+            app.convert_current_line_to(&parts[1..].join(" "));
+        }
+        "unit" => {
+            // Always show the current line's result in a unit: :unit <unit>, or :unit to stop
+            let line = app.core.cursor_line;
+            if parts.len() > 1 {
+                if !app.core.set_display_unit(line, &parts[1..].join(" ")) {
+                    return false;
+                }
+            } else {
+                app.core.clear_display_unit(line);
+            }
+            app.recalculate_all();
+        }
+        "pin" => {
+            // Snapshot the current line's result as a variable: :pin <label>
+            let label = parts.get(1).copied().unwrap_or_default();
+            let line = app.core.cursor_line;
+            if app.core.pin_result(line, label) {
+                app.recalculate_all();
+            }
+        }
+        "unpin" => {
+            // Forget a pinned result: :unpin <label>
+            if let Some(label) = parts.get(1) {
+                app.core.unpin_result(label);
+                app.recalculate_all();
+            }
+        }
+        "steps" => {
+            // Show how the current line evaluates, step by step
+            app.show_steps_for_current_line();
+        }
+        "yankall" => {
+            // Copy every "expression = result" pair to the clipboard
+            if let Err(e) = app.copy_all_results_to_clipboard() {
+                eprintln!("Copy failed: {}", e);
+            }
+        }
+        "sort" | "sort!" => {
+            // Sort lines by their result: :sort smallest first, :sort! largest first
+            app.sort_lines(selection, parts[0] == "sort!");
+        }
+        "theme" => {
+            // Switch syntax highlighting colors: :theme default|high-contrast|monochrome
+            match parts.get(1).and_then(|name| Theme::builtin(name)) {
+                Some(theme) => app.theme = theme,
+                None => return false,
+            }
+        }
+        "recalc" => {
+            // Re-evaluate every line from scratch, in case a result went stale
+            app.core.recompute_all();
+        }
+        "dup" => {
+            // Duplicate the current line below it
+            app.duplicate_line();
+        }
+        "snip" => {
+            // Insert a saved snippet at the cursor: :snip name
+            if let Some(text) = parts.get(1).and_then(|name| load_snippet(name)) {
+                app.insert_text(&text);
+            }
+        }
+        "snipsave" => {
+            // Save the current line as a snippet: :snipsave name
+            if let Some(name) = parts.get(1) {
+                let line = app.core.current_line().trim().to_string();
+                if let Err(e) = save_snippet(name, &line) {
+                    eprintln!("Snippet not saved: {}", e);
+                }
+            }
+        }
+        "fmt" => {
+            // Normalize spacing in the current line, e.g. "5+3*GiB" becomes "5 + 3 * GiB"
+            let line_index = app.core.cursor_line;
+            if let Some(line) = app.core.text_lines.get(line_index) {
+                let formatted = format_expression(line);
+                if formatted != *line {
+                    app.core.cursor_col = app.core.cursor_col.min(formatted.chars().count());
+                    app.core.text_lines[line_index] = formatted;
+                    app.recalculate_all();
+                    app.has_unsaved_changes = true;
+                }
+            }
+        }
+        "type" => {
+            // Show the current result's unit type: :type on|off, or toggle with no argument
+            match parts.get(1) {
+                Some(&"on") => app.show_result_type = true,
+                Some(&"off") => app.show_result_type = false,
+                None => app.show_result_type = !app.show_result_type,
+                Some(_) => {}
+            }
+        }
+        "results" => {
+            // Show or hide the results panel: :results on|off, or toggle with no argument
+            match parts.get(1) {
+                Some(&"on") => app.show_results_panel = true,
+                Some(&"off") => app.show_results_panel = false,
+                None => app.show_results_panel = !app.show_results_panel,
+                Some(_) => {}
+            }
+        }
+        "split" => {
+            // Size the results panel as a percentage of the width: :split 30
+            match parts.get(1).and_then(|width| width.parse().ok()) {
+                Some(width) => app.set_results_width(width),
+                None => return false,
+            }
+        }
         _ => {
             // Unknown command, ignore
         }
@@ -845,10 +1277,7 @@ fn handle_mouse_event(app: &mut App, mouse: MouseEvent, terminal_width: u16) {
 
 /// Handle double-click to copy text or result
 fn handle_double_click_copy(app: &mut App, mouse_x: u16, mouse_y: u16, terminal_width: u16) {
-    use ratatui::{
-        layout::{Constraint, Direction, Layout, Rect},
-        widgets::{Block, Borders},
-    };
+    use ratatui::widgets::{Block, Borders};
 
     // Recreate the same layout calculation as the render function
     let terminal_area = Rect {
@@ -858,22 +1287,13 @@ fn handle_double_click_copy(app: &mut App, mouse_x: u16, mouse_y: u16, terminal_
         height: 50, // Height doesn't matter for our calculation
     };
 
-    let text_percentage = app.separator_position;
-    let results_percentage = 100 - app.separator_position;
-
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(text_percentage),
-            Constraint::Percentage(results_percentage),
-        ])
-        .split(terminal_area);
+    let (_, terminal_area) = split_tab_bar(app, terminal_area);
+    let (text_area, results_area) = split_main_area(app, terminal_area);
 
     // Determine which panel was clicked
-    let (is_results_panel, panel_area) = if mouse_x < chunks[0].x + chunks[0].width {
-        (false, chunks[0])
-    } else {
-        (true, chunks[1])
+    let (is_results_panel, panel_area) = match results_area {
+        Some(results_area) if mouse_x >= text_area.x + text_area.width => (true, results_area),
+        _ => (false, text_area),
     };
 
     // Calculate the inner area (content area) for the clicked panel
@@ -973,7 +1393,7 @@ fn handle_save_as_input(app: &mut App, key: KeyCode) -> bool {
 
             // Save with the entered filename
             match app.save_as_from_dialog() {
-                Ok(should_quit) => should_quit,
+                Ok(should_quit) => should_quit && quit_after_save(app),
                 Err(e) => {
                     eprintln!("Save failed: {}", e);
                     false
@@ -984,34 +1404,37 @@ fn handle_save_as_input(app: &mut App, key: KeyCode) -> bool {
     }
 }
 
+/// Decide whether to quit after saving the active buffer on the way out
+///
+/// If another buffer still has unsaved changes, it is switched to and the unsaved
+/// changes dialog asks about it instead.
+fn quit_after_save(app: &mut App) -> bool {
+    if app.focus_unsaved_buffer() {
+        app.show_unsaved_dialog = true;
+        false
+    } else {
+        true
+    }
+}
+
 /// Handle key events for welcome dialog input (scrolling)
-fn handle_welcome_dialog_input(app: &mut App, key: KeyCode) {
+///
+/// `area` is the terminal area the dialog is drawn in, which bounds the scrolling.
+fn handle_welcome_dialog_input(app: &mut App, key: KeyCode, area: Rect) {
     // Get the changelog content to calculate max scroll
     let changelog_content = crate::version::get_changelog_since_version().unwrap_or_else(|| {
         "Welcome to mathypad!\n\nThis appears to be your first time running this version."
             .to_string()
     });
-
-    // Calculate the total number of lines (header + changelog)
-    let header_line_count = 4; // "Welcome! ...", "", "What's new:", ""
-    let changelog_line_count = changelog_content.lines().count();
-    let total_lines = header_line_count + changelog_line_count;
-
-    // Calculate scrollable height (matches calculation in render.rs)
-    // Dialog height is 25, minus 2 for borders, minus 3 for footer = 20 lines of scrollable content
-    let dialog_height: usize = 25;
-    let inner_height = dialog_height.saturating_sub(2); // Remove borders
-    let footer_height = 3; // Empty line + instructions + scroll indicator
-    let scrollable_height = inner_height.saturating_sub(footer_height);
-    let max_scroll = total_lines.saturating_sub(scrollable_height);
+    let (scrollable_height, max_scroll) = welcome_scroll_bounds(area, &changelog_content);
 
     match key {
-        KeyCode::Up => {
+        KeyCode::Up | KeyCode::Char('k') => {
             if app.welcome_scroll_offset > 0 {
                 app.welcome_scroll_offset -= 1;
             }
         }
-        KeyCode::Down => {
+        KeyCode::Down | KeyCode::Char('j') => {
             if app.welcome_scroll_offset < max_scroll {
                 app.welcome_scroll_offset += 1;
             }
@@ -9,6 +9,8 @@ mod render;
 mod tests;
 
 pub use events::{
-    handle_command_mode, handle_normal_mode, run_interactive_mode, run_interactive_mode_with_file,
+    handle_command_mode, handle_control_key, handle_insert_mode, handle_normal_mode,
+    handle_visual_mode, run_interactive_mode, run_interactive_mode_with_file,
+    unit_completion_candidates,
 };
 pub use render::{parse_colors, render_results_panel, render_text_area, ui};
@@ -1,19 +1,22 @@
 //! UI rendering functions
 
+use crate::version::tidy_changelog;
 use crate::{App, Mode};
 use mathypad_core::core::highlighting::{HighlightType, highlight_expression};
+use mathypad_core::core::{LineReferenceStatus, ResultStyle, Theme};
+use mathypad_core::expression::{EvaluationError, parse_line_reference};
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
 };
 use std::collections::HashMap;
 
-/// Convert a HighlightType to a ratatui Color using shared color system
-fn highlight_type_to_color(highlight_type: &HighlightType) -> Color {
-    let (r, g, b) = highlight_type.rgb_color();
+/// Convert a HighlightType to a ratatui Color using the theme's colors
+fn highlight_type_to_color(theme: &Theme, highlight_type: &HighlightType) -> Color {
+    let (r, g, b) = theme.color(highlight_type);
     Color::Rgb(r, g, b)
 }
 
@@ -58,6 +61,11 @@ fn create_flash_color(opacity: f32) -> Color {
 
 /// Main UI layout and rendering
 pub fn ui(f: &mut Frame, app: &App) {
+    let (tab_bar_area, area) = split_tab_bar(app, f.area());
+    if let Some(tab_bar_area) = tab_bar_area {
+        render_tab_bar(f, app, tab_bar_area);
+    }
+
     // Check if we need to reserve space for command line
     let main_area = if app.mode == Mode::Command {
         // Reserve one line at the bottom for command line
@@ -67,32 +75,36 @@ pub fn ui(f: &mut Frame, app: &App) {
                 Constraint::Min(0),    // Main content area
                 Constraint::Length(1), // Command line
             ])
-            .split(f.area());
+            .split(area);
 
         // Render command line first
         render_command_line(f, app, vertical_chunks[1]);
 
         vertical_chunks[0] // Use the main content area
+    } else if let Some(message) = &app.message {
+        // Show the latest error or warning where the command line was
+        let vertical_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        let message = Paragraph::new(message.as_str())
+            .style(Style::default().bg(Color::Black).fg(Color::Red));
+        f.render_widget(message, vertical_chunks[1]);
+
+        vertical_chunks[0]
     } else {
-        f.area() // Use the full area
+        area // Use the full area
     };
 
-    let text_percentage = app.separator_position;
-    let results_percentage = 100 - app.separator_position;
-
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(text_percentage),
-            Constraint::Percentage(results_percentage),
-        ])
-        .split(main_area);
+    let (text_area, results_area) = split_main_area(app, main_area);
 
-    render_text_area(f, app, chunks[0]);
-    render_results_panel(f, app, chunks[1]);
+    render_text_area(f, app, text_area);
+    if let Some(results_area) = results_area {
+        render_results_panel(f, app, results_area);
+    }
 
     // Render separator visual feedback if hovering or dragging
-    if app.is_dragging_separator || app.is_hovering_separator {
+    if results_area.is_some() && (app.is_dragging_separator || app.is_hovering_separator) {
         render_separator_indicator(f, app, f.area());
     }
 
@@ -103,7 +115,57 @@ pub fn ui(f: &mut Frame, app: &App) {
         render_unsaved_dialog(f, app, f.area());
     } else if app.show_save_as_dialog {
         render_save_as_dialog(f, app, f.area());
+    } else if let Some(steps) = &app.steps_popup {
+        render_steps_dialog(f, steps, f.area());
+    }
+}
+
+/// Take a row off the top of the area for the tab bar, if more than one buffer is open
+pub fn split_tab_bar(app: &App, area: Rect) -> (Option<Rect>, Rect) {
+    if app.buffers.len() < 2 {
+        return (None, area);
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(area);
+    (Some(chunks[0]), chunks[1])
+}
+
+/// Render the open buffers as tabs, highlighting the active one
+pub fn render_tab_bar(f: &mut Frame, app: &App, area: Rect) {
+    let mut spans = Vec::new();
+    for (index, label) in app.buffer_labels().into_iter().enumerate() {
+        let style = if index == app.active_buffer {
+            Style::default().fg(Color::Black).bg(Color::White)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        spans.push(Span::styled(format!(" {}: {} ", index + 1, label), style));
+        spans.push(Span::raw(" "));
     }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Split the main area into the text area and, unless it is hidden, the results panel
+pub fn split_main_area(app: &App, area: Rect) -> (Rect, Option<Rect>) {
+    if !app.show_results_panel {
+        return (area, None);
+    }
+
+    let text_percentage = app.separator_position;
+    let results_percentage = 100 - app.separator_position;
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(text_percentage),
+            Constraint::Percentage(results_percentage),
+        ])
+        .split(area);
+
+    (chunks[0], Some(chunks[1]))
 }
 
 /// Render the main text editing area
@@ -123,6 +185,21 @@ pub fn render_text_area(f: &mut Frame, app: &App, area: Rect) {
             .title(title)
             .borders(Borders::ALL)
             .title_bottom(" COMMAND "),
+        Mode::Visual => {
+            // Show the sum and average of the selected results as they change
+            let status = match app.selection_summary() {
+                Some(summary) => format!(
+                    " VISUAL  sum {}  avg {} ",
+                    summary.sum.format(),
+                    summary.average.format()
+                ),
+                None => " VISUAL ".to_string(),
+            };
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .title_bottom(status)
+        }
     };
 
     let inner_area = block.inner(area);
@@ -132,6 +209,8 @@ pub fn render_text_area(f: &mut Frame, app: &App, area: Rect) {
     let start_line = app.scroll_offset;
     let end_line = (start_line + visible_height).min(app.core.text_lines.len());
 
+    let selected_lines = app.selected_lines();
+
     let mut lines = Vec::new();
     for (i, line_text) in app.core.text_lines[start_line..end_line].iter().enumerate() {
         let line_num = start_line + i + 1;
@@ -157,26 +236,60 @@ pub fn render_text_area(f: &mut Frame, app: &App, area: Rect) {
             Style::default()
         };
 
-        if start_line + i == app.core.cursor_line {
-            // Parse with cursor highlighting
-            let mut colored_spans =
-                parse_colors_with_cursor(line_text, app.core.cursor_col, &app.core.variables);
-            // Apply flash background to all spans if flashing
-            if line_style.bg.is_some() {
-                for span in &mut colored_spans {
-                    span.style = span.style.patch(line_style);
-                }
-            }
-            spans.extend(colored_spans);
+        // Shade the lines selected in visual mode
+        let is_selected =
+            selected_lines.is_some_and(|(first, last)| (first..=last).contains(&line_index));
+        let line_style = if is_selected && line_style.bg.is_none() {
+            line_style.bg(Color::DarkGray)
         } else {
-            let mut colored_spans = parse_colors(line_text, &app.core.variables);
-            // Apply flash background to all spans if flashing
-            if line_style.bg.is_some() {
-                for span in &mut colored_spans {
-                    span.style = span.style.patch(line_style);
-                }
+            line_style
+        };
+
+        // Point at the parenthesis that leaves the line unbalanced
+        let unbalanced_col = match app.core.line_error(line_index) {
+            Some(EvaluationError::UnbalancedParenthesis(mismatch)) => {
+                Some(line_text[..mismatch.offset()].chars().count())
+            }
+            _ => None,
+        };
+
+        // Dim references to lines that are missing, below or without a result
+        let resolves = |reference: &str| {
+            parse_line_reference(reference.trim_end_matches(".value")).is_none_or(|target| {
+                app.core.line_reference_status(line_index, target) == LineReferenceStatus::Resolved
+            })
+        };
+        let cursor_col = (line_index == app.core.cursor_line).then_some(app.core.cursor_col);
+        let mut colored_spans = parse_colors_with_cursor(
+            line_text,
+            cursor_col,
+            &app.core.variables,
+            &app.theme,
+            &resolves,
+        );
+        // Apply flash background to all spans if flashing
+        if line_style.bg.is_some() {
+            for span in &mut colored_spans {
+                span.style = span.style.patch(line_style);
+            }
+        }
+        match unbalanced_col {
+            Some(col) => spans.extend(mark_char(
+                colored_spans,
+                col,
+                Style::default().fg(Color::White).bg(Color::Red),
+            )),
+            None => spans.extend(colored_spans),
+        }
+
+        // With the results panel hidden, show results inline after a trailing "="
+        if !app.show_results_panel && line_text.trim_end().ends_with('=') {
+            if let Some(Some(result)) = app.core.results.get(line_index) {
+                spans.push(Span::styled(
+                    format!(" {}", result),
+                    Style::default().fg(Color::Green),
+                ));
             }
-            spans.extend(colored_spans);
         }
 
         lines.push(Line::from(spans));
@@ -188,7 +301,12 @@ pub fn render_text_area(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render the results panel
 pub fn render_results_panel(f: &mut Frame, app: &App, area: Rect) {
-    let block = Block::default().title("Results").borders(Borders::ALL);
+    let mut block = Block::default().title("Results").borders(Borders::ALL);
+    if app.show_result_type {
+        if let Some(result_type) = app.current_result_type() {
+            block = block.title_bottom(format!(" {} ", result_type));
+        }
+    }
 
     let inner_area = block.inner(area);
     f.render_widget(block, area);
@@ -198,10 +316,8 @@ pub fn render_results_panel(f: &mut Frame, app: &App, area: Rect) {
     let end_line = (start_line + visible_height).min(app.core.results.len());
 
     let mut lines = Vec::new();
-    for (i, result) in app.core.results[start_line..end_line].iter().enumerate() {
-        let line_num = start_line + i + 1;
-        let line_num_str = format!("{:4} ", line_num);
-        let line_index = start_line + i;
+    for line_index in start_line..end_line {
+        let line_num_str = format!("{:4} ", line_index + 1);
 
         let mut spans = vec![Span::styled(
             line_num_str,
@@ -221,23 +337,36 @@ pub fn render_results_panel(f: &mut Frame, app: &App, area: Rect) {
             None
         };
 
-        if let Some(value) = result {
-            // Get animation state for this line
-            let color = if let Some(animation) = app.get_result_animation(line_index) {
-                // Apply fade-in animation by adjusting color intensity
-                let opacity = animation.opacity();
-                animate_color(Color::Green, opacity)
-            } else {
-                Color::Green
-            };
+        match app.core.rendered_result(line_index) {
+            Some(rendered) if rendered.style == ResultStyle::Value => {
+                // Get animation state for this line
+                let color = if let Some(animation) = app.get_result_animation(line_index) {
+                    // Apply fade-in animation by adjusting color intensity
+                    let opacity = animation.opacity();
+                    animate_color(Color::Green, opacity)
+                } else {
+                    Color::Green
+                };
 
-            let mut result_style = Style::default().fg(color);
-            // Apply flash background if flashing
-            if let Some(flash) = flash_style {
-                result_style = result_style.patch(flash);
-            }
+                let mut result_style = Style::default().fg(color);
+                // Apply flash background if flashing
+                if let Some(flash) = flash_style {
+                    result_style = result_style.patch(flash);
+                }
 
-            spans.push(Span::styled(value.clone(), result_style));
+                spans.push(Span::styled(rendered.text, result_style));
+            }
+            Some(rendered) if rendered.style == ResultStyle::Error => {
+                spans.push(Span::styled(rendered.text, Style::default().fg(Color::Red)));
+            }
+            // Hint at a likely typo in a unit name instead of showing nothing
+            Some(rendered) => {
+                spans.push(Span::styled(
+                    rendered.text,
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            None => {}
         }
 
         lines.push(Line::from(spans));
@@ -247,84 +376,102 @@ pub fn render_results_panel(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(paragraph, inner_area);
 }
 
-/// Parse text and return colored spans for syntax highlighting using shared logic
-pub fn parse_colors<'a>(text: &'a str, variables: &'a HashMap<String, String>) -> Vec<Span<'a>> {
-    let highlighted_spans = highlight_expression(text, variables);
-
-    highlighted_spans
-        .into_iter()
-        .map(|span| {
-            let color = highlight_type_to_color(&span.highlight_type);
-            if color == Color::Reset {
-                Span::raw(span.text)
-            } else {
-                Span::styled(span.text, Style::default().fg(color))
+/// Give the character at `col` an extra style, splitting the span it falls in
+fn mark_char(spans: Vec<Span<'_>>, col: usize, style: Style) -> Vec<Span<'_>> {
+    let mut marked = Vec::with_capacity(spans.len() + 2);
+    let mut start = 0;
+    for span in spans {
+        let len = span.content.chars().count();
+        if (start..start + len).contains(&col) {
+            let offset = col - start;
+            let chars: Vec<char> = span.content.chars().collect();
+            let piece = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+            if offset > 0 {
+                marked.push(Span::styled(piece(0..offset), span.style));
             }
-        })
-        .collect()
+            marked.push(Span::styled(
+                piece(offset..offset + 1),
+                span.style.patch(style),
+            ));
+            if offset + 1 < len {
+                marked.push(Span::styled(piece(offset + 1..len), span.style));
+            }
+        } else {
+            marked.push(span);
+        }
+        start += len;
+    }
+    marked
 }
 
-/// Parse text and return colored spans with cursor highlighting using shared logic
+/// Parse text and return colored spans for syntax highlighting using shared logic
+pub fn parse_colors<'a>(
+    text: &'a str,
+    variables: &'a HashMap<String, String>,
+    theme: &Theme,
+) -> Vec<Span<'a>> {
+    parse_colors_with_cursor(text, None, variables, theme, &|_| true)
+}
+
+/// Parse text and return colored spans, highlighting the character under the cursor
+///
+/// `resolves` tells whether a line reference like "line3" has a value to use, and
+/// references that don't are dimmed red so they stand out.
 pub fn parse_colors_with_cursor<'a>(
     text: &'a str,
-    cursor_col: usize,
+    cursor_col: Option<usize>,
     variables: &'a HashMap<String, String>,
+    theme: &Theme,
+    resolves: &dyn Fn(&str) -> bool,
 ) -> Vec<Span<'a>> {
     let highlighted_spans = highlight_expression(text, variables);
     let mut spans = Vec::new();
     let mut char_index = 0; // Track character position for cursor
+    let cursor_style = Style::default().bg(Color::White).fg(Color::Black);
 
     for highlighted_span in highlighted_spans {
         let span_text = highlighted_span.text;
         let span_start = char_index;
         let span_end = char_index + span_text.chars().count();
-        let base_color = highlight_type_to_color(&highlighted_span.highlight_type);
+        let style = if highlighted_span.highlight_type == HighlightType::LineReference
+            && !resolves(&span_text)
+        {
+            Style::default().fg(Color::Red).add_modifier(Modifier::DIM)
+        } else {
+            Style::default().fg(highlight_type_to_color(
+                theme,
+                &highlighted_span.highlight_type,
+            ))
+        };
 
-        // Check if cursor is within this span
-        if cursor_col >= span_start && cursor_col < span_end {
+        match cursor_col {
             // Split the span to highlight the cursor character
-            let cursor_offset = cursor_col - span_start;
-            let span_chars: Vec<char> = span_text.chars().collect();
+            Some(col) if (span_start..span_end).contains(&col) => {
+                let cursor_offset = col - span_start;
+                let span_chars: Vec<char> = span_text.chars().collect();
 
-            if cursor_offset > 0 {
-                let before: String = span_chars[..cursor_offset].iter().collect();
-                if base_color == Color::Reset {
-                    spans.push(Span::raw(before));
-                } else {
-                    spans.push(Span::styled(before, Style::default().fg(base_color)));
+                if cursor_offset > 0 {
+                    let before: String = span_chars[..cursor_offset].iter().collect();
+                    spans.push(Span::styled(before, style));
                 }
-            }
-
-            let cursor_char = span_chars[cursor_offset];
-            let cursor_style = Style::default().bg(Color::White).fg(Color::Black);
-            spans.push(Span::styled(cursor_char.to_string(), cursor_style));
-
-            if cursor_offset + 1 < span_chars.len() {
-                let after: String = span_chars[cursor_offset + 1..].iter().collect();
-                if base_color == Color::Reset {
-                    spans.push(Span::raw(after));
-                } else {
-                    spans.push(Span::styled(after, Style::default().fg(base_color)));
+                spans.push(Span::styled(
+                    span_chars[cursor_offset].to_string(),
+                    cursor_style,
+                ));
+                if cursor_offset + 1 < span_chars.len() {
+                    let after: String = span_chars[cursor_offset + 1..].iter().collect();
+                    spans.push(Span::styled(after, style));
                 }
             }
-        } else {
-            // Normal span without cursor
-            if base_color == Color::Reset {
-                spans.push(Span::raw(span_text));
-            } else {
-                spans.push(Span::styled(span_text, Style::default().fg(base_color)));
-            }
+            _ => spans.push(Span::styled(span_text, style)),
         }
 
         char_index = span_end;
     }
 
     // Handle cursor at end of line
-    if cursor_col >= char_index {
-        spans.push(Span::styled(
-            " ",
-            Style::default().bg(Color::White).fg(Color::Black),
-        ));
+    if cursor_col.is_some_and(|col| col >= char_index) {
+        spans.push(Span::styled(" ", cursor_style));
     }
 
     spans
@@ -398,28 +545,67 @@ pub fn render_unsaved_dialog(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(paragraph, dialog_area);
 }
 
+/// Render the popup listing how an expression evaluates (from :steps)
+pub fn render_steps_dialog(f: &mut Frame, steps: &[String], area: Rect) {
+    use ratatui::widgets::Clear;
+
+    // Size the dialog to fit the steps, within the terminal
+    let content_width = steps.iter().map(|s| s.chars().count()).max().unwrap_or(0) as u16;
+    let dialog_width = (content_width + 4).clamp(40, area.width);
+    let dialog_height = (steps.len() as u16 + 4).min(area.height);
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = Rect {
+        x: area.x + x,
+        y: area.y + y,
+        width: dialog_width,
+        height: dialog_height,
+    };
+
+    // Clear the background for the dialog
+    f.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Steps ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::DarkGray).fg(Color::White));
+
+    let mut lines: Vec<Line> = steps
+        .iter()
+        .map(|step| {
+            Line::from(Span::styled(
+                step.clone(),
+                Style::default().fg(Color::White),
+            ))
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Press any key to close",
+        Style::default().fg(Color::Cyan),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(paragraph, dialog_area);
+}
+
 /// Render a visual indicator for the separator when dragging
 pub fn render_separator_indicator(f: &mut Frame, app: &App, area: Rect) {
     // Calculate the layout split to get the exact separator position
-    let text_percentage = app.separator_position;
-    let results_percentage = 100 - app.separator_position;
-
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(text_percentage),
-            Constraint::Percentage(results_percentage),
-        ])
-        .split(area);
+    let (text_area, _) = split_main_area(app, area);
 
     // The separator should be at the boundary between the two panels
     // We want to draw it exactly where the new layout boundary will be
-    let separator_x = chunks[0].x + chunks[0].width;
+    let separator_x = text_area.x + text_area.width;
 
     // Calculate the inner area (excluding borders) to determine where to draw the line
     // Both panels have the same border structure, so we only need to calculate one
     let panel_block = Block::default().borders(Borders::ALL);
-    let inner_area = panel_block.inner(chunks[0]);
+    let inner_area = panel_block.inner(text_area);
 
     // Use the inner area to determine the vertical bounds for the separator line
     // Extend one character up and down to cover the border corners for a cleaner look
@@ -532,6 +718,40 @@ pub fn render_welcome_dialog(f: &mut Frame, app: &App, area: Rect) {
     )
 }
 
+/// Lines above the changelog in the welcome screen: greeting, blank, "What's new:", blank
+const WELCOME_HEADER_LINES: usize = 4;
+
+/// Lines below the scrollable changelog: blank, key help and scroll position
+const WELCOME_FOOTER_LINES: usize = 3;
+
+/// Where the welcome screen is drawn within the terminal area
+fn welcome_dialog_area(area: Rect) -> Rect {
+    // Larger than other dialogs for changelog content
+    let dialog_width = 100.min(area.width.saturating_sub(4));
+    let dialog_height = 25.min(area.height.saturating_sub(4));
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    Rect {
+        x: area.x + x,
+        y: area.y + y,
+        width: dialog_width,
+        height: dialog_height,
+    }
+}
+
+/// Rows of the welcome screen that scroll, and the largest scroll offset, for the
+/// terminal area the screen is drawn in
+pub fn welcome_scroll_bounds(area: Rect, changelog_content: &str) -> (usize, usize) {
+    let inner_height = welcome_dialog_area(area).height.saturating_sub(2) as usize; // Borders
+    let scrollable_height = inner_height.saturating_sub(WELCOME_FOOTER_LINES);
+    let total_lines = WELCOME_HEADER_LINES + tidy_changelog(changelog_content).lines().count();
+    (
+        scrollable_height,
+        total_lines.saturating_sub(scrollable_height),
+    )
+}
+
 /// Render the welcome screen dialog with specific content (for testing)
 pub fn render_welcome_dialog_with_content(
     f: &mut Frame,
@@ -543,18 +763,7 @@ pub fn render_welcome_dialog_with_content(
 ) {
     use ratatui::widgets::Clear;
 
-    // Calculate dialog size and position (larger than other dialogs for changelog content)
-    let dialog_width = 100.min(area.width.saturating_sub(4));
-    let dialog_height = 25.min(area.height.saturating_sub(4));
-    let x = (area.width.saturating_sub(dialog_width)) / 2;
-    let y = (area.height.saturating_sub(dialog_height)) / 2;
-
-    let dialog_area = Rect {
-        x: area.x + x,
-        y: area.y + y,
-        width: dialog_width,
-        height: dialog_height,
-    };
+    let dialog_area = welcome_dialog_area(area);
 
     // Clear the background
     f.render_widget(Clear, dialog_area);
@@ -603,19 +812,32 @@ pub fn render_welcome_dialog_with_content(
         ]
     };
 
-    // Split changelog into lines and apply scroll offset
+    // Split changelog into lines, dropping the markdown syntax, and apply scroll offset
+    let changelog_content = tidy_changelog(changelog_content);
     let changelog_lines: Vec<Line> = changelog_content
         .lines()
         .map(|line| {
-            if line.starts_with("## ") {
-                // Version headers in bright yellow
-                Line::from(Span::styled(line, Style::default().fg(Color::Yellow)))
-            } else if line.starts_with("### ") {
+            if let Some(header) = line.strip_prefix("## ") {
+                // Version headers ("## [0.1.9] - 2025-06-19") in bold yellow, as "v0.1.9 - 2025-06-19"
+                let header = header.replacen('[', "v", 1).replacen(']', "", 1);
+                Line::from(Span::styled(
+                    header,
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ))
+            } else if let Some(section) = line.strip_prefix("### ") {
                 // Section headers in cyan
-                Line::from(Span::styled(line, Style::default().fg(Color::Cyan)))
-            } else if line.starts_with("- ") {
+                Line::from(Span::styled(
+                    format!("  {}", section),
+                    Style::default().fg(Color::Cyan),
+                ))
+            } else if let Some(item) = line.strip_prefix("- ") {
                 // Bullet points in green
-                Line::from(Span::styled(line, Style::default().fg(Color::Green)))
+                Line::from(Span::styled(
+                    format!("    • {}", item),
+                    Style::default().fg(Color::Green),
+                ))
             } else {
                 // Regular text
                 Line::from(Span::styled(line, Style::default().fg(Color::White)))
@@ -629,13 +851,11 @@ pub fn render_welcome_dialog_with_content(
 
     // Calculate layout: reserve space for footer (3 lines: empty, instructions, scroll indicator)
     let inner_area = block.inner(dialog_area);
-    let footer_height = 3; // Empty line + instructions + scroll indicator
-    let content_height = inner_area.height as usize;
-    let scrollable_height = content_height.saturating_sub(footer_height);
+    let footer_height = WELCOME_FOOTER_LINES;
+    let (scrollable_height, max_scroll) = welcome_scroll_bounds(area, &changelog_content);
 
     // Apply scroll offset
     let total_lines = all_lines.len();
-    let max_scroll = total_lines.saturating_sub(scrollable_height);
     let scroll_offset = app.welcome_scroll_offset.min(max_scroll);
 
     let visible_lines: Vec<Line> = all_lines
@@ -673,6 +893,8 @@ pub fn render_welcome_dialog_with_content(
         Line::from(vec![
             Span::styled("↑↓", Style::default().fg(Color::Yellow)),
             Span::styled(" scroll  ", Style::default().fg(Color::White)),
+            Span::styled("PgUp/PgDn", Style::default().fg(Color::Yellow)),
+            Span::styled(" page  ", Style::default().fg(Color::White)),
             Span::styled("Enter", Style::default().fg(Color::Green)),
             Span::styled(" or ", Style::default().fg(Color::White)),
             Span::styled("Esc", Style::default().fg(Color::Red)),
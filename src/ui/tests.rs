@@ -1,7 +1,7 @@
 //! UI snapshot tests using insta and ratatui TestBackend
 
 use super::*;
-use crate::ui::render::render_welcome_dialog_with_content;
+use crate::ui::render::{render_welcome_dialog_with_content, split_main_area};
 use crate::{App, Mode};
 use insta::assert_snapshot;
 use ratatui::{Terminal, backend::TestBackend};
@@ -308,6 +308,17 @@ fn test_empty_results() {
     assert_snapshot!("empty_results", output);
 }
 
+#[test]
+fn test_unit_suggestion_in_results() {
+    let mut app = App::default();
+    app.core.text_lines = vec!["1 gigbyte + 5 GB".to_string(), "5 + 3".to_string()];
+    app.core.results = vec![None, Some("8".to_string())];
+
+    let output = render_app_to_string(&app);
+    assert!(output.contains("did you mean GB?"));
+    assert_snapshot!("unit_suggestion_in_results", output);
+}
+
 #[test]
 fn test_unsaved_dialog() {
     let mut app = create_sample_app();
@@ -449,3 +460,225 @@ fn test_command_mode_rendering() {
     let output = render_app_to_string(&app);
     assert_snapshot!("command_mode_rendering", output);
 }
+
+#[test]
+fn test_split_main_area_with_results_panel_shown_and_hidden() {
+    use ratatui::layout::Rect;
+
+    let area = Rect::new(0, 0, 100, 30);
+    let mut app = App::default();
+
+    let (text_area, results_area) = split_main_area(&app, area);
+    assert_eq!(text_area, Rect::new(0, 0, 80, 30));
+    assert_eq!(results_area, Some(Rect::new(80, 0, 20, 30)));
+
+    app.show_results_panel = false;
+    let (text_area, results_area) = split_main_area(&app, area);
+    assert_eq!(text_area, area);
+    assert_eq!(results_area, None);
+}
+
+#[test]
+fn test_results_panel_hidden() {
+    let mut app = create_sample_app();
+    app.core.text_lines[0] = "5 + 3 =".to_string();
+    app.show_results_panel = false;
+    let output = render_app_to_string(&app);
+    assert_snapshot!("results_panel_hidden", output);
+}
+
+#[test]
+fn test_steps_popup() {
+    let mut app = App::default();
+    app.core.text_lines = vec!["(1 GiB + 1 GiB) / 2 to MiB".to_string()];
+    app.recalculate_all();
+    app.show_steps_for_current_line();
+    assert_eq!(
+        app.steps_popup.as_deref(),
+        Some(
+            &[
+                "Tokens: ( 1 GiB + 1 GiB ) / 2 to MiB".to_string(),
+                "1 GiB + 1 GiB = 2 GiB".to_string(),
+                "2 GiB / 2 = 1 GiB".to_string(),
+                "1 GiB to MiB = 1,024 MiB".to_string(),
+                "Result: 1,024 MiB".to_string(),
+            ][..]
+        )
+    );
+
+    let output = render_app_to_string(&app);
+    assert_snapshot!("steps_popup", output);
+}
+
+#[test]
+fn test_result_type_in_results_panel() {
+    let mut app = App::default();
+    app.core.text_lines = vec!["10 GiB / 2 s".to_string(), "5 + 3".to_string()];
+    app.recalculate_all();
+
+    // Only shown on demand
+    assert!(!render_app_to_string(&app).contains("DataRate"));
+
+    app.show_result_type = true;
+    assert_eq!(
+        app.current_result_type(),
+        Some("DataRate: GiB/s".to_string())
+    );
+    let output = render_app_to_string(&app);
+    assert_snapshot!("result_type_in_results_panel", output);
+
+    app.core.cursor_line = 1;
+    assert_eq!(app.current_result_type(), Some("Dimensionless".to_string()));
+}
+
+#[test]
+fn test_highlighting_uses_theme_colors() {
+    use mathypad_core::core::Theme;
+    use ratatui::style::Color;
+    use std::collections::HashMap;
+
+    let variables = HashMap::new();
+    let color_of = |spans: &[ratatui::text::Span], text: &str| {
+        spans
+            .iter()
+            .find(|span| span.content == text)
+            .and_then(|span| span.style.fg)
+    };
+
+    let theme = Theme::high_contrast();
+    let spans = parse_colors("5 GiB to MiB", &variables, &theme);
+    assert_eq!(color_of(&spans, "5"), Some(Color::Rgb(0, 191, 255)));
+    assert_eq!(color_of(&spans, "GiB"), Some(Color::Rgb(0, 255, 127)));
+    assert_eq!(color_of(&spans, "to"), Some(Color::Rgb(255, 85, 85)));
+
+    let theme = Theme::from_config("theme = monochrome\nnumber = #102030").unwrap();
+    let spans = parse_colors("line1 + 5", &variables, &theme);
+    assert_eq!(color_of(&spans, "5"), Some(Color::Rgb(16, 32, 48)));
+    assert_eq!(color_of(&spans, "line1"), Some(Color::Rgb(255, 255, 255)));
+}
+
+#[test]
+fn test_visual_mode_selection_sum() {
+    use crossterm::event::KeyCode;
+
+    let mut app = App::default();
+    app.core.text_lines = vec![
+        "Disks".to_string(),
+        "1 GiB".to_string(),
+        "512 MiB".to_string(),
+        "1.5 GiB".to_string(),
+    ];
+    app.recalculate_all();
+    app.mode = Mode::Normal;
+    app.core.cursor_line = 1;
+
+    handle_normal_mode(&mut app, KeyCode::Char('V'));
+    assert_eq!(app.mode, Mode::Visual);
+    handle_visual_mode(&mut app, KeyCode::Char('j'));
+    handle_visual_mode(&mut app, KeyCode::Char('j'));
+    assert_eq!(app.selected_lines(), Some((1, 3)));
+
+    let output = render_app_to_string(&app);
+    assert_snapshot!("visual_mode_selection_sum", output);
+
+    handle_visual_mode(&mut app, KeyCode::Char('V'));
+    assert_eq!(app.mode, Mode::Normal);
+    assert!(!render_app_to_string(&app).contains("sum"));
+}
+
+#[test]
+fn test_failed_unit_assertion_is_flagged() {
+    let mut app = App::default();
+    app.core.text_lines = vec![
+        "1 GiB".to_string(),
+        "line1 * 2 :: GiB".to_string(),
+        "line1 * 2 :: hours".to_string(),
+    ];
+    app.recalculate_all();
+
+    let output = render_app_to_string(&app);
+    assert_snapshot!("failed_unit_assertion", output);
+}
+
+#[test]
+fn test_unbalanced_parenthesis_is_flagged() {
+    let mut app = App::default();
+    app.core.text_lines = vec![
+        "((1 + 2) * 3".to_string(),
+        "(1 + 2) * 3)".to_string(),
+        "(1 + 2) * 3".to_string(),
+    ];
+    app.core.cursor_line = 2;
+    app.recalculate_all();
+
+    let output = render_app_to_string(&app);
+    assert_snapshot!("unbalanced_parenthesis", output);
+}
+
+#[test]
+fn test_tab_bar_with_several_buffers() {
+    use crate::app::Buffer;
+    use mathypad_core::core::MathypadCore;
+    use std::path::PathBuf;
+
+    let mut app = App::default();
+    app.core.text_lines = vec!["5 + 5".to_string()];
+    app.recalculate_all();
+    app.open_buffer(Buffer {
+        core: MathypadCore::from_lines(vec!["1 GiB to MiB".to_string()]),
+        file_path: Some(PathBuf::from("sizes.pad")),
+        has_unsaved_changes: true,
+        ..Buffer::default()
+    });
+
+    let output = render_app_to_string(&app);
+    assert_snapshot!("tab_bar", output);
+}
+
+#[test]
+fn test_dead_line_references_are_dimmed() {
+    use ratatui::style::{Color, Modifier};
+
+    let mut app = App::default();
+    app.core.text_lines = vec![
+        "5 GiB".to_string(),
+        "line1 + line3 + line9".to_string(),
+        "Notes".to_string(),
+    ];
+    app.core.cursor_line = 2;
+    app.recalculate_all();
+
+    let mut terminal = create_test_terminal();
+    terminal.draw(|frame| ui(frame, &app)).unwrap();
+    let buffer = terminal.backend().buffer();
+
+    // Style of the first cell of `text` on the second line of the pad
+    let row = 2;
+    let style_of = |text: &str| {
+        let line: String = (0..buffer.area.width)
+            .map(|x| buffer[(x, row)].symbol())
+            .collect();
+        let x = line.find(text).unwrap() as u16;
+        let cell = &buffer[(x, row)];
+        (cell.fg, cell.modifier.contains(Modifier::DIM))
+    };
+
+    let (line1_color, line1_dim) = style_of("line1");
+    assert_ne!(line1_color, Color::Red);
+    assert!(!line1_dim);
+    assert_eq!(style_of("line3"), (Color::Red, true));
+    assert_eq!(style_of("line9"), (Color::Red, true));
+}
+
+#[test]
+fn test_message_is_shown_on_the_bottom_line() {
+    let mut app = create_sample_app();
+    app.mode = Mode::Normal;
+    app.message = Some("Not sorted: a line would move above a line it uses".to_string());
+
+    let output = render_app_to_string(&app);
+    let last_line = output.lines().last().unwrap();
+    assert!(last_line.contains("Not sorted: a line would move above a line it uses"));
+    // The pad is still shown above it
+    assert!(output.contains("10 kg to lb"));
+}
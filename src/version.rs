@@ -1,6 +1,7 @@
 //! Version tracking functionality for mathypad
 
 use std::fs;
+use std::path::Path;
 
 const VERSION_FILE: &str = "version";
 
@@ -28,10 +29,13 @@ pub fn update_stored_version() -> Result<(), Box<dyn std::error::Error>> {
 /// Get the stored version from ~/.mathypad/version
 pub fn get_stored_version() -> Option<String> {
     let mathypad_dir = get_mathypad_dir().ok()?;
-    let version_file = mathypad_dir.join(VERSION_FILE);
+    read_version_file(&mathypad_dir.join(VERSION_FILE))
+}
 
+/// Read a version file, or None if it doesn't exist or can't be read
+fn read_version_file(version_file: &Path) -> Option<String> {
     if version_file.exists() {
-        fs::read_to_string(&version_file)
+        fs::read_to_string(version_file)
             .ok()?
             .trim()
             .to_string()
@@ -41,6 +45,36 @@ pub fn get_stored_version() -> Option<String> {
     }
 }
 
+/// Describe the current version and the last one seen as JSON, for update checkers
+///
+/// Gives `{"version":"0.1.17","latest_seen":"0.1.16"}`, with `latest_seen` null
+/// before any version has been recorded.
+pub fn version_json(stored_version: Option<&str>) -> String {
+    let latest_seen = match stored_version {
+        Some(stored) => format!("\"{}\"", escape_json(stored)),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"version\":\"{}\",\"latest_seen\":{}}}",
+        escape_json(get_current_version()),
+        latest_seen
+    )
+}
+
+/// Escape a string for use inside a JSON string literal
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Get the current version from Cargo.toml
 pub fn get_current_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
@@ -53,11 +87,17 @@ pub fn is_first_run() -> bool {
 
 /// Check if this is a newer version than what's stored
 pub fn is_newer_version() -> bool {
-    match get_stored_version() {
-        Some(stored) => {
-            let current = get_current_version();
-            version_compare(current, &stored) > 0
-        }
+    should_show_welcome(get_stored_version().as_deref(), get_current_version())
+}
+
+/// Decide whether the welcome screen is shown, given the stored and current versions
+///
+/// It is shown on the first run and once after each upgrade, but not again for a
+/// version that was already seen or after a downgrade. An unreadable stored version
+/// counts as older, so the screen is shown and the file rewritten when dismissed.
+pub fn should_show_welcome(stored_version: Option<&str>, current_version: &str) -> bool {
+    match stored_version {
+        Some(stored) => version_compare(current_version, stored) > 0,
         None => true, // First run counts as newer version
     }
 }
@@ -70,7 +110,7 @@ pub fn get_changelog_since_version() -> Option<String> {
     match get_stored_version() {
         Some(stored_version) => {
             // If versions are the same, no changelog to show
-            if version_compare(current_version, &stored_version) <= 0 {
+            if !should_show_welcome(Some(&stored_version), current_version) {
                 return None;
             }
             extract_changelog_between_versions(&stored_version, current_version)
@@ -82,6 +122,34 @@ pub fn get_changelog_since_version() -> Option<String> {
     }
 }
 
+/// Tidy changelog sections for display in the welcome screen
+///
+/// Sections without any entries (like an empty "### 👤 Artisanally Crafted") are
+/// dropped, and blank lines are only kept to separate one version from the next.
+pub fn tidy_changelog(changelog: &str) -> String {
+    let lines: Vec<&str> = changelog.lines().map(str::trim_end).collect();
+    let mut result: Vec<&str> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let next_content = lines[i + 1..].iter().find(|line| !line.is_empty());
+        if line.is_empty() {
+            let before_version = next_content.is_some_and(|next| next.starts_with("## "));
+            if before_version && result.last().is_some_and(|last| !last.is_empty()) {
+                result.push(line);
+            }
+        } else if line.starts_with("### ") {
+            // A section is empty when the next non-blank line is another header
+            if next_content.is_some_and(|next| !next.starts_with('#')) {
+                result.push(line);
+            }
+        } else {
+            result.push(line);
+        }
+    }
+
+    result.join("\n")
+}
+
 /// Extract the changelog for just the latest version (for first-time users)
 fn extract_latest_version_changelog() -> Option<String> {
     let changelog = include_str!("../CHANGELOG.md");
@@ -177,7 +245,7 @@ fn extract_version_from_header(line: &str) -> Option<String> {
 }
 
 /// Get the ~/.mathypad directory path
-fn get_mathypad_dir() -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+pub(crate) fn get_mathypad_dir() -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
     let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
     Ok(home_dir.join(".mathypad"))
 }
@@ -221,6 +289,65 @@ mod tests {
         assert_eq!(version_compare("1.0", "1.0.0"), 0);
     }
 
+    #[test]
+    fn test_should_show_welcome() {
+        // First run
+        assert!(should_show_welcome(None, "0.1.17"));
+        // Upgrades show it once
+        assert!(should_show_welcome(Some("0.1.16"), "0.1.17"));
+        assert!(should_show_welcome(Some("0.1.17"), "0.2.0"));
+        // The same version was already seen, and downgrades don't show it
+        assert!(!should_show_welcome(Some("0.1.17"), "0.1.17"));
+        assert!(!should_show_welcome(Some("0.1.17.0"), "0.1.17"));
+        assert!(!should_show_welcome(Some("0.2.0"), "0.1.17"));
+        // An unreadable stored version is treated as older
+        assert!(should_show_welcome(Some(""), "0.1.17"));
+    }
+
+    #[test]
+    fn test_version_json() {
+        use tempfile::TempDir;
+
+        let current = get_current_version();
+        let dir = TempDir::new().unwrap();
+        let version_file = dir.path().join(VERSION_FILE);
+
+        // Nothing recorded yet
+        let stored = read_version_file(&version_file);
+        assert_eq!(
+            version_json(stored.as_deref()),
+            format!("{{\"version\":\"{}\",\"latest_seen\":null}}", current)
+        );
+
+        fs::write(&version_file, "0.1.16\n").unwrap();
+        let stored = read_version_file(&version_file);
+        assert_eq!(
+            version_json(stored.as_deref()),
+            format!("{{\"version\":\"{}\",\"latest_seen\":\"0.1.16\"}}", current)
+        );
+
+        // Whatever is in the file stays valid JSON
+        fs::write(&version_file, "0.1\"16\\").unwrap();
+        let stored = read_version_file(&version_file);
+        assert_eq!(
+            version_json(stored.as_deref()),
+            format!(
+                "{{\"version\":\"{}\",\"latest_seen\":\"0.1\\\"16\\\\\"}}",
+                current
+            )
+        );
+    }
+
+    #[test]
+    fn test_tidy_changelog() {
+        let changelog = "## [0.1.16] - 2025-07-14\n\n### 🤖 AI Assisted\n- Fix broken test\n\n\n### 👤 Artisanally Crafted\n\n\n\n### Changes\n- Release scripts\n\n\n## [0.1.15] - 2025-07-12\n\n### 👤 Artisanally Crafted\n\n- Fix cargo publish\n\n\n\n### Empty at the end\n\n";
+        assert_eq!(
+            tidy_changelog(changelog),
+            "## [0.1.16] - 2025-07-14\n### 🤖 AI Assisted\n- Fix broken test\n### Changes\n- Release scripts\n\n## [0.1.15] - 2025-07-12\n### 👤 Artisanally Crafted\n- Fix cargo publish"
+        );
+        assert_eq!(tidy_changelog(""), "");
+    }
+
     #[test]
     fn test_extract_version_from_header() {
         assert_eq!(